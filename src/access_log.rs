@@ -0,0 +1,395 @@
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use regex::Regex;
+use tui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    widgets::{Cell, Row, Table},
+};
+
+use crate::{
+    config::{Config, ConfigError},
+    tail::LogTailer,
+};
+
+/// The format nginx uses when `access_log` names none.
+const COMBINED: &str = r#"$remote_addr - $remote_user [$time_local] "$request" $status $body_bytes_sent "$http_referer" "$http_user_agent""#;
+
+/// How times are compared in filters, so that `time>=2023-08-01T12` keeps
+/// what was logged from noon on.
+pub const TIME_FIELD: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// A `log_format`, compiled to a regex capturing each variable.
+#[derive(Debug, Clone)]
+pub struct LogFormat {
+    pub name: String,
+    regex: Regex,
+    /// The variable captured by each group, in order.
+    variables: Vec<String>,
+}
+
+/// The fields of an access log line that the log view knows about. Fields
+/// the format does not log, or logged as `-`, are `None`.
+#[derive(Debug, Clone, Default)]
+pub struct AccessRecord {
+    pub remote_addr: Option<String>,
+    pub time: Option<DateTime<FixedOffset>>,
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub status: Option<u16>,
+    pub bytes: Option<u64>,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    /// Seconds.
+    pub request_time: Option<f64>,
+    /// Seconds, summed over every upstream tried.
+    pub upstream_response_time: Option<f64>,
+}
+
+impl AccessRecord {
+    /// Names [`AccessRecord::field`] knows, for filters.
+    pub const FIELDS: [&'static str; 14] = [
+        "time",
+        "remote_addr",
+        "remote",
+        "method",
+        "path",
+        "uri",
+        "status",
+        "bytes",
+        "referer",
+        "user_agent",
+        "ua",
+        "request_time",
+        "upstream_response_time",
+        "upstream_time",
+    ];
+
+    /// The field called `name` as text, `None` when it was not logged.
+    pub fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "time" => self.time.map(|time| time.format(TIME_FIELD).to_string()),
+            "remote_addr" | "remote" => self.remote_addr.clone(),
+            "method" => self.method.clone(),
+            "path" | "uri" => self.path.clone(),
+            "status" => self.status.map(|status| status.to_string()),
+            "bytes" => self.bytes.map(|bytes| bytes.to_string()),
+            "referer" => self.referer.clone(),
+            "user_agent" | "ua" => self.user_agent.clone(),
+            "request_time" => self.request_time.map(|time| time.to_string()),
+            "upstream_response_time" | "upstream_time" => {
+                self.upstream_response_time.map(|time| time.to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl LogFormat {
+    /// Compiles the format string of a `log_format` directive. Everything
+    /// between variables must appear literally in the line.
+    pub fn new(name: &str, format: &str) -> Option<Self> {
+        let variable = Regex::new(r"\$(?:\{(\w+)\}|(\w+))").unwrap();
+        let mut pattern = String::from("^");
+        let mut variables = Vec::new();
+        let mut last = 0;
+        for captures in variable.captures_iter(format) {
+            let whole = captures.get(0)?;
+            pattern.push_str(&regex::escape(&format[last..whole.start()]));
+            pattern.push_str("(.*?)");
+            variables.push(captures.get(1).or(captures.get(2))?.as_str().to_string());
+            last = whole.end();
+        }
+        pattern.push_str(&regex::escape(&format[last..]));
+        pattern.push('$');
+        Some(Self {
+            name: name.to_string(),
+            regex: Regex::new(&pattern).ok()?,
+            variables,
+        })
+    }
+
+    pub fn combined() -> Self {
+        Self::new("combined", COMBINED).unwrap()
+    }
+
+    /// `None` when the line was not written with this format.
+    pub fn parse(&self, line: &str) -> Option<AccessRecord> {
+        let captures = self.regex.captures(line)?;
+        let mut record = AccessRecord::default();
+        for (variable, value) in self.variables.iter().zip(captures.iter().skip(1)) {
+            let Some(value) = value.map(|v| v.as_str()).filter(|v| *v != "-") else {
+                continue;
+            };
+            match variable.as_str() {
+                "remote_addr" => record.remote_addr = Some(value.to_string()),
+                "time_local" => {
+                    record.time = DateTime::parse_from_str(value, "%d/%b/%Y:%H:%M:%S %z").ok()
+                }
+                "time_iso8601" => record.time = DateTime::parse_from_rfc3339(value).ok(),
+                "msec" => {
+                    record.time = value
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|secs| Utc.timestamp_millis_opt((secs * 1000.0) as i64).single())
+                        .map(|time| time.fixed_offset())
+                }
+                "request" => {
+                    let mut parts = value.split(' ');
+                    record.method = parts.next().map(str::to_string);
+                    record.path = parts.next().map(str::to_string);
+                }
+                "request_method" => record.method = Some(value.to_string()),
+                "request_uri" => record.path = Some(value.to_string()),
+                "uri" if record.path.is_none() => record.path = Some(value.to_string()),
+                "status" => record.status = value.parse().ok(),
+                "body_bytes_sent" => record.bytes = value.parse().ok(),
+                "bytes_sent" if record.bytes.is_none() => record.bytes = value.parse().ok(),
+                "http_referer" => record.referer = Some(value.to_string()),
+                "http_user_agent" => record.user_agent = Some(value.to_string()),
+                "request_time" => record.request_time = value.parse().ok(),
+                "upstream_response_time" => {
+                    record.upstream_response_time = sum_upstream_times(value)
+                }
+                _ => {}
+            }
+        }
+        Some(record)
+    }
+}
+
+/// `$upstream_response_time` lists one time per upstream tried, separated
+/// by commas, and colons across internal redirects.
+fn sum_upstream_times(value: &str) -> Option<f64> {
+    let times: Vec<f64> = value
+        .split([',', ':'])
+        .filter_map(|time| time.trim().parse().ok())
+        .collect();
+    (!times.is_empty()).then(|| times.iter().sum())
+}
+
+/// The `log_format` called `name`, or the built-in `combined` format.
+pub fn find_log_format(config: &Result<Config, ConfigError>, name: &str) -> Option<LogFormat> {
+    if name == "combined" {
+        return Some(LogFormat::combined());
+    }
+    let mut format = None;
+    if let Ok(config) = config {
+        config.walk(|directive, _| {
+            if directive.name == "log_format" && directive.arg(0) == Some(name) {
+                let parts: String = directive.args[1..]
+                    .iter()
+                    .skip_while(|arg| arg.starts_with("escape="))
+                    .map(String::as_str)
+                    .collect();
+                format = LogFormat::new(name, &parts);
+            }
+        });
+    }
+    format
+}
+
+fn status_color(status: u16) -> Color {
+    match status {
+        200..=299 => Color::Green,
+        300..=399 => Color::Cyan,
+        400..=499 => Color::Yellow,
+        500..=599 => Color::Red,
+        _ => Color::Reset,
+    }
+}
+
+fn seconds(time: Option<f64>) -> String {
+    time.map(|time| format!("{:.3}", time)).unwrap_or_default()
+}
+
+/// The visible lines of an access log parsed with `format`, one column per
+/// field. Lines that do not match the format are shown raw in the path
+/// column, rows matching `search` reversed.
+pub fn access_log_component<'a>(
+    tailer: &'a LogTailer,
+    format: &LogFormat,
+    search: Option<&Regex>,
+) -> Table<'a> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let rows: Vec<_> = tailer
+        .window()
+        .iter()
+        .map(|(number, line)| {
+            let matched = search.is_some_and(|regex| regex.is_match(line));
+            let row_style = if matched {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            let number = Cell::from(number.map(|n| n.to_string()).unwrap_or_default()).style(dim);
+            let Some(record) = format.parse(line) else {
+                return Row::new(vec![
+                    number,
+                    Cell::from(""),
+                    Cell::from(""),
+                    Cell::from(""),
+                    Cell::from(""),
+                    Cell::from(line.as_str()).style(dim),
+                ])
+                .style(row_style);
+            };
+            let status = match record.status {
+                Some(status) => {
+                    Cell::from(status.to_string()).style(Style::default().fg(status_color(status)))
+                }
+                None => Cell::from(""),
+            };
+            Row::new(vec![
+                number,
+                Cell::from(
+                    record
+                        .time
+                        .map(|time| time.format("%d/%b %H:%M:%S").to_string())
+                        .unwrap_or_default(),
+                ),
+                Cell::from(record.remote_addr.unwrap_or_default()),
+                Cell::from(record.method.unwrap_or_default()),
+                status,
+                Cell::from(record.path.unwrap_or_default()),
+                Cell::from(record.bytes.map(|b| b.to_string()).unwrap_or_default()),
+                Cell::from(seconds(record.request_time)),
+                Cell::from(seconds(record.upstream_response_time)),
+                Cell::from(record.referer.unwrap_or_default()),
+                Cell::from(record.user_agent.unwrap_or_default()),
+            ])
+            .style(row_style)
+        })
+        .collect();
+
+    Table::new(rows)
+        .header(
+            Row::new(vec![
+                "Line",
+                "Time",
+                "Remote",
+                "Method",
+                "Status",
+                "Path",
+                "Bytes",
+                "Req time",
+                "Upstream",
+                "Referer",
+                "User agent",
+            ])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .widths(&[
+            Constraint::Length(7),
+            Constraint::Length(15),
+            Constraint::Length(15),
+            Constraint::Length(7),
+            Constraint::Length(6),
+            Constraint::Min(20),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(20),
+            Constraint::Length(30),
+        ])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn parses_the_combined_default() {
+        let missing = Err(ConfigError {
+            path: "/etc/nginx/nginx.conf".into(),
+            line: 0,
+            message: "No such file or directory".to_string(),
+        });
+        let format = find_log_format(&missing, "combined").unwrap();
+        let record = format
+            .parse(
+                r#"203.0.113.7 - alice [18/Oct/2026:09:12:39 +0200] "POST /login?next=%2F HTTP/1.1" 302 0 "https://example.com/" "Mozilla/5.0 (X11; Linux x86_64)""#,
+            )
+            .unwrap();
+        assert_eq!(record.remote_addr.as_deref(), Some("203.0.113.7"));
+        assert_eq!(
+            record.time.unwrap().to_rfc3339(),
+            "2026-10-18T09:12:39+02:00"
+        );
+        assert_eq!(record.method.as_deref(), Some("POST"));
+        assert_eq!(record.path.as_deref(), Some("/login?next=%2F"));
+        assert_eq!(record.status, Some(302));
+        assert_eq!(record.bytes, Some(0));
+        assert_eq!(record.referer.as_deref(), Some("https://example.com/"));
+        assert_eq!(
+            record.user_agent.as_deref(),
+            Some("Mozilla/5.0 (X11; Linux x86_64)")
+        );
+        assert_eq!(record.request_time, None);
+
+        // Fields logged as `-` are left out.
+        let record = format
+            .parse(r#"10.0.0.1 - - [18/Oct/2026:09:12:39 +0000] "GET / HTTP/1.1" 200 612 "-" "-""#)
+            .unwrap();
+        assert_eq!(record.referer, None);
+        assert_eq!(record.user_agent, None);
+    }
+
+    #[test]
+    fn parses_a_custom_log_format() {
+        let dir = std::env::temp_dir().join(format!("lazynginx-access-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("nginx.conf");
+        fs::write(
+            &path,
+            r#"http {
+    log_format timed escape=json '$remote_addr [$time_iso8601] '
+                     "\"$request\" $status $bytes_sent "
+                     '"$http_user_agent" rt=$request_time uct=${upstream_connect_time} urt=$upstream_response_time';
+}
+"#,
+        )
+        .unwrap();
+        let config = Config::load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let format = find_log_format(&config, "timed").unwrap();
+        assert_eq!(format.name, "timed");
+        let record = format
+            .parse(
+                r#"10.0.0.1 [2026-10-18T09:00:00+00:00] "GET /api?x=1 HTTP/1.1" 502 512 "curl/8.0" rt=0.250 uct=0.001 urt=0.500, 0.250 : 0.125"#,
+            )
+            .unwrap();
+        assert_eq!(
+            record.time.unwrap().to_rfc3339(),
+            "2026-10-18T09:00:00+00:00"
+        );
+        assert_eq!(record.path.as_deref(), Some("/api?x=1"));
+        assert_eq!(record.status, Some(502));
+        assert_eq!(record.bytes, Some(512));
+        assert_eq!(record.user_agent.as_deref(), Some("curl/8.0"));
+        assert_eq!(record.request_time, Some(0.25));
+        assert_eq!(record.upstream_response_time, Some(0.875));
+        assert_eq!(record.field("upstream_time"), Some("0.875".to_string()));
+
+        // No upstream was asked.
+        let record = format
+            .parse(r#"10.0.0.1 [2026-10-18T09:00:01+00:00] "GET / HTTP/1.1" 200 90 "-" rt=0.000 uct=- urt=-"#)
+            .unwrap();
+        assert_eq!(record.request_time, Some(0.0));
+        assert_eq!(record.upstream_response_time, None);
+
+        assert!(find_log_format(&config, "missing").is_none());
+    }
+
+    #[test]
+    fn rejects_lines_of_another_format() {
+        let format = LogFormat::new("short", "$remote_addr $status $request_time").unwrap();
+        assert!(format.parse("10.0.0.1 200 0.003").is_some());
+        assert!(format.parse("10.0.0.1 200").is_none());
+        assert!(LogFormat::combined()
+            .parse("2026/10/18 09:00:00 [error] 1#1: *1 open() failed")
+            .is_none());
+    }
+}
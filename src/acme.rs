@@ -0,0 +1,874 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::{chown, MetadataExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair};
+use ring::{
+    digest,
+    rand::SystemRandom,
+    signature::{self, EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use serde_json::{json, Value};
+use ureq::{
+    tls::{parse_pem, PemItem, RootCerts, TlsConfig},
+    Agent,
+};
+use x509_parser::pem::Pem;
+
+use crate::{
+    history::state_dir,
+    stub_status::{http_get, Endpoint},
+};
+
+/// How long a single request to the CA may take.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often the CA is asked whether it is done validating or issuing.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Polls before giving up on the CA.
+const POLL_LIMIT: usize = 60;
+
+/// Prefix of the error types defined by RFC 8555.
+const ERROR_PREFIX: &str = "urn:ietf:params:acme:error:";
+
+/// What to ask the CA for and where to put it.
+#[derive(Debug, Clone)]
+pub struct Order {
+    /// URL of the ACME directory.
+    pub directory: String,
+    /// PEM file with the roots trusted for the directory's HTTPS, instead of
+    /// Mozilla's.
+    pub ca_bundle: Option<PathBuf>,
+    pub email: Option<String>,
+    pub names: Vec<String>,
+    /// Directory nginx serves `/.well-known/acme-challenge/` from.
+    pub webroot: PathBuf,
+    /// Port nginx answers the challenges on, checked before asking the CA.
+    pub port: u16,
+    pub certificate: PathBuf,
+    pub key: PathBuf,
+}
+
+#[derive(Debug)]
+enum Message {
+    Step(String),
+    Done(Result<(), String>),
+}
+
+/// A certificate being issued on a background thread.
+#[derive(Debug)]
+pub struct Issuance {
+    pub order: Order,
+    messages: Receiver<Message>,
+    /// What is being done right now.
+    pub step: String,
+    /// Set once the certificate and key are written, or issuing failed.
+    pub result: Option<Result<(), String>>,
+}
+
+impl Issuance {
+    pub fn start(order: Order) -> Self {
+        let (sender, messages) = mpsc::channel();
+        let job = order.clone();
+        thread::spawn(move || {
+            let result = issue(&job, &sender);
+            let _ = sender.send(Message::Done(result));
+        });
+        Self {
+            order,
+            messages,
+            step: "Starting".to_string(),
+            result: None,
+        }
+    }
+
+    /// Takes in the progress made since the last call. Returns true when
+    /// issuing ended since.
+    pub fn poll(&mut self) -> bool {
+        let mut ended = false;
+        while let Ok(message) = self.messages.try_recv() {
+            match message {
+                Message::Step(step) => self.step = step,
+                Message::Done(result) => {
+                    self.result = Some(result);
+                    ended = true;
+                }
+            }
+        }
+        ended
+    }
+
+    pub fn running(&self) -> bool {
+        self.result.is_none()
+    }
+}
+
+/// Runs `order` through the ACME flow of RFC 8555: registers the account,
+/// answers an HTTP-01 challenge for each name, sends a CSR for a new key
+/// and writes the key and the certificate chain.
+fn issue(order: &Order, progress: &Sender<Message>) -> Result<(), String> {
+    let step = |step: String| {
+        let _ = progress.send(Message::Step(step));
+    };
+
+    if order.names.is_empty() {
+        return Err("No names to issue a certificate for".to_string());
+    }
+    step(format!("Contacting {}", order.directory));
+    let mut client = Client::new(order)?;
+    step("Registering the ACME account".to_string());
+    client.register(order.email.as_deref())?;
+
+    step(format!(
+        "Ordering a certificate for {}",
+        order.names.join(" ")
+    ));
+    let identifiers: Vec<Value> = order
+        .names
+        .iter()
+        .map(|name| json!({ "type": "dns", "value": name }))
+        .collect();
+    let new_order = client.endpoint("newOrder")?;
+    let reply = client.post(&new_order, Some(&json!({ "identifiers": identifiers })))?;
+    let created = reply.json()?;
+    let order_url = reply.location.ok_or("The CA gave no order URL")?;
+
+    let mut challenges = ChallengeFiles {
+        dir: order.webroot.join(".well-known/acme-challenge"),
+        files: Vec::new(),
+    };
+    for authorization in created["authorizations"].as_array().into_iter().flatten() {
+        let url = authorization.as_str().ok_or("Invalid authorization URL")?;
+        let authorization = client.post(url, None)?.json()?;
+        let name = authorization["identifier"]["value"]
+            .as_str()
+            .unwrap_or_default();
+        if authorization["status"] == "valid" {
+            continue;
+        }
+        let challenge = authorization["challenges"]
+            .as_array()
+            .and_then(|challenges| challenges.iter().find(|c| c["type"] == "http-01"))
+            .ok_or_else(|| format!("The CA offers no http-01 challenge for {}", name))?;
+        let token = text(challenge, "token")?;
+        if !token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("The CA sent an invalid token for {}", name));
+        }
+        let key_authorization = format!("{}.{}", token, client.thumbprint());
+        challenges.write(token, &key_authorization)?;
+
+        step(format!("Checking nginx serves the challenge for {}", name));
+        self_check(order.port, name, token, &key_authorization)?;
+
+        step(format!("Waiting for the CA to validate {}", name));
+        client.post(text(challenge, "url")?, Some(&json!({})))?;
+        let authorization = client.poll(url, &["pending", "processing"])?;
+        if authorization["status"] != "valid" {
+            let error = authorization["challenges"]
+                .as_array()
+                .and_then(|challenges| challenges.iter().find(|c| c["type"] == "http-01"))
+                .map(|challenge| &challenge["error"])
+                .unwrap_or(&Value::Null);
+            return Err(format!(
+                "The CA could not validate {}: {}",
+                name,
+                problem(error, "no reason given")
+            ));
+        }
+    }
+
+    step("Sending the certificate signing request".to_string());
+    let key = KeyPair::generate().map_err(|err| format!("Failed to generate a key: {}", err))?;
+    let csr = CertificateParams::new(order.names.clone())
+        .and_then(|mut params| {
+            params.distinguished_name = DistinguishedName::new();
+            params
+                .distinguished_name
+                .push(DnType::CommonName, order.names[0].as_str());
+            params.serialize_request(&key)
+        })
+        .map_err(|err| format!("Failed to make the signing request: {}", err))?;
+    client.post(
+        text(&created, "finalize")?,
+        Some(&json!({ "csr": URL_SAFE_NO_PAD.encode(csr.der()) })),
+    )?;
+    let issued = client.poll(&order_url, &["pending", "ready", "processing"])?;
+    if issued["status"] != "valid" {
+        return Err(format!(
+            "The CA did not issue the certificate: {}",
+            problem(&issued["error"], "no reason given")
+        ));
+    }
+
+    step("Downloading the certificate".to_string());
+    let chain = client.post(text(&issued, "certificate")?, None)?.body;
+    let key_file = stage(&order.key, key.serialize_pem().as_bytes(), 0o600)?;
+    let certificate_file =
+        stage(&order.certificate, chain.as_bytes(), 0o644).inspect_err(|_| {
+            let _ = fs::remove_file(&key_file);
+        })?;
+    replace(&key_file, &order.key, &certificate_file, &order.certificate)
+}
+
+/// Renames the staged key and certificate into place. The certificate goes
+/// last, so whatever watches it for changes finds the new key in place
+/// already; should it fail, the old key is put back so the pair on disk
+/// still matches.
+fn replace(
+    key_file: &Path,
+    key: &Path,
+    certificate_file: &Path,
+    certificate: &Path,
+) -> Result<(), String> {
+    let cleanup = || {
+        let _ = fs::remove_file(key_file);
+        let _ = fs::remove_file(certificate_file);
+    };
+    let old_key = key_file.with_extension("old");
+    let _ = fs::remove_file(&old_key);
+    let kept = match fs::hard_link(key, &old_key).or_else(|_| fs::copy(key, &old_key).map(|_| ())) {
+        Ok(()) => true,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+        Err(err) => {
+            cleanup();
+            return Err(format!("Failed to keep the old {}: {}", key.display(), err));
+        }
+    };
+    if let Err(err) = fs::rename(key_file, key) {
+        cleanup();
+        let _ = fs::remove_file(&old_key);
+        return Err(format!("Failed to replace {}: {}", key.display(), err));
+    }
+    if let Err(err) = fs::rename(certificate_file, certificate) {
+        cleanup();
+        let restored = if kept {
+            fs::rename(&old_key, key)
+        } else {
+            fs::remove_file(key)
+        };
+        return Err(match restored {
+            Ok(()) => format!("Failed to replace {}: {}", certificate.display(), err),
+            Err(restore) => format!(
+                "Failed to replace {}: {}; the new key stays in {} as the old one could not be put back: {}",
+                certificate.display(),
+                err,
+                key.display(),
+                restore
+            ),
+        });
+    }
+    let _ = fs::remove_file(&old_key);
+    Ok(())
+}
+
+/// Checks nginx serves the challenge answer over plain HTTP, which the CA
+/// would otherwise only find out after a while, counting it against its
+/// rate limits.
+fn self_check(port: u16, name: &str, token: &str, expected: &str) -> Result<(), String> {
+    let endpoint = Endpoint {
+        address: format!("127.0.0.1:{}", port),
+        host: name.to_string(),
+        path: format!("/.well-known/acme-challenge/{}", token),
+    };
+    match http_get(&endpoint) {
+        Ok(body) if body.trim() == expected => Ok(()),
+        Ok(_) => Err(format!(
+            "{} answers the challenge for {} with something else, does another server block \
+             take its requests?",
+            endpoint.url(),
+            name
+        )),
+        Err(err) => Err(format!(
+            "nginx does not serve the challenge for {} ({}). Write the template and reload \
+             nginx first.",
+            name, err
+        )),
+    }
+}
+
+/// A string member of a JSON object from the CA.
+fn text<'v>(value: &'v Value, name: &str) -> Result<&'v str, String> {
+    value[name]
+        .as_str()
+        .ok_or_else(|| format!("The CA left out {}", name))
+}
+
+/// The detail of an RFC 7807 problem document, with its ACME error type.
+fn problem(value: &Value, fallback: &str) -> String {
+    let detail = value["detail"].as_str().unwrap_or(fallback);
+    match value["type"].as_str() {
+        Some(kind) => format!("{} ({})", detail, kind.trim_start_matches(ERROR_PREFIX)),
+        None => detail.to_string(),
+    }
+}
+
+/// Challenge answers written to the webroot, removed again once done with.
+struct ChallengeFiles {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl ChallengeFiles {
+    fn write(&mut self, token: &str, contents: &str) -> Result<(), String> {
+        let path = self.dir.join(token);
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&path, contents))
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        self.files.push(path);
+        Ok(())
+    }
+}
+
+impl Drop for ChallengeFiles {
+    fn drop(&mut self) {
+        for file in &self.files {
+            let _ = fs::remove_file(file);
+        }
+    }
+}
+
+/// Writes `contents` to `path`, creating its directory. `mode` applies to
+/// new files only.
+fn write_file(path: &Path, contents: &[u8], mode: u32) -> Result<(), String> {
+    let write = || -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(mode)
+            .open(path)?
+            .write_all(contents)
+    };
+    write().map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+}
+
+/// Writes `contents` to a new file next to `path`, to be renamed over it
+/// so it is replaced at once. The file takes the mode and owner of the one
+/// it replaces, `mode` when there is none.
+fn stage(path: &Path, contents: &[u8], mode: u32) -> Result<PathBuf, String> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(format!("{} is not a file path", path.display()));
+    };
+    let staged = dir.join(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let existing = fs::metadata(path).ok();
+    let write = || -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(existing.as_ref().map_or(mode, |meta| meta.mode() & 0o7777))
+            .open(&staged)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        if let Some(meta) = &existing {
+            let _ = chown(&staged, Some(meta.uid()), Some(meta.gid()));
+        }
+        Ok(())
+    };
+    write().map_err(|err| {
+        let _ = fs::remove_file(&staged);
+        format!("Failed to write {}: {}", staged.display(), err)
+    })?;
+    Ok(staged)
+}
+
+fn pem(label: &str, der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+/// The P-256 key of the ACME account, kept in the state directory so every
+/// certificate is ordered by the same account.
+fn account_key(rng: &SystemRandom) -> Result<EcdsaKeyPair, String> {
+    let path = state_dir()
+        .ok_or("No state directory to keep the ACME account key in")?
+        .join("acme-account.key");
+    let pkcs8 = match fs::read(&path) {
+        Ok(contents) => {
+            Pem::iter_from_buffer(&contents)
+                .filter_map(Result::ok)
+                .find(|pem| pem.label == "PRIVATE KEY")
+                .ok_or_else(|| format!("No private key in {}", path.display()))?
+                .contents
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, rng)
+                .map_err(|_| "Failed to generate the ACME account key")?;
+            write_file(&path, pem("PRIVATE KEY", pkcs8.as_ref()).as_bytes(), 0o600)?;
+            pkcs8.as_ref().to_vec()
+        }
+        Err(err) => return Err(format!("Failed to read {}: {}", path.display(), err)),
+    };
+    EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8, rng)
+        .map_err(|err| format!("Invalid ACME account key {}: {}", path.display(), err))
+}
+
+/// What the CA answered to a request.
+struct Reply {
+    location: Option<String>,
+    body: String,
+}
+
+impl Reply {
+    fn json(&self) -> Result<Value, String> {
+        serde_json::from_str(&self.body)
+            .map_err(|err| format!("Invalid answer from the CA: {}", err))
+    }
+}
+
+/// Talks to the CA with requests signed by the account key.
+struct Client {
+    agent: Agent,
+    directory: Value,
+    key: EcdsaKeyPair,
+    rng: SystemRandom,
+    /// URL of the account, once registered.
+    account: Option<String>,
+    /// Handed out by the last answer, good for one request.
+    nonce: Option<String>,
+}
+
+impl Client {
+    fn new(order: &Order) -> Result<Self, String> {
+        let mut tls = TlsConfig::builder();
+        if let Some(bundle) = &order.ca_bundle {
+            let contents = fs::read(bundle)
+                .map_err(|err| format!("Failed to read {}: {}", bundle.display(), err))?;
+            let roots: Vec<_> = parse_pem(&contents)
+                .filter_map(|item| match item {
+                    Ok(PemItem::Certificate(certificate)) => Some(certificate),
+                    _ => None,
+                })
+                .collect();
+            if roots.is_empty() {
+                return Err(format!("No certificates in {}", bundle.display()));
+            }
+            tls = tls.root_certs(RootCerts::new_with_certs(&roots));
+        }
+        let agent: Agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(TIMEOUT))
+            .user_agent(concat!("lazynginx/", env!("CARGO_PKG_VERSION")))
+            .tls_config(tls.build())
+            .build()
+            .into();
+        let directory = agent
+            .get(&order.directory)
+            .call()
+            .and_then(|mut response| response.body_mut().read_to_string())
+            .map_err(|err| format!("Cannot reach {}: {}", order.directory, err))?;
+        let directory: Value = serde_json::from_str(&directory)
+            .map_err(|_| format!("{} is not an ACME directory", order.directory))?;
+        let rng = SystemRandom::new();
+        Ok(Self {
+            agent,
+            directory,
+            key: account_key(&rng)?,
+            rng,
+            account: None,
+            nonce: None,
+        })
+    }
+
+    /// One of the URLs listed in the directory.
+    fn endpoint(&self, name: &str) -> Result<String, String> {
+        text(&self.directory, name).map(str::to_string)
+    }
+
+    fn nonce(&mut self) -> Result<String, String> {
+        if let Some(nonce) = self.nonce.take() {
+            return Ok(nonce);
+        }
+        let url = self.endpoint("newNonce")?;
+        let response = self
+            .agent
+            .head(&url)
+            .call()
+            .map_err(|err| format!("Cannot reach {}: {}", url, err))?;
+        header(&response, "replay-nonce").ok_or_else(|| format!("{} gave no nonce", url))
+    }
+
+    /// The thumbprint of the account key, which challenge answers end with.
+    fn thumbprint(&self) -> String {
+        thumbprint(&jwk(&self.key))
+    }
+
+    fn register(&mut self, email: Option<&str>) -> Result<(), String> {
+        let mut account = json!({ "termsOfServiceAgreed": true });
+        if let Some(email) = email {
+            account["contact"] = json!([format!("mailto:{}", email)]);
+        }
+        let url = self.endpoint("newAccount")?;
+        let reply = self.post(&url, Some(&account))?;
+        self.account = Some(reply.location.ok_or("The CA gave no account URL")?);
+        Ok(())
+    }
+
+    /// Sends `payload` signed as a JWS, or a POST-as-GET without one. A
+    /// rejected nonce is retried with the fresh one that comes with the
+    /// rejection.
+    fn post(&mut self, url: &str, payload: Option<&Value>) -> Result<Reply, String> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let mut protected = json!({ "alg": "ES256", "nonce": self.nonce()?, "url": url });
+            match &self.account {
+                Some(account) => protected["kid"] = json!(account),
+                None => protected["jwk"] = jwk(&self.key),
+            }
+            let payload = payload.map_or_else(String::new, Value::to_string);
+            let input = signing_input(protected.to_string().as_bytes(), payload.as_bytes());
+            let signature = self
+                .key
+                .sign(&self.rng, input.as_bytes())
+                .map_err(|_| "Failed to sign the request")?;
+            let (protected, payload) = input.split_once('.').unwrap_or_default();
+            let body = json!({
+                "protected": protected,
+                "payload": payload,
+                "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            })
+            .to_string();
+
+            let mut response = self
+                .agent
+                .post(url)
+                .header("Content-Type", "application/jose+json")
+                .send(body.as_str())
+                .map_err(|err| format!("Cannot reach {}: {}", url, err))?;
+            self.nonce = header(&response, "replay-nonce");
+            let location = header(&response, "location");
+            let body = response
+                .body_mut()
+                .read_to_string()
+                .map_err(|err| format!("Cannot read the answer of {}: {}", url, err))?;
+            if response.status().is_success() {
+                return Ok(Reply { location, body });
+            }
+            let error: Value = serde_json::from_str(&body).unwrap_or_default();
+            if error["type"] == format!("{}badNonce", ERROR_PREFIX) && attempts < 3 {
+                continue;
+            }
+            return Err(format!(
+                "{} refused the request: {}",
+                url,
+                problem(&error, response.status().as_str())
+            ));
+        }
+    }
+
+    /// Fetches `url` until its status is no longer one of `pending`.
+    fn poll(&mut self, url: &str, pending: &[&str]) -> Result<Value, String> {
+        for _ in 0..POLL_LIMIT {
+            let value = self.post(url, None)?.json()?;
+            if !pending.contains(&value["status"].as_str().unwrap_or_default()) {
+                return Ok(value);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        Err(format!("The CA took too long, gave up waiting on {}", url))
+    }
+}
+
+/// The public half of `key` as a JWK.
+fn jwk(key: &EcdsaKeyPair) -> Value {
+    // An uncompressed point: 0x04, then x and y.
+    let point = signature::KeyPair::public_key(key).as_ref();
+    json!({
+        "crv": "P-256",
+        "kty": "EC",
+        "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+        "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+    })
+}
+
+/// The RFC 7638 thumbprint of a public JWK: the hash of its required
+/// members in lexicographic order, without whitespace.
+fn thumbprint(jwk: &Value) -> String {
+    let required: &[&str] = match jwk["kty"].as_str() {
+        Some("RSA") => &["e", "kty", "n"],
+        _ => &["crv", "kty", "x", "y"],
+    };
+    let members: Vec<String> = required
+        .iter()
+        .map(|name| format!("{}:{}", json!(name), jwk[*name]))
+        .collect();
+    let canonical = format!("{{{}}}", members.join(","));
+    URL_SAFE_NO_PAD.encode(digest::digest(&digest::SHA256, canonical.as_bytes()))
+}
+
+/// What a JWS signs (RFC 7515, section 5.1): the protected header and the
+/// payload, base64url encoded and joined by a dot.
+fn signing_input(protected: &[u8], payload: &[u8]) -> String {
+    format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(protected),
+        URL_SAFE_NO_PAD.encode(payload)
+    )
+}
+
+fn header(response: &ureq::http::Response<ureq::Body>, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        os::unix::fs::PermissionsExt,
+        time::Instant,
+    };
+
+    use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_FIXED};
+
+    use super::*;
+
+    /// The ES256 key of RFC 7515, appendix A.3.
+    const X: &str = "f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU";
+    const Y: &str = "x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0";
+
+    fn public_key(x: &str, y: &str) -> Vec<u8> {
+        let mut point = vec![4];
+        point.extend(URL_SAFE_NO_PAD.decode(x).unwrap());
+        point.extend(URL_SAFE_NO_PAD.decode(y).unwrap());
+        point
+    }
+
+    #[test]
+    fn thumbprints_match_rfc_7638() {
+        // The example of RFC 7638, section 3.1.
+        let rsa = json!({
+            "kty": "RSA",
+            "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6\
+                  tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5\
+                  v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD0\
+                  8qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU\
+                  8awapJzKnqDKgw",
+            "e": "AQAB",
+            "alg": "RS256",
+            "kid": "2011-04-29",
+        });
+        assert_eq!(
+            thumbprint(&rsa),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+
+        // Members beyond the required ones and their order do not count.
+        let ec = json!({ "y": Y, "x": X, "use": "sig", "kty": "EC", "crv": "P-256" });
+        assert_eq!(
+            thumbprint(&ec),
+            "oKIywvGUpTVTyxMQ3bwIIeQUudfr_CkLMjCE19ECD-U"
+        );
+    }
+
+    #[test]
+    fn signing_input_matches_rfc_7515() {
+        // Appendix A.3: the header and payload bytes, and the signature
+        // made over their signing input.
+        let input = signing_input(
+            br#"{"alg":"ES256"}"#,
+            b"{\"iss\":\"joe\",\r\n \"exp\":1300819380,\r\n \"http://example.com/is_root\":true}",
+        );
+        assert_eq!(
+            input,
+            "eyJhbGciOiJFUzI1NiJ9.eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFt\
+             cGxlLmNvbS9pc19yb290Ijp0cnVlfQ"
+        );
+        let signature = URL_SAFE_NO_PAD
+            .decode(
+                "DtEhU3ljbEg8L38VWAfUAqOyKAM6-Xx-F4GawxaepmXFCgfTjDxw5djxLa8ISlSApmWQxfKTUJqPP3-Kg6NU1Q",
+            )
+            .unwrap();
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, public_key(X, Y))
+            .verify(input.as_bytes(), &signature)
+            .unwrap();
+
+        // A POST-as-GET has an empty payload (RFC 8555, section 6.3).
+        assert_eq!(signing_input(b"{}", b""), "e30.");
+    }
+
+    #[test]
+    fn signs_with_the_key_its_jwk_describes() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+        let jwk = jwk(&key);
+        let input = signing_input(br#"{"alg":"ES256"}"#, br#"{"termsOfServiceAgreed":true}"#);
+        let signature = key.sign(&rng, input.as_bytes()).unwrap();
+        let point = public_key(jwk["x"].as_str().unwrap(), jwk["y"].as_str().unwrap());
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, point)
+            .verify(input.as_bytes(), signature.as_ref())
+            .unwrap();
+    }
+
+    #[test]
+    fn staged_files_keep_the_mode_of_the_file_they_replace() {
+        let dir = std::env::temp_dir().join(format!("lazynginx-acme-{}", std::process::id()));
+        let path = dir.join("example.com.key");
+        let staged = stage(&path, b"new", 0o600).unwrap();
+        assert_eq!(fs::metadata(&staged).unwrap().mode() & 0o777, 0o600);
+        assert!(!path.exists());
+        fs::rename(&staged, &path).unwrap();
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let staged = stage(&path, b"newer", 0o600).unwrap();
+        assert_eq!(staged.parent(), path.parent());
+        assert_eq!(fs::metadata(&staged).unwrap().mode() & 0o777, 0o640);
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn puts_the_old_key_back_when_the_certificate_is_not_replaced() {
+        let dir = std::env::temp_dir().join(format!("lazynginx-replace-{}", std::process::id()));
+        let key = dir.join("example.com.key");
+        let certificate = dir.join("example.com.crt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&key, "old key").unwrap();
+        fs::write(&certificate, "old certificate").unwrap();
+
+        let key_file = stage(&key, b"new key", 0o600).unwrap();
+        let certificate_file = stage(&certificate, b"new certificate", 0o644).unwrap();
+        replace(&key_file, &key, &certificate_file, &certificate).unwrap();
+        assert_eq!(fs::read(&key).unwrap(), b"new key");
+        assert_eq!(fs::read(&certificate).unwrap(), b"new certificate");
+
+        // A directory in the way makes the certificate rename fail.
+        let blocked = dir.join("blocked.crt");
+        fs::create_dir_all(blocked.join("in-the-way")).unwrap();
+        let key_file = stage(&key, b"newer key", 0o600).unwrap();
+        let certificate_file = stage(&blocked, b"newer certificate", 0o644).unwrap();
+        assert!(replace(&key_file, &key, &certificate_file, &blocked).is_err());
+        assert_eq!(fs::read(&key).unwrap(), b"new key");
+        let mut left: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["blocked.crt", "example.com.crt", "example.com.key"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Serves the files under `webroot` over plain HTTP on `port`, for the
+    /// self check and the CA.
+    fn serve(webroot: PathBuf, port: u16) {
+        let listener = TcpListener::bind(("0.0.0.0", port)).unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let mut request = String::new();
+                let _ = BufReader::new(&stream).read_line(&mut request);
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let response = match fs::read_to_string(webroot.join(path.trim_start_matches('/')))
+                {
+                    Ok(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    Err(_) => {
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string()
+                    }
+                };
+                let _ = (&stream).write_all(response.as_bytes());
+            }
+        });
+    }
+
+    /// Issues a certificate from a Pebble test CA, started with its test
+    /// config and the name resolving to this machine, for instance through
+    /// `pebble-challtestsrv -defaultIPv4 127.0.0.1`:
+    ///
+    /// ```text
+    /// PEBBLE_DIRECTORY=https://localhost:14000/dir \
+    /// PEBBLE_CA_BUNDLE=test/certs/pebble.minica.pem \
+    /// cargo test issues_from_pebble -- --ignored
+    /// ```
+    ///
+    /// `PEBBLE_NAME` picks the name, `lazynginx.test` by default, and
+    /// `PEBBLE_HTTP_PORT` the port Pebble validates on, 5002 by default.
+    #[test]
+    #[ignore]
+    fn issues_from_pebble() {
+        let directory = env::var("PEBBLE_DIRECTORY").expect("PEBBLE_DIRECTORY is not set");
+        let name = env::var("PEBBLE_NAME").unwrap_or_else(|_| "lazynginx.test".to_string());
+        let port = env::var("PEBBLE_HTTP_PORT")
+            .ok()
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(5002);
+        let dir = env::temp_dir().join(format!("lazynginx-pebble-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // Keeps the account key of the test apart.
+        env::set_var("XDG_STATE_HOME", dir.join("state"));
+        serve(dir.join("webroot"), port);
+
+        let order = Order {
+            directory,
+            ca_bundle: env::var_os("PEBBLE_CA_BUNDLE").map(PathBuf::from),
+            email: Some("admin@example.com".to_string()),
+            names: vec![name],
+            webroot: dir.join("webroot"),
+            port,
+            certificate: dir.join("certs/fullchain.pem"),
+            key: dir.join("certs/privkey.pem"),
+        };
+        let mut issuance = Issuance::start(order.clone());
+        let started = Instant::now();
+        while !issuance.poll() {
+            assert!(
+                started.elapsed() < Duration::from_secs(300),
+                "still {}",
+                issuance.step
+            );
+            thread::sleep(Duration::from_millis(200));
+        }
+        issuance.result.unwrap().unwrap();
+
+        let chain = fs::read_to_string(&order.certificate).unwrap();
+        assert!(chain.starts_with("-----BEGIN CERTIFICATE-----"));
+        assert_eq!(fs::metadata(&order.key).unwrap().mode() & 0o777, 0o600);
+        let staged: Vec<_> = fs::read_dir(dir.join("certs"))
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(staged.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
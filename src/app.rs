@@ -1,17 +1,122 @@
-use std::error;
+use std::{
+    error,
+    fs::{self, create_dir_all},
+    io::ErrorKind,
+    os::unix::fs::symlink,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
-use tui::widgets::ListState;
+use regex::Regex;
+use tui::widgets::{ListState, TableState};
 
 use crate::{
-    status::get_nginx_status,
-    systemctl::SystemctlCommand,
-    tabs::{get_current_screen, Screen},
+    access_log::{find_log_format, LogFormat},
+    acme::{Issuance, Order},
+    certificates::{find_certificates, Certificate},
+    cli::Args,
+    command::CommandResult,
+    config::{default_config_path, Config, ConfigError},
+    config_tree::ConfigTree,
+    dashboard::Dashboard,
+    error_log::{line_level, next_min_level},
+    history::{append_audit, load_history, HistoryEntry},
+    htpasswd::{Htpasswd, Scheme},
+    log_filter::{LogFilter, LogSource, TimeRange},
+    logs::{get_log_locations, log_files, LogKind, LogLocation},
+    modal::Modal,
+    prompt::{Prompt, PromptKind},
+    refresh::Poller,
+    service::{
+        detect_service_manager, pid_path, service_manager_by_name, CommandOutcome, CommandRunner,
+        ServiceCommand, ServiceManager,
+    },
+    spool::files_in_range,
+    status::{get_nginx_status, NginxStatus},
+    stub_status::{find_stub_status, Endpoint, StubStatusMonitor},
+    tabs::{get_current_screen, get_screen_index, Screen},
+    tail::LogTailer,
+    template::{includes_dir, insert_in_block, sites_dirs, Context, TemplateForm, TEMPLATES},
+    validate::{ConfigTest, Level},
     version::{get_nginx_version, NginxVersion},
 };
 
 /// Application result type.
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
 
+/// Entries of the command list on the Status tab.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Service(ServiceCommand),
+    TestConfig,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::Service(ServiceCommand::Start),
+        Action::Service(ServiceCommand::Stop),
+        Action::Service(ServiceCommand::Restart),
+        Action::Service(ServiceCommand::Reload),
+        Action::Service(ServiceCommand::Status),
+        Action::Service(ServiceCommand::Enable),
+        Action::Service(ServiceCommand::Disable),
+        Action::TestConfig,
+    ];
+
+    pub fn label(&self) -> &str {
+        match *self {
+            Action::Service(ServiceCommand::Start) => "Start",
+            Action::Service(ServiceCommand::Stop) => "Stop",
+            Action::Service(ServiceCommand::Restart) => "Restart",
+            Action::Service(ServiceCommand::Reload) => "Reload",
+            Action::Service(ServiceCommand::Status) => "Status",
+            Action::Service(ServiceCommand::Enable) => "Enable",
+            Action::Service(ServiceCommand::Disable) => "Disable",
+            Action::TestConfig => "Test config",
+        }
+    }
+}
+
+/// Which pane of the Config tab receives the arrow keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFocus {
+    Tree,
+    Diagnostics,
+}
+
+/// Which pane of the Templates tab receives the arrow keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemplateFocus {
+    List,
+    Form,
+    /// The users of the htpasswd file named by the form.
+    Users,
+}
+
+/// What the `nginx -t` run in the background was started for.
+#[derive(Debug)]
+pub enum PendingTest {
+    /// Asked for on the Status or Config tab, only to be shown.
+    Show,
+    /// After writing a template to `path`, undone when the test fails.
+    Template {
+        path: PathBuf,
+        /// What `path` held before, `None` if it is new.
+        previous: Option<Vec<u8>>,
+        link: PathBuf,
+        /// Whether the write made `link`.
+        linked: bool,
+    },
+    /// After adding a template to `location` in `file`, which is put back
+    /// to `source` when the test fails.
+    Location {
+        location: String,
+        file: PathBuf,
+        source: String,
+    },
+}
+
 /// Application.
 #[derive(Debug)]
 pub struct App<'a> {
@@ -23,40 +128,220 @@ pub struct App<'a> {
 
     pub list_state: ListState,
     pub log_list_state: ListState,
-    pub status: String,
+    /// Logs named in the config, listed on the Logs tab.
+    pub log_locations: Vec<LogLocation>,
+    /// The log picked on the Logs tab.
+    pub log_view: Option<LogTailer>,
+    /// Format of the picked access log, to show it as a table.
+    pub log_format: Option<LogFormat>,
+    /// Least severe level shown for error logs.
+    pub error_level: Level,
+    /// Only lines matching this are shown in the log view.
+    pub log_filter: Option<LogFilter>,
+    /// Only lines logged in this range are shown, from the log and all of
+    /// its rotations.
+    pub log_range: Option<TimeRange>,
+    /// Highlighted in the log view, `n` and `N` jump between matches.
+    pub search: Option<Regex>,
+    /// Outcome of the last search or filter, shown under the log view.
+    pub log_notice: Option<String>,
+    /// Traffic of the access log last picked on the Logs tab, or of the
+    /// first one in the config.
+    pub dashboard: Option<Dashboard>,
+    /// Every template with the values typed in for it.
+    pub templates: Vec<TemplateForm>,
+    pub template_state: ListState,
+    pub template_focus: TemplateFocus,
+    /// What the templates check their values against.
+    pub template_context: Context,
+    /// The selected template rendered, with the index and values it was
+    /// rendered from. See [`App::refresh_template_preview`].
+    pub template_preview: Option<(usize, Vec<String>, Result<String, String>)>,
+    pub preview_scroll: u16,
+    /// Outcome of the last write, or why it failed.
+    pub template_notice: Option<Result<String, String>>,
+    /// The user file of the selected template, if it has one.
+    pub htpasswd: Option<Htpasswd>,
+    /// The user file that failed to load, tried again once the form names
+    /// another.
+    pub htpasswd_failed: Option<PathBuf>,
+    pub htpasswd_state: TableState,
+    /// The user a password is being typed for.
+    pub htpasswd_user: Option<String>,
+    /// The certificate being issued from the ACME template, or the last one.
+    pub acme: Option<Issuance>,
+    /// Certificates named in the config, listed on the Certificates tab.
+    pub certificates: Vec<Certificate>,
+    pub certificate_state: TableState,
+    /// Certificates expiring within this many days are highlighted.
+    pub expiry_warning: u32,
+    pub status: NginxStatus,
+    /// Refreshes [`App::status`] in the background.
+    pub status_poller: Poller<NginxStatus>,
+    /// Connection metrics from the stub_status page, when there is one.
+    pub stub_status: Option<StubStatusMonitor>,
+    pub refresh_interval: Duration,
     pub nginx_version: Option<NginxVersion>,
     pub titles: Vec<&'a str>,
     pub tab_index: usize,
+    pub config_path: PathBuf,
+    pub config: Result<Config, ConfigError>,
+    pub config_tree: ConfigTree,
+    pub service_manager: Arc<dyn ServiceManager>,
+    /// Runs the commands of the Status tab and `nginx -t` in the
+    /// background.
+    pub command_runner: CommandRunner,
+    /// What the `nginx -t` under way is for.
+    pub pending_test: Option<PendingTest>,
+    pub pid_path: PathBuf,
+    pub config_focus: ConfigFocus,
+    pub config_test: Option<ConfigTest>,
+    pub diagnostics_state: ListState,
+    pub modal: Option<Modal>,
+    pub prompt: Option<Prompt>,
+    /// Result of the last command run from the Status tab.
+    pub command_output: Option<CommandResult>,
+    pub output_scroll: u16,
+    /// Commands run from lazynginx, oldest first, including earlier sessions.
+    pub history: Vec<HistoryEntry>,
+    pub history_state: TableState,
+    /// Why the last entry could not be written to the audit file.
+    pub audit_error: Option<String>,
 }
 
 impl<'a> Default for App<'a> {
     fn default() -> Self {
+        Self::with_args(&Args::default()).expect("default arguments are valid")
+    }
+}
+
+impl<'a> App<'a> {
+    /// Constructs a new instance of [`App`] configured by the command line.
+    pub fn with_args(args: &Args) -> Result<Self, String> {
+        let config_path = default_config_path();
+        let config = Config::load(&config_path);
+        let service_manager = match &args.service_manager {
+            Some(name) => service_manager_by_name(name, &config_path, &config)
+                .ok_or_else(|| format!("unknown service manager {}", name))?,
+            None => detect_service_manager(&config_path, &config),
+        };
+        let pid_path = pid_path(&config);
+        let status_poller = {
+            let service_manager = service_manager.clone();
+            let pid_path = pid_path.clone();
+            Poller::new(args.refresh_interval, move || {
+                get_nginx_status(service_manager.as_ref(), &pid_path)
+            })
+        };
+        let stub_status = match &args.stub_status {
+            Some(url) => Some(StubStatusMonitor::new(
+                Endpoint::parse(url).map_err(|err| format!("--stub-status: {}", err))?,
+                false,
+                args.refresh_interval,
+            )),
+            None => find_stub_status(&config)
+                .map(|endpoint| StubStatusMonitor::new(endpoint, true, args.refresh_interval)),
+        };
         let mut list_state = ListState::default();
         let mut log_list_state = ListState::default();
+        let mut template_state = ListState::default();
         list_state.select(Some(0));
         log_list_state.select(Some(0));
-        Self {
+        template_state.select(Some(0));
+        let nginx_version = get_nginx_version();
+        let mut app = Self {
             running: true,
             vertical_position: 0,
             horizontal_position: 0,
             list_state,
-            status: get_nginx_status(),
-            nginx_version: get_nginx_version(),
+            status: NginxStatus::default(),
+            status_poller,
+            stub_status,
+            refresh_interval: args.refresh_interval,
+            template_context: Context::new(&config, nginx_version.as_ref()),
+            template_preview: None,
+            nginx_version,
             tab_index: 0,
-            titles: vec!["Status", "Config", "Logs", "Templates"],
+            titles: vec![
+                "Status",
+                "Config",
+                "Logs",
+                "Dashboard",
+                "Templates",
+                "Certificates",
+                "History",
+            ],
+            log_view: None,
+            log_format: None,
+            error_level: Level::Debug,
+            log_filter: None,
+            log_range: None,
+            search: None,
+            log_notice: None,
+            dashboard: None,
+            templates: TEMPLATES.iter().map(TemplateForm::new).collect(),
+            template_state,
+            template_focus: TemplateFocus::List,
+            preview_scroll: 0,
+            template_notice: None,
+            htpasswd: None,
+            htpasswd_failed: None,
+            htpasswd_state: TableState::default(),
+            htpasswd_user: None,
+            acme: None,
+            certificates: find_certificates(&config, &config_path),
+            certificate_state: TableState::default(),
+            expiry_warning: args.expiry_warning,
+            log_locations: get_log_locations(&config),
             log_list_state,
-        }
+            config_tree: ConfigTree::new(&config),
+            config,
+            config_path,
+            service_manager,
+            command_runner: CommandRunner::default(),
+            pending_test: None,
+            pid_path,
+            config_focus: ConfigFocus::Tree,
+            config_test: None,
+            diagnostics_state: ListState::default(),
+            modal: None,
+            prompt: None,
+            command_output: None,
+            output_scroll: 0,
+            history: load_history(),
+            history_state: TableState::default(),
+            audit_error: None,
+        };
+        app.open_selected_log();
+        app.open_dashboard();
+        app.certificate_state.select(Some(0));
+        Ok(app)
     }
-}
-
-impl<'a> App<'a> {
     /// Constructs a new instance of [`App`].
     pub fn new() -> Self {
         Self::default()
     }
 
     /// Handles the tick event of the terminal.
-    pub fn tick(&self) {}
+    pub fn tick(&mut self) {
+        if let Some(status) = self.status_poller.poll() {
+            self.status = status;
+        }
+        if let Some(outcome) = self.command_runner.poll() {
+            self.finish_command(outcome);
+        }
+        if let Some(stub_status) = self.stub_status.as_mut() {
+            stub_status.poll();
+        }
+        if let Some(log_view) = self.log_view.as_mut() {
+            log_view.poll();
+        }
+        if let Some(dashboard) = self.dashboard.as_mut() {
+            dashboard.poll(self.log_view.as_mut());
+        }
+        self.sync_htpasswd();
+        self.poll_acme();
+    }
 
     /// Set running to false to quit the application.
     pub fn quit(&mut self) {
@@ -90,13 +375,26 @@ impl<'a> App<'a> {
         self.tab_index = self.horizontal_position;
     }
 
+    pub fn select_screen(&mut self, screen: Screen) {
+        self.horizontal_position = get_screen_index(screen);
+        self.tab_index = self.horizontal_position;
+    }
+
     pub fn increment_selection(&mut self) {
         match get_current_screen(self) {
-            Screen::Config => {}
+            Screen::Config if self.config_focus == ConfigFocus::Diagnostics => {
+                let len = self.diagnostics_len();
+                let i = match self.diagnostics_state.selected() {
+                    Some(i) if i + 1 < len => i + 1,
+                    _ => 0,
+                };
+                self.diagnostics_state.select(Some(i));
+            }
+            Screen::Config => self.config_tree.select_next(),
             Screen::Status => {
                 let i = match self.list_state.selected() {
                     Some(i) => {
-                        if i >= 6 {
+                        if i >= Action::ALL.len() - 1 {
                             0
                         } else {
                             i + 1
@@ -108,31 +406,64 @@ impl<'a> App<'a> {
             }
             Screen::Logs => {
                 let i = match self.log_list_state.selected() {
-                    Some(i) => {
-                        //todo be nice if i didn't hardcode this 1, also code is replicated from above
-                        if i >= 1 {
-                            0
-                        } else {
-                            i + 1
-                        }
-                    }
-                    None => 0,
+                    Some(i) if i + 1 < self.log_locations.len() => i + 1,
+                    _ => 0,
                 };
                 self.log_list_state.select(Some(i));
+                self.open_selected_log();
+            }
+            Screen::Template if self.template_focus == TemplateFocus::Form => {
+                let form = self.selected_template_mut();
+                let i = (form.selected() + 1) % form.template.fields.len();
+                form.state.select(Some(i));
+            }
+            Screen::Template if self.template_focus == TemplateFocus::Users => {
+                let len = self.htpasswd.as_ref().map_or(0, |h| h.users.len());
+                let i = match self.htpasswd_state.selected() {
+                    Some(i) if i + 1 < len => i + 1,
+                    _ => 0,
+                };
+                self.htpasswd_state.select(Some(i));
+            }
+            Screen::Template => {
+                let i = (self.selected_template() + 1) % self.templates.len();
+                self.template_state.select(Some(i));
+                self.preview_scroll = 0;
+            }
+            Screen::Dashboard => {}
+            Screen::Certificates => {
+                let i = match self.certificate_state.selected() {
+                    Some(i) if i + 1 < self.certificates.len() => i + 1,
+                    _ => 0,
+                };
+                self.certificate_state.select(Some(i));
+            }
+            Screen::History => {
+                let i = match self.history_state.selected() {
+                    Some(i) if i + 1 < self.history.len() => i + 1,
+                    _ => 0,
+                };
+                self.history_state.select(Some(i));
             }
-            Screen::Template => {}
             Screen::Unknown => {}
         }
     }
 
     pub fn decrement_selection(&mut self) {
         match get_current_screen(self) {
-            Screen::Config => {}
+            Screen::Config if self.config_focus == ConfigFocus::Diagnostics => {
+                let i = match self.diagnostics_state.selected() {
+                    Some(0) | None => self.diagnostics_len().saturating_sub(1),
+                    Some(i) => i - 1,
+                };
+                self.diagnostics_state.select(Some(i));
+            }
+            Screen::Config => self.config_tree.select_previous(),
             Screen::Status => {
                 let i = match self.list_state.selected() {
                     Some(i) => {
                         if i == 0 {
-                            6
+                            Action::ALL.len() - 1
                         } else {
                             i - 1
                         }
@@ -143,32 +474,879 @@ impl<'a> App<'a> {
             }
             Screen::Logs => {
                 let i = match self.log_list_state.selected() {
-                    Some(i) => {
-                        if i == 0 {
-                            1
-                        } else {
-                            i - 1
-                        }
-                    }
-                    None => 0,
+                    Some(i) if i > 0 => i - 1,
+                    _ => self.log_locations.len().saturating_sub(1),
                 };
                 self.log_list_state.select(Some(i));
+                self.open_selected_log();
+            }
+            Screen::Template if self.template_focus == TemplateFocus::Form => {
+                let form = self.selected_template_mut();
+                let len = form.template.fields.len();
+                form.state.select(Some((form.selected() + len - 1) % len));
+            }
+            Screen::Template if self.template_focus == TemplateFocus::Users => {
+                let len = self.htpasswd.as_ref().map_or(0, |h| h.users.len());
+                let i = match self.htpasswd_state.selected() {
+                    Some(i) if i > 0 => i - 1,
+                    _ => len.saturating_sub(1),
+                };
+                self.htpasswd_state.select(Some(i));
+            }
+            Screen::Template => {
+                let len = self.templates.len();
+                self.template_state
+                    .select(Some((self.selected_template() + len - 1) % len));
+                self.preview_scroll = 0;
+            }
+            Screen::Dashboard => {}
+            Screen::Certificates => {
+                let i = match self.certificate_state.selected() {
+                    Some(0) | None => self.certificates.len().saturating_sub(1),
+                    Some(i) => i - 1,
+                };
+                self.certificate_state.select(Some(i));
+            }
+            Screen::History => {
+                let i = match self.history_state.selected() {
+                    Some(0) | None => self.history.len().saturating_sub(1),
+                    Some(i) => i - 1,
+                };
+                self.history_state.select(Some(i));
             }
-            Screen::Template => {}
             Screen::Unknown => {}
         }
     }
 
-    pub fn selected_command(&self) -> SystemctlCommand {
+    pub fn selected_action(&self) -> Action {
         match self.list_state.selected() {
-            Some(0) => SystemctlCommand::Start,
-            Some(1) => SystemctlCommand::Stop,
-            Some(2) => SystemctlCommand::Restart,
-            Some(3) => SystemctlCommand::Reload,
-            Some(4) => SystemctlCommand::Status,
-            Some(5) => SystemctlCommand::Enable,
-            Some(6) => SystemctlCommand::Disable,
+            Some(i) if i < Action::ALL.len() => Action::ALL[i],
             _ => panic!("Unexpected command selection"),
         }
     }
+
+    /// Starts `command` in the background, unless another one is under way,
+    /// in which case false is returned. Commands that load the config are
+    /// only run if `nginx -t` passes, otherwise the failure is shown in a
+    /// modal offering to run them anyway.
+    pub fn run_command(&mut self, command: ServiceCommand) -> bool {
+        self.command_runner.start(
+            self.service_manager.clone(),
+            &self.config_path,
+            command,
+            false,
+        )
+    }
+
+    /// Runs `command` after the user chose to ignore a failed config test.
+    pub fn force_command(&mut self, command: ServiceCommand) {
+        self.command_runner.start(
+            self.service_manager.clone(),
+            &self.config_path,
+            command,
+            true,
+        );
+    }
+
+    fn finish_command(&mut self, outcome: CommandOutcome) {
+        match outcome {
+            CommandOutcome::TestFailed { command, test } => {
+                self.record(&test.result, false);
+                self.config_test = Some(test.clone());
+                self.diagnostics_state.select(Some(0));
+                self.modal = Some(Modal::ConfigTestFailed { command, test });
+            }
+            CommandOutcome::Ran {
+                test,
+                result,
+                forced,
+            } => {
+                if let Some(test) = &test {
+                    self.record(test, false);
+                }
+                self.record(&result, forced);
+                self.command_output = Some(result);
+                self.output_scroll = 0;
+                self.status_poller.request();
+            }
+            CommandOutcome::Tested(test) => self.finish_test(test),
+        }
+    }
+
+    /// Adds `result` to the history and the audit file.
+    fn record(&mut self, result: &CommandResult, forced: bool) {
+        let entry = HistoryEntry::new(result, forced);
+        self.audit_error = append_audit(&entry).err().map(|err| err.to_string());
+        self.history.push(entry);
+    }
+
+    pub fn scroll_output_down(&mut self, lines: u16) {
+        self.output_scroll = self.output_scroll.saturating_add(lines);
+    }
+
+    pub fn scroll_output_up(&mut self, lines: u16) {
+        self.output_scroll = self.output_scroll.saturating_sub(lines);
+    }
+
+    /// Acts on the input of the open prompt and closes it.
+    pub fn submit_prompt(&mut self) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+        match prompt.kind {
+            PromptKind::GotoLine => {
+                if let (Some(log_view), Ok(line)) =
+                    (self.log_view.as_mut(), prompt.input.trim().parse())
+                {
+                    log_view.goto_line(line);
+                }
+            }
+            PromptKind::Search if prompt.input.is_empty() => self.search = None,
+            PromptKind::Search => match Regex::new(&prompt.input) {
+                Ok(regex) => {
+                    self.search = Some(regex);
+                    self.search_next(true);
+                }
+                Err(err) => self.log_notice = Some(format!("Invalid regex: {}", err)),
+            },
+            PromptKind::Filter if prompt.input.trim().is_empty() => {
+                self.log_filter = None;
+                self.apply_log_filter();
+            }
+            PromptKind::Filter => match LogFilter::parse(&prompt.input) {
+                Ok(filter) => {
+                    self.log_filter = Some(filter);
+                    self.apply_log_filter();
+                }
+                Err(err) => self.log_notice = Some(format!("Invalid filter: {}", err)),
+            },
+            PromptKind::TimeRange if prompt.input.trim().is_empty() => {
+                self.log_range = None;
+                self.open_selected_log();
+            }
+            PromptKind::TimeRange => match TimeRange::parse(&prompt.input) {
+                Ok(range) => {
+                    self.log_range = Some(range);
+                    self.open_selected_log();
+                }
+                Err(err) => self.log_notice = Some(format!("Invalid time range: {}", err)),
+            },
+            PromptKind::TemplateField(_) => {
+                let form = self.selected_template_mut();
+                let i = form.selected();
+                form.values[i] = prompt.input.trim().to_string();
+            }
+            PromptKind::HtpasswdUser if prompt.input.trim().is_empty() => {}
+            PromptKind::HtpasswdUser => {
+                self.htpasswd_user = Some(prompt.input.trim().to_string());
+                self.prompt = Some(Prompt::new(PromptKind::HtpasswdPassword));
+            }
+            PromptKind::HtpasswdPassword => self.set_htpasswd_password(&prompt.input),
+        }
+    }
+
+    /// Scrolls to the next line matching the search, or the previous one.
+    pub fn search_next(&mut self, forward: bool) {
+        let (Some(regex), Some(log_view)) = (&self.search, self.log_view.as_mut()) else {
+            return;
+        };
+        let from = log_view.top();
+        self.log_notice = match log_view.find(from, forward, |line| regex.is_match(line)) {
+            Ok(Some(row)) => {
+                log_view.goto_row(row);
+                None
+            }
+            Ok(None) => Some(format!("Pattern not found: {}", regex)),
+            Err(err) => Some(format!("Failed to read log file: {}", err)),
+        };
+    }
+
+    pub fn selected_template(&self) -> usize {
+        self.template_state.selected().unwrap_or(0)
+    }
+
+    pub fn selected_template_mut(&mut self) -> &mut TemplateForm {
+        let i = self.selected_template();
+        &mut self.templates[i]
+    }
+
+    /// Moves the focus to the next pane of the Templates tab, the Users
+    /// pane only being there for templates with a user file.
+    pub fn toggle_template_focus(&mut self) {
+        self.template_focus = match self.template_focus {
+            TemplateFocus::List => TemplateFocus::Form,
+            TemplateFocus::Form if self.htpasswd.is_some() => TemplateFocus::Users,
+            TemplateFocus::Form | TemplateFocus::Users => TemplateFocus::List,
+        };
+    }
+
+    /// Loads the user file named by the selected template when it changed.
+    fn sync_htpasswd(&mut self) {
+        let path = self.templates[self.selected_template()]
+            .value("user_file")
+            .filter(|path| path.starts_with('/'))
+            .map(PathBuf::from);
+        if self.htpasswd.as_ref().map(|h| &h.path) == path.as_ref()
+            || (path.is_some() && self.htpasswd_failed == path)
+        {
+            return;
+        }
+        self.htpasswd_failed = None;
+        self.htpasswd = path.and_then(|path| match Htpasswd::load(&path) {
+            Ok(htpasswd) => Some(htpasswd),
+            Err(err) => {
+                self.template_notice =
+                    Some(Err(format!("Failed to read {}: {}", path.display(), err)));
+                self.htpasswd_failed = Some(path);
+                None
+            }
+        });
+        self.htpasswd_state.select(Some(0));
+        if self.htpasswd.is_none() && self.template_focus == TemplateFocus::Users {
+            self.template_focus = TemplateFocus::List;
+        }
+    }
+
+    fn selected_htpasswd_user(&self) -> Option<String> {
+        let htpasswd = self.htpasswd.as_ref()?;
+        let (user, _) = htpasswd.users.get(self.htpasswd_state.selected()?)?;
+        Some(user.clone())
+    }
+
+    /// Asks for the name of a user to add.
+    pub fn add_htpasswd_user(&mut self) {
+        if self.htpasswd.is_some() {
+            self.prompt = Some(Prompt::new(PromptKind::HtpasswdUser));
+        }
+    }
+
+    /// Asks for a new password for the selected user.
+    pub fn change_htpasswd_password(&mut self) {
+        if let Some(user) = self.selected_htpasswd_user() {
+            self.htpasswd_user = Some(user);
+            self.prompt = Some(Prompt::new(PromptKind::HtpasswdPassword));
+        }
+    }
+
+    /// Removes the selected user once confirmed in a modal.
+    pub fn remove_htpasswd_user(&mut self, confirmed: bool) {
+        let Some(user) = self.selected_htpasswd_user() else {
+            return;
+        };
+        if !confirmed {
+            self.modal = Some(Modal::RemoveHtpasswdUser { user });
+            return;
+        }
+        if let Some(htpasswd) = self.htpasswd.as_mut() {
+            htpasswd.remove(&user);
+        }
+        self.save_htpasswd(format!("Removed {}", user));
+    }
+
+    fn set_htpasswd_password(&mut self, password: &str) {
+        let scheme = self.templates[self.selected_template()]
+            .value("hash")
+            .and_then(Scheme::parse)
+            .unwrap_or(Scheme::Apr1);
+        let (Some(user), Some(htpasswd)) = (self.htpasswd_user.take(), self.htpasswd.as_mut())
+        else {
+            return;
+        };
+        let adding = !htpasswd.users.iter().any(|(name, _)| *name == user);
+        match htpasswd.set(&user, password, scheme) {
+            Ok(()) if adding => self.save_htpasswd(format!("Added {}", user)),
+            Ok(()) => self.save_htpasswd(format!("Changed the password of {}", user)),
+            Err(err) => self.template_notice = Some(Err(err)),
+        }
+    }
+
+    fn save_htpasswd(&mut self, done: String) {
+        let Some(htpasswd) = &self.htpasswd else {
+            return;
+        };
+        self.template_notice = Some(match htpasswd.save(self.template_context.gid) {
+            Ok(()) => Ok(format!("{} in {}", done, htpasswd.path.display())),
+            Err(err) => Err(format!(
+                "Failed to write {}: {}",
+                htpasswd.path.display(),
+                err
+            )),
+        });
+        // Reread so the list shows what is on disk.
+        let selected = self.htpasswd_state.selected();
+        self.htpasswd = None;
+        self.sync_htpasswd();
+        let len = self.htpasswd.as_ref().map_or(0, |h| h.users.len());
+        self.htpasswd_state
+            .select(selected.map(|i| i.min(len.saturating_sub(1))));
+    }
+
+    /// Flips or cycles the selected field, or opens a prompt to type it in.
+    pub fn edit_template_field(&mut self) {
+        let form = self.selected_template_mut();
+        if form.cycle_selected() {
+            return;
+        }
+        let field = form.selected_field();
+        let mut prompt = Prompt::new(PromptKind::TemplateField(field.label));
+        prompt.input = form.values[form.selected()].clone();
+        self.prompt = Some(prompt);
+    }
+
+    /// Issues a certificate with the values of the selected template, which
+    /// must be the ACME one, in the background.
+    pub fn issue_certificate(&mut self) {
+        if self.acme.as_ref().is_some_and(Issuance::running) {
+            return;
+        }
+        let form = &self.templates[self.selected_template()];
+        if let Err(err) = form.render(&self.template_context) {
+            self.template_notice = Some(Err(err));
+            return;
+        }
+        let value = |name| form.value(name).unwrap_or_default();
+        let path = |name| Some(PathBuf::from(value(name))).filter(|p| p.is_absolute());
+        let (Some(webroot), Some(certificate), Some(key)) =
+            (path("webroot"), path("certificate"), path("key"))
+        else {
+            return;
+        };
+        let order = Order {
+            directory: value("directory").to_string(),
+            ca_bundle: path("ca_bundle"),
+            email: Some(value("email").to_string()).filter(|email| !email.is_empty()),
+            names: value("server_name")
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect(),
+            webroot,
+            port: value("port").parse().unwrap_or(80),
+            certificate,
+            key,
+        };
+        self.template_notice = Some(Ok(format!(
+            "Issuing a certificate for {}",
+            order.names.join(" ")
+        )));
+        self.acme = Some(Issuance::start(order));
+    }
+
+    /// Shows the progress of the issuance under way. Once the certificate
+    /// is written, nginx is reloaded to pick it up and the TLS template set
+    /// to use it.
+    fn poll_acme(&mut self) {
+        let Some(acme) = self.acme.as_mut() else {
+            return;
+        };
+        if !acme.poll() {
+            if acme.running() {
+                self.template_notice = Some(Ok(format!("{}…", acme.step)));
+            }
+            return;
+        }
+        let order = acme.order.clone();
+        match acme.result.clone() {
+            Some(Ok(())) => {
+                if let Some(tls) = self.templates.iter_mut().find(|f| f.template.name == "TLS") {
+                    for (name, value) in [
+                        ("server_name", order.names.join(" ")),
+                        ("certificate", order.certificate.display().to_string()),
+                        ("key", order.key.display().to_string()),
+                    ] {
+                        if let Some(i) = tls.template.fields.iter().position(|f| f.name == name) {
+                            tls.values[i] = value;
+                        }
+                    }
+                }
+                self.reload_certificates();
+                let reload = if self.run_command(ServiceCommand::Reload) {
+                    "Reloading nginx"
+                } else {
+                    "Reload nginx once the running command is done"
+                };
+                self.template_notice = Some(Ok(format!(
+                    "Issued a certificate for {} to {}, the TLS template now uses it. {}.",
+                    order.names.join(" "),
+                    order.certificate.display(),
+                    reload
+                )));
+            }
+            Some(Err(err)) => self.template_notice = Some(Err(format!("Issuing failed: {}", err))),
+            None => {}
+        }
+    }
+
+    /// Renders the selected template again when another one was picked or
+    /// a value changed since [`App::template_preview`] was rendered, rather
+    /// than checking the values on every frame.
+    pub fn refresh_template_preview(&mut self) {
+        let i = self.selected_template();
+        let form = &self.templates[i];
+        if let Some((rendered, values, _)) = &self.template_preview {
+            if *rendered == i && *values == form.values {
+                return;
+            }
+        }
+        let preview = form.render(&self.template_context);
+        self.template_preview = Some((i, form.values.clone(), preview));
+    }
+
+    /// Where the selected template is written to.
+    pub fn template_path(&self) -> PathBuf {
+        let (available, _) = sites_dirs(&self.config_path);
+        available.join(self.templates[self.selected_template()].file_name())
+    }
+
+    /// What the selected template is previewed as: the file it writes, or
+    /// the location it adds to.
+    pub fn template_target(&self) -> String {
+        let form = &self.templates[self.selected_template()];
+        match form.existing_location(&self.template_context) {
+            Some(Ok(location)) => format!(
+                "the lines added to location {} in {}:{}",
+                location.path,
+                location.file.display(),
+                location.line
+            ),
+            Some(Err(_)) => format!(
+                "the lines added to location {}",
+                form.value("location").unwrap_or_default()
+            ),
+            None => self.template_path().display().to_string(),
+        }
+    }
+
+    /// Writes the selected template to `sites-available`, links it from
+    /// `sites-enabled` and tests the config in the background, see
+    /// [`App::finish_test`]. A failing test puts back the file it replaced,
+    /// removes the link if this call made it, and leaves its diagnostics on
+    /// the Config tab.
+    ///
+    /// An existing file is only replaced when `overwrite` is set, otherwise
+    /// a modal asks first. Templates adding to a location of the config
+    /// edit its file instead, see [`App::add_to_location`]. Nothing is
+    /// written while another command is under way.
+    pub fn write_template(&mut self, overwrite: bool) {
+        if let Some(job) = self.command_runner.running {
+            self.template_notice = Some(Err(format!(
+                "Wait for {} to finish before writing.",
+                job.as_str()
+            )));
+            return;
+        }
+        if self.templates[self.selected_template()]
+            .existing_location(&self.template_context)
+            .is_some()
+        {
+            self.add_to_location();
+            return;
+        }
+        let path = self.template_path();
+        let (available, enabled) = sites_dirs(&self.config_path);
+        let config = match self.templates[self.selected_template()].render(&self.template_context) {
+            Ok(config) => config,
+            Err(err) => {
+                self.template_notice = Some(Err(err));
+                return;
+            }
+        };
+        if path.exists() && !overwrite {
+            self.modal = Some(Modal::OverwriteTemplate { path });
+            return;
+        }
+        let previous = match fs::read(&path) {
+            Ok(previous) => Some(previous),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => {
+                self.template_notice =
+                    Some(Err(format!("Failed to read {}: {}", path.display(), err)));
+                return;
+            }
+        };
+        let link = enabled.join(path.file_name().unwrap_or_default());
+        let linked = match create_dir_all(&available)
+            .and_then(|_| create_dir_all(&enabled))
+            .and_then(|_| fs::write(&path, config))
+            .and_then(|_| match link.symlink_metadata() {
+                Ok(_) => Ok(false),
+                Err(_) => symlink(&path, &link).map(|_| true),
+            }) {
+            Ok(linked) => linked,
+            Err(err) => {
+                self.template_notice =
+                    Some(Err(format!("Failed to write {}: {}", path.display(), err)));
+                return;
+            }
+        };
+
+        self.template_notice = Some(Ok(format!("Wrote {}, testing the config…", path.display())));
+        self.start_test(PendingTest::Template {
+            path,
+            previous,
+            link,
+            linked,
+        });
+    }
+
+    /// Adds the selected template to the end of the location it names and
+    /// tests the config in the background, putting the file back as it was
+    /// when the test fails.
+    fn add_to_location(&mut self) {
+        // The offsets found are only good for the files as they are now.
+        self.reload_config();
+        let form = &self.templates[self.selected_template()];
+        let (location, lines) = match (
+            form.existing_location(&self.template_context),
+            form.render(&self.template_context),
+        ) {
+            (Some(Ok(location)), Ok(lines)) => (location.clone(), lines),
+            (Some(Err(err)), _) | (_, Err(err)) => {
+                self.template_notice = Some(Err(err));
+                return;
+            }
+            (None, _) => return,
+        };
+        let file = location.file.display().to_string();
+        let source = match fs::read_to_string(&location.file) {
+            Ok(source) => source,
+            Err(err) => {
+                self.template_notice = Some(Err(format!("Failed to read {}: {}", file, err)));
+                return;
+            }
+        };
+        let edited = match insert_in_block(&source, location.end, &lines) {
+            Ok(edited) => edited,
+            Err(err) => {
+                self.template_notice = Some(Err(format!("Cannot edit {}: {}", file, err)));
+                return;
+            }
+        };
+        if let Err(err) = fs::write(&location.file, edited) {
+            self.template_notice = Some(Err(format!("Failed to write {}: {}", file, err)));
+            return;
+        }
+
+        self.template_notice = Some(Ok(format!(
+            "Added to location {} in {}, testing the config…",
+            location.path, file
+        )));
+        self.start_test(PendingTest::Location {
+            location: location.path,
+            file: location.file,
+            source,
+        });
+    }
+
+    /// Starts `nginx -t` in the background, to be handled as `pending`
+    /// once it finishes. Should the runner be gone, the test counts as
+    /// failed so edits are still undone.
+    fn start_test(&mut self, pending: PendingTest) {
+        self.pending_test = Some(pending);
+        if !self.command_runner.test(&self.config_path) {
+            self.finish_test(ConfigTest {
+                success: false,
+                diagnostics: Vec::new(),
+                result: CommandResult::failed("nginx -t", "The command runner is not running."),
+            });
+        }
+    }
+
+    /// Records the outcome of the `nginx -t` started by
+    /// [`App::start_test`] and acts on it: shows it on the Config tab, and
+    /// keeps or undoes the edit made before it.
+    fn finish_test(&mut self, test: ConfigTest) {
+        self.record(&test.result, false);
+        self.diagnostics_state.select(Some(0));
+        match self.pending_test.take().unwrap_or(PendingTest::Show) {
+            PendingTest::Show => {
+                self.config_focus = if test.diagnostics.is_empty() {
+                    ConfigFocus::Tree
+                } else {
+                    ConfigFocus::Diagnostics
+                };
+            }
+            PendingTest::Template { path, .. } if test.success => {
+                let (_, enabled) = sites_dirs(&self.config_path);
+                let mut notice = format!(
+                    "Wrote {} and enabled it, reload nginx to apply.",
+                    path.display()
+                );
+                if !includes_dir(&self.config, &self.config_path, &enabled) {
+                    notice = format!(
+                        "Wrote {}, but {} does not include {}/*.",
+                        path.display(),
+                        self.config_path.display(),
+                        enabled.display()
+                    );
+                }
+                self.template_notice = Some(Ok(notice));
+                self.reload_config();
+            }
+            PendingTest::Template {
+                path,
+                previous,
+                link,
+                linked,
+            } => {
+                if linked {
+                    let _ = fs::remove_file(&link);
+                }
+                let restored = match &previous {
+                    Some(previous) => fs::write(&path, previous),
+                    // Enabled through a link that was there before.
+                    None if !linked => fs::remove_file(&path),
+                    None => Ok(()),
+                };
+                let notice = match (restored, previous) {
+                    (Err(err), _) => format!(
+                        "The config test failed with the new {}, and undoing the write failed \
+                         too: {}. See the Config tab.",
+                        path.display(),
+                        err
+                    ),
+                    (Ok(()), Some(_)) => format!(
+                        "Kept {} as it was, as the config test failed with the new one, see \
+                         the Config tab.",
+                        path.display()
+                    ),
+                    (Ok(()), None) if linked => format!(
+                        "Wrote {}, but left it disabled as the config test failed, see the \
+                         Config tab.",
+                        path.display()
+                    ),
+                    (Ok(()), None) => format!(
+                        "Did not write {} as the config test failed with it, see the Config \
+                         tab.",
+                        path.display()
+                    ),
+                };
+                self.template_notice = Some(Err(notice));
+            }
+            PendingTest::Location { location, file, .. } if test.success => {
+                self.template_notice = Some(Ok(format!(
+                    "Added to location {} in {}, reload nginx to apply.",
+                    location,
+                    file.display()
+                )));
+                self.reload_config();
+            }
+            PendingTest::Location { file, source, .. } => {
+                self.template_notice = Some(Err(match fs::write(&file, source) {
+                    Ok(()) => format!(
+                        "Left {} as it was, as the config test failed with the lines added, \
+                         see the Config tab.",
+                        file.display()
+                    ),
+                    Err(err) => format!(
+                        "The config test failed with the lines added to {}, and putting it \
+                         back failed too: {}. See the Config tab.",
+                        file.display(),
+                        err
+                    ),
+                }));
+            }
+        }
+        self.config_test = Some(test);
+    }
+
+    /// The log picked on the Logs tab.
+    pub fn selected_log(&self) -> Option<&LogLocation> {
+        self.log_locations.get(self.log_list_state.selected()?)
+    }
+
+    fn open_selected_log(&mut self) {
+        self.log_format = self
+            .selected_log()
+            .and_then(|log| log.format.as_deref())
+            .and_then(|name| find_log_format(&self.config, name));
+        let source = self.log_source();
+        let range = self
+            .log_range
+            .clone()
+            .filter(|_| !matches!(source, LogSource::Raw));
+        let files = match (self.selected_log(), &range) {
+            (Some(log), Some(range)) => {
+                let files = files_in_range(&log_files(log), range, &source);
+                self.log_notice = files
+                    .is_empty()
+                    .then(|| format!("Nothing was logged in {}", range));
+                files
+            }
+            (log, _) => log.map(|log| log.files.clone()).unwrap_or_default(),
+        };
+        let shown = self
+            .log_view
+            .as_ref()
+            .map(|view| (&view.files, &view.range));
+        if shown != Some((&files, &range)) {
+            self.log_view = (!files.is_empty())
+                .then(|| LogTailer::with_range(&files, range.map(|range| (range, source))));
+            self.apply_log_filter();
+            if self.log_format.is_some() {
+                self.open_dashboard();
+            }
+        }
+    }
+
+    /// Shows the picked access log on the Dashboard tab, or the first one
+    /// in the config when an error log is picked.
+    fn open_dashboard(&mut self) {
+        let readable = |log: &LogLocation| {
+            if log.kind != LogKind::Access || log.files.is_empty() {
+                return None;
+            }
+            let format = find_log_format(&self.config, log.format.as_deref()?)?;
+            Some((log.files.clone(), format))
+        };
+        let Some((files, format)) = self.selected_log().and_then(readable).or_else(|| {
+            self.log_locations
+                .iter()
+                .filter(|log| log.rotation.is_none())
+                .find_map(readable)
+        }) else {
+            self.dashboard = None;
+            return;
+        };
+        if self.dashboard.as_ref().map(|dashboard| dashboard.files()) != Some(&files[..]) {
+            let window = self.dashboard.as_ref().map(|dashboard| dashboard.window);
+            let mut dashboard = Dashboard::new(&files, format);
+            dashboard.window = window.unwrap_or(dashboard.window);
+            dashboard.poll(self.log_view.as_mut());
+            self.dashboard = Some(dashboard);
+        }
+    }
+
+    /// Shows only error log lines at least as severe as the next level.
+    pub fn cycle_error_level(&mut self) {
+        self.error_level = next_min_level(self.error_level);
+        self.apply_log_filter();
+    }
+
+    /// How the lines of the picked log are parsed for filters.
+    fn log_source(&self) -> LogSource {
+        match (self.selected_log().map(|log| log.kind), &self.log_format) {
+            (Some(LogKind::Error), _) => LogSource::Error,
+            (_, Some(format)) => LogSource::Access(format.clone()),
+            _ => LogSource::Raw,
+        }
+    }
+
+    /// Applies the minimum level of error logs and the filter to the log view.
+    fn apply_log_filter(&mut self) {
+        let is_error_log = self
+            .selected_log()
+            .is_some_and(|log| log.kind == LogKind::Error);
+        let level = (is_error_log && self.error_level != Level::Debug).then_some(self.error_level);
+        let filter = self.log_filter.clone();
+        let source = self.log_source();
+        let Some(log_view) = self.log_view.as_mut() else {
+            return;
+        };
+        if level.is_none() && filter.is_none() {
+            if log_view.filtered() {
+                log_view.set_filter(None);
+            }
+            return;
+        }
+        log_view.set_filter(Some(Box::new(move |line| {
+            level.is_none_or(|level| line_level(line).is_some_and(|l| l <= level))
+                && filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(line, &source))
+        })));
+    }
+
+    /// Reads the certificate files again, such as after they were renewed.
+    pub fn reload_certificates(&mut self) {
+        self.certificates = find_certificates(&self.config, &self.config_path);
+        if self
+            .certificate_state
+            .selected()
+            .is_some_and(|i| i >= self.certificates.len())
+        {
+            self.certificate_state.select(Some(0));
+        }
+    }
+
+    /// Parses the config again, picking up edits made since startup.
+    pub fn reload_config(&mut self) {
+        self.config = Config::load(&self.config_path);
+        self.config_tree = ConfigTree::new(&self.config);
+        self.log_locations = get_log_locations(&self.config);
+        self.template_context = Context::new(&self.config, self.nginx_version.as_ref());
+        self.template_preview = None;
+        self.reload_certificates();
+        if self
+            .log_list_state
+            .selected()
+            .is_none_or(|i| i >= self.log_locations.len())
+        {
+            self.log_list_state.select(Some(0));
+        }
+        self.open_selected_log();
+        // Keep showing the log picked before, unless it is gone.
+        let shown = self.dashboard.as_ref().is_some_and(|dashboard| {
+            self.log_locations
+                .iter()
+                .any(|log| log.files == dashboard.files())
+        });
+        if !shown {
+            self.open_dashboard();
+        }
+        if self
+            .stub_status
+            .as_ref()
+            .is_none_or(|monitor| monitor.detected)
+        {
+            let endpoint = find_stub_status(&self.config);
+            if self.stub_status.as_ref().map(|monitor| &monitor.endpoint) != endpoint.as_ref() {
+                self.stub_status = endpoint
+                    .map(|endpoint| StubStatusMonitor::new(endpoint, true, self.refresh_interval));
+            }
+        }
+    }
+
+    /// Runs `nginx -t` in the background and shows its diagnostics on the
+    /// Config tab once it finishes. Does nothing while another command is
+    /// under way.
+    pub fn test_config(&mut self) {
+        self.select_screen(Screen::Config);
+        if self.command_runner.running.is_some() {
+            return;
+        }
+        self.reload_config();
+        self.start_test(PendingTest::Show);
+    }
+
+    pub fn toggle_config_focus(&mut self) {
+        self.config_focus = match self.config_focus {
+            ConfigFocus::Tree if self.config_test.is_some() => ConfigFocus::Diagnostics,
+            _ => ConfigFocus::Tree,
+        };
+    }
+
+    /// Shows the line the selected diagnostic points at in the config viewer.
+    pub fn jump_to_diagnostic(&mut self) {
+        let Some(test) = &self.config_test else {
+            return;
+        };
+        let Some(diagnostic) = self
+            .diagnostics_state
+            .selected()
+            .and_then(|i| test.diagnostics.get(i))
+        else {
+            return;
+        };
+        if let (Some(file), Some(line)) = (&diagnostic.file, diagnostic.line) {
+            self.config_tree.reveal(&self.config, file, line);
+            self.config_focus = ConfigFocus::Tree;
+        }
+    }
+
+    fn diagnostics_len(&self) -> usize {
+        self.config_test
+            .as_ref()
+            .map_or(0, |test| test.diagnostics.len())
+    }
 }
@@ -0,0 +1,653 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use tui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Row, Table, Wrap},
+};
+use x509_parser::{
+    asn1_rs::{Any, Class, FromDer, Tag},
+    extensions::GeneralName,
+    pem::Pem,
+    public_key::PublicKey,
+    x509::SubjectPublicKeyInfo,
+};
+
+use crate::config::{Config, ConfigError, Directive};
+
+/// A certificate named by `ssl_certificate`, with the key paired with it.
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    pub path: PathBuf,
+    /// The `ssl_certificate_key`, `None` when the config has none for it.
+    pub key_path: Option<PathBuf>,
+    /// `file:line` of every directive naming this certificate and key.
+    pub references: Vec<String>,
+    /// What the file holds, or why it could not be read.
+    pub info: Result<CertificateInfo, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub common_name: Option<String>,
+    /// DNS names and IP addresses of the subject alternative name extension.
+    pub names: Vec<String>,
+    pub issuer: String,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// Such as `RSA 2048` or `EC P-256`.
+    pub key_type: String,
+    /// Whether the key file holds the private half of the certificate's key,
+    /// or why that could not be told.
+    pub key_matches: Result<bool, String>,
+}
+
+impl Certificate {
+    /// Whole days left until the certificate expires, negative once it
+    /// has. Rounded towards zero, so 0 on either side of the expiry: tell
+    /// those apart with [`Certificate::expired`].
+    pub fn days_left(&self, now: DateTime<Utc>) -> Option<i64> {
+        let info = self.info.as_ref().ok()?;
+        Some((info.not_after - now).num_days())
+    }
+
+    /// Whether the certificate is no longer valid at `now`.
+    pub fn expired(&self, now: DateTime<Utc>) -> bool {
+        self.info.as_ref().is_ok_and(|info| info.not_after < now)
+    }
+
+    /// Whether the certificate expires within `days`, or already has.
+    pub fn expires_within(&self, days: u32, now: DateTime<Utc>) -> bool {
+        self.days_left(now).is_some_and(|left| left < days as i64)
+    }
+
+    /// The first DNS name, or the common name when there are none.
+    pub fn name(&self) -> String {
+        match &self.info {
+            Ok(info) => info
+                .names
+                .first()
+                .or(info.common_name.as_ref())
+                .cloned()
+                .unwrap_or_else(|| info.subject.clone()),
+            Err(_) => file_name(&self.path),
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Every certificate the config names, each pair of certificate and key
+/// once. Relative paths are taken from the directory of the main file, as
+/// nginx does for the default prefix.
+pub fn find_certificates(
+    config: &Result<Config, ConfigError>,
+    config_path: &Path,
+) -> Vec<Certificate> {
+    let Ok(config) = config else {
+        return Vec::new();
+    };
+    let base = config_path.parent().unwrap_or(Path::new("/"));
+    let mut pairs: Vec<(String, Option<String>, String)> = Vec::new();
+    config.walk(|directive, parents| {
+        if directive.name != "ssl_certificate" {
+            return;
+        }
+        let Some(path) = directive.arg(0) else {
+            return;
+        };
+        let key = find_key(config, directive, parents);
+        let reference = format!(
+            "{}:{}",
+            config.path(&directive.span).display(),
+            directive.span.start_line
+        );
+        pairs.push((path.to_string(), key.map(str::to_string), reference));
+    });
+
+    let mut certificates: Vec<Certificate> = Vec::new();
+    for (path, key, reference) in pairs {
+        let path = base.join(path);
+        let key_path = key.map(|key| base.join(key));
+        match certificates
+            .iter_mut()
+            .find(|c| c.path == path && c.key_path == key_path)
+        {
+            Some(certificate) => certificate.references.push(reference),
+            None => certificates.push(Certificate {
+                info: read_certificate(&path, key_path.as_deref()),
+                path,
+                key_path,
+                references: vec![reference],
+            }),
+        }
+    }
+    certificates
+}
+
+/// The `ssl_certificate_key` going with `certificate`: the one in the same
+/// position among the keys of the nearest block that has any, as nginx
+/// inherits certificates and keys separately.
+fn find_key<'c>(
+    config: &'c Config,
+    certificate: &Directive,
+    parents: &[&'c Directive],
+) -> Option<&'c str> {
+    let siblings = |block: Option<&&'c Directive>| match block {
+        Some(block) => config.expand(block.children()),
+        None => config.expand(&config.root().nodes),
+    };
+    let position = siblings(parents.last())
+        .iter()
+        .filter(|d| d.name == "ssl_certificate")
+        .position(|d| std::ptr::eq(*d, certificate))?;
+    (0..=parents.len()).rev().find_map(|depth| {
+        let keys: Vec<&Directive> = siblings(depth.checked_sub(1).map(|i| &parents[i]))
+            .into_iter()
+            .filter(|d| d.name == "ssl_certificate_key")
+            .collect();
+        (!keys.is_empty()).then(|| keys.get(position).and_then(|key| key.arg(0)))?
+    })
+}
+
+fn read_certificate(path: &Path, key_path: Option<&Path>) -> Result<CertificateInfo, String> {
+    let raw = path.to_string_lossy();
+    if raw.contains('$') {
+        return Err("Named with variables, picked per connection".to_string());
+    }
+    if raw.contains("data:") {
+        return Err("Given inline in the config".to_string());
+    }
+    let contents = fs::read(path).map_err(|err| err.to_string())?;
+    let pem = Pem::iter_from_buffer(&contents)
+        .filter_map(Result::ok)
+        .find(|pem| pem.label == "CERTIFICATE")
+        .ok_or("No PEM certificate in the file")?;
+    let certificate = pem
+        .parse_x509()
+        .map_err(|err| format!("Invalid certificate: {}", err))?;
+
+    let mut names = Vec::new();
+    if let Ok(Some(san)) = certificate.subject_alternative_name() {
+        for name in &san.value.general_names {
+            match name {
+                GeneralName::DNSName(name) => names.push(name.to_string()),
+                GeneralName::IPAddress(ip) => names.push(ip_address(ip)),
+                _ => {}
+            }
+        }
+    }
+    let common_name = certificate
+        .subject()
+        .iter_common_name()
+        .find_map(|name| Some(name.as_str().ok()?.to_string()));
+    let validity = certificate.validity();
+    let time = |time: i64| DateTime::from_timestamp(time, 0).unwrap_or_default();
+    let key_matches = match key_path {
+        None => Err("No ssl_certificate_key".to_string()),
+        Some(key_path) => {
+            read_public_key(key_path).map(|key| same_key(certificate.public_key(), &key))
+        }
+    };
+    Ok(CertificateInfo {
+        subject: certificate.subject().to_string(),
+        common_name,
+        names,
+        issuer: certificate
+            .issuer()
+            .iter_organization()
+            .chain(certificate.issuer().iter_common_name())
+            .filter_map(|name| name.as_str().ok())
+            .collect::<Vec<_>>()
+            .join(", "),
+        not_before: time(validity.not_before.timestamp()),
+        not_after: time(validity.not_after.timestamp()),
+        key_type: key_type(certificate.public_key()),
+        key_matches,
+    })
+}
+
+fn ip_address(bytes: &[u8]) -> String {
+    match bytes.len() {
+        4 => format!("{}.{}.{}.{}", bytes[0], bytes[1], bytes[2], bytes[3]),
+        16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(bytes);
+            std::net::Ipv6Addr::from(octets).to_string()
+        }
+        _ => format!("{:02x?}", bytes),
+    }
+}
+
+/// OIDs of the key algorithms and curves told apart, with their names.
+const ALGORITHMS: &[(&str, &str)] = &[
+    ("1.2.840.113549.1.1.1", "RSA"),
+    ("1.2.840.10045.2.1", "EC"),
+    ("1.3.101.112", "Ed25519"),
+    ("1.3.101.113", "Ed448"),
+];
+const CURVES: &[(&str, &str)] = &[
+    ("1.2.840.10045.3.1.7", "P-256"),
+    ("1.3.132.0.34", "P-384"),
+    ("1.3.132.0.35", "P-521"),
+];
+
+fn oid_name(names: &[(&str, &'static str)], oid: &str) -> Option<&'static str> {
+    names
+        .iter()
+        .find(|(id, _)| *id == oid)
+        .map(|(_, name)| *name)
+}
+
+fn key_type(key: &SubjectPublicKeyInfo) -> String {
+    let algorithm = key.algorithm.algorithm.to_id_string();
+    match (oid_name(ALGORITHMS, &algorithm), key.parsed()) {
+        (_, Ok(PublicKey::RSA(rsa))) => format!("RSA {}", rsa_bits(rsa.modulus)),
+        (_, Ok(PublicKey::EC(point))) => {
+            let curve = key
+                .algorithm
+                .parameters
+                .as_ref()
+                .and_then(|parameters| parameters.as_oid().ok())
+                .map(|oid| oid.to_id_string());
+            match curve.as_deref().and_then(|curve| oid_name(CURVES, curve)) {
+                Some(curve) => format!("EC {}", curve),
+                None => format!("EC {}", point.key_size()),
+            }
+        }
+        (Some(name), _) => name.to_string(),
+        (None, _) => algorithm,
+    }
+}
+
+fn rsa_bits(modulus: &[u8]) -> usize {
+    let modulus = strip_zeros(modulus);
+    match modulus.first() {
+        Some(first) => modulus.len() * 8 - first.leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// The public half of a private key, as far as it can be told without
+/// doing the key's arithmetic.
+#[derive(Debug, PartialEq)]
+enum KeyPublic {
+    Rsa {
+        modulus: Vec<u8>,
+        exponent: Vec<u8>,
+    },
+    /// The encoded point or key, as in the certificate.
+    Raw(Vec<u8>),
+}
+
+fn same_key(certificate: &SubjectPublicKeyInfo, key: &KeyPublic) -> bool {
+    match (certificate.parsed(), key) {
+        (Ok(PublicKey::RSA(rsa)), KeyPublic::Rsa { modulus, exponent }) => {
+            strip_zeros(rsa.modulus) == modulus.as_slice()
+                && strip_zeros(rsa.exponent) == exponent.as_slice()
+        }
+        (_, KeyPublic::Raw(raw)) => certificate.subject_public_key.data.as_ref() == raw.as_slice(),
+        _ => false,
+    }
+}
+
+/// Reads the public key out of a PKCS#1, SEC1 or PKCS#8 private key.
+fn read_public_key(path: &Path) -> Result<KeyPublic, String> {
+    let raw = path.to_string_lossy();
+    if raw.contains('$') || raw.contains("data:") || raw.contains("engine:") {
+        return Err("Key not in a file".to_string());
+    }
+    let contents = fs::read(path).map_err(|err| err.to_string())?;
+    let pem = Pem::iter_from_buffer(&contents)
+        .filter_map(Result::ok)
+        .find(|pem| pem.label.ends_with("PRIVATE KEY"))
+        .ok_or("No PEM private key in the file")?;
+    let invalid = || "Invalid private key".to_string();
+    match pem.label.as_str() {
+        "RSA PRIVATE KEY" => rsa_public_key(&pem.contents).ok_or_else(invalid),
+        "EC PRIVATE KEY" => ec_public_key(&pem.contents)?.ok_or_else(invalid),
+        "PRIVATE KEY" => pkcs8_public_key(&pem.contents),
+        "ENCRYPTED PRIVATE KEY" => Err("The key is encrypted".to_string()),
+        label => Err(format!("Unknown key type {}", label)),
+    }
+}
+
+/// The elements of the DER sequence `der`.
+fn sequence(der: &[u8]) -> Option<Vec<Any<'_>>> {
+    let (_, any) = Any::from_der(der).ok()?;
+    if any.header.tag() != Tag::Sequence {
+        return None;
+    }
+    elements(any.data)
+}
+
+/// The DER values one after the other in `data`.
+fn elements(data: &[u8]) -> Option<Vec<Any<'_>>> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let (remainder, element) = Any::from_der(rest).ok()?;
+        out.push(element);
+        rest = remainder;
+    }
+    Some(out)
+}
+
+/// `RSAPrivateKey ::= SEQUENCE { version, modulus, publicExponent, ... }`
+fn rsa_public_key(der: &[u8]) -> Option<KeyPublic> {
+    let elements = sequence(der)?;
+    let (modulus, exponent) = (elements.get(1)?, elements.get(2)?);
+    Some(KeyPublic::Rsa {
+        modulus: strip_zeros(modulus.data).to_vec(),
+        exponent: strip_zeros(exponent.data).to_vec(),
+    })
+}
+
+/// `ECPrivateKey ::= SEQUENCE { version, privateKey, [0] parameters
+/// OPTIONAL, [1] publicKey OPTIONAL }`, `None` when the public key is left
+/// out.
+fn ec_public_key(der: &[u8]) -> Result<Option<KeyPublic>, String> {
+    let elements = sequence(der).ok_or("Invalid private key")?;
+    let Some(public) = context_tagged(&elements, 1) else {
+        return Err("The key does not include its public half".to_string());
+    };
+    // Explicitly tagged BIT STRING, whose first byte counts unused bits.
+    let (_, bits) = Any::from_der(public.data).map_err(|_| "Invalid private key")?;
+    Ok(bits
+        .data
+        .get(1..)
+        .map(|point| KeyPublic::Raw(point.to_vec())))
+}
+
+/// `PrivateKeyInfo ::= SEQUENCE { version, algorithm, privateKey,
+/// [0] attributes OPTIONAL, [1] publicKey OPTIONAL }`
+fn pkcs8_public_key(der: &[u8]) -> Result<KeyPublic, String> {
+    let invalid = || "Invalid private key".to_string();
+    let fields = sequence(der).ok_or_else(invalid)?;
+    let algorithm = fields
+        .get(1)
+        .and_then(|algorithm| elements(algorithm.data))
+        .and_then(|algorithm| Some(algorithm.first()?.as_oid().ok()?.to_id_string()))
+        .ok_or_else(invalid)?;
+    let private = fields.get(2).ok_or_else(invalid)?.data;
+    match oid_name(ALGORITHMS, &algorithm) {
+        Some("RSA") => rsa_public_key(private).ok_or_else(invalid),
+        Some("EC") => ec_public_key(private)?.ok_or_else(invalid),
+        // Implicitly tagged BIT STRING in version 2 keys only.
+        Some(name) => match context_tagged(&fields, 1) {
+            Some(public) => Ok(KeyPublic::Raw(
+                public.data.get(1..).unwrap_or_default().to_vec(),
+            )),
+            None => Err(format!(
+                "Cannot check {} keys without their public half",
+                name
+            )),
+        },
+        None => Err(format!("Unknown key algorithm {}", algorithm)),
+    }
+}
+
+fn context_tagged<'a>(elements: &'a [Any<'a>], tag: u32) -> Option<&'a Any<'a>> {
+    elements
+        .iter()
+        .find(|e| e.header.class() == Class::ContextSpecific && e.header.tag() == Tag(tag))
+}
+
+fn expiry_style(certificate: &Certificate, warn_days: u32, now: DateTime<Utc>) -> Style {
+    if certificate.expired(now) {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else if certificate.expires_within(warn_days, now) {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    }
+}
+
+/// One row per certificate, those expiring within `warn_days` highlighted.
+pub fn certificates_component(
+    certificates: &[Certificate],
+    warn_days: u32,
+    now: DateTime<Utc>,
+) -> Table<'static> {
+    let rows: Vec<_> = certificates
+        .iter()
+        .map(|certificate| {
+            let style = expiry_style(certificate, warn_days, now);
+            let cells = match &certificate.info {
+                Ok(info) => vec![
+                    certificate.name(),
+                    info.issuer.clone(),
+                    info.not_after.format("%Y-%m-%d").to_string(),
+                    certificate
+                        .days_left(now)
+                        .map_or_else(String::new, |left| left.to_string()),
+                    info.key_type.clone(),
+                    match info.key_matches {
+                        Ok(true) => "yes".to_string(),
+                        Ok(false) => "NO".to_string(),
+                        Err(_) => "?".to_string(),
+                    },
+                ],
+                Err(err) => vec![certificate.name(), err.clone()],
+            };
+            let style = match &certificate.info {
+                Ok(info) if info.key_matches == Ok(false) => style.fg(Color::Red),
+                Err(_) => Style::default().fg(Color::DarkGray),
+                Ok(_) => style,
+            };
+            Row::new(cells).style(style)
+        })
+        .collect();
+    Table::new(rows)
+        .header(
+            Row::new(vec!["Name", "Issuer", "Expires", "Days", "Key", "Matches"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .widths(&[
+            Constraint::Percentage(30),
+            Constraint::Percentage(26),
+            Constraint::Length(10),
+            Constraint::Length(5),
+            Constraint::Length(10),
+            Constraint::Length(7),
+        ])
+        .column_spacing(2)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+}
+
+/// Everything known about `certificate`.
+pub fn certificate_component(
+    certificate: &Certificate,
+    warn_days: u32,
+    now: DateTime<Utc>,
+) -> Paragraph<'static> {
+    let label = |label: &str| {
+        Span::styled(
+            format!("{:<12}", label),
+            Style::default().fg(Color::DarkGray),
+        )
+    };
+    let mut lines = vec![Line::from(vec![
+        label("Certificate"),
+        Span::raw(certificate.path.display().to_string()),
+    ])];
+    lines.push(Line::from(vec![
+        label("Key"),
+        Span::raw(
+            certificate
+                .key_path
+                .as_ref()
+                .map_or_else(|| "none".to_string(), |path| path.display().to_string()),
+        ),
+    ]));
+    match &certificate.info {
+        Ok(info) => {
+            let left = certificate.days_left(now).unwrap_or_default();
+            let expiry = match left {
+                0 if certificate.expired(now) => "expired today".to_string(),
+                left if left < 0 => format!("expired {} days ago", -left),
+                left => format!("{} days left", left),
+            };
+            lines.push(Line::from(vec![
+                label("Subject"),
+                Span::raw(info.subject.clone()),
+            ]));
+            if !info.names.is_empty() {
+                lines.push(Line::from(vec![
+                    label("Names"),
+                    Span::raw(info.names.join(" ")),
+                ]));
+            }
+            lines.extend([
+                Line::from(vec![label("Issuer"), Span::raw(info.issuer.clone())]),
+                Line::from(vec![
+                    label("Valid"),
+                    Span::raw(format!(
+                        "{} to {}, ",
+                        info.not_before.format("%Y-%m-%d %H:%M"),
+                        info.not_after.format("%Y-%m-%d %H:%M UTC"),
+                    )),
+                    Span::styled(expiry, expiry_style(certificate, warn_days, now)),
+                ]),
+                Line::from(vec![label("Key type"), Span::raw(info.key_type.clone())]),
+                Line::from(vec![
+                    label("Key matches"),
+                    match &info.key_matches {
+                        Ok(true) => Span::styled("yes", Style::default().fg(Color::Green)),
+                        Ok(false) => Span::styled(
+                            "no, nginx will refuse to start",
+                            Style::default().fg(Color::Red),
+                        ),
+                        Err(err) => Span::styled(
+                            format!("unknown: {}", err),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                    },
+                ]),
+            ]);
+        }
+        Err(err) => lines.push(Line::from(vec![
+            label("Error"),
+            Span::styled(err.clone(), Style::default().fg(Color::Red)),
+        ])),
+    }
+    for (i, reference) in certificate.references.iter().enumerate() {
+        lines.push(Line::from(vec![
+            label(if i == 0 { "Used in" } else { "" }),
+            Span::raw(reference.clone()),
+        ]));
+    }
+    Paragraph::new(lines).wrap(Wrap { trim: false })
+}
+
+/// A warning for the Status tab when certificates expire within
+/// `warn_days`.
+pub fn expiry_warning(
+    certificates: &[Certificate],
+    warn_days: u32,
+    now: DateTime<Utc>,
+) -> Option<Line<'static>> {
+    let expiring: Vec<&Certificate> = certificates
+        .iter()
+        .filter(|c| c.expires_within(warn_days, now))
+        .collect();
+    let soonest = expiring
+        .iter()
+        .min_by_key(|c| c.info.as_ref().ok().map(|info| info.not_after))?;
+    let left = soonest.days_left(now).unwrap_or_default();
+    let when = match left {
+        0 if soonest.expired(now) => "expired today".to_string(),
+        left if left < 0 => format!("expired {} days ago", -left),
+        0 => "expires today".to_string(),
+        1 => "expires tomorrow".to_string(),
+        left => format!("expires in {} days", left),
+    };
+    let mut text = format!("Certificate for {} {}", soonest.name(), when);
+    if expiring.len() > 1 {
+        text.push_str(&format!(
+            ", {} more expire within {} days",
+            expiring.len() - 1,
+            warn_days
+        ));
+    }
+    text.push_str(". See the Certificates tab.");
+    let color = if soonest.expired(now) {
+        Color::Red
+    } else {
+        Color::Yellow
+    };
+    Some(Line::styled(
+        text,
+        Style::default().fg(color).add_modifier(Modifier::BOLD),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    fn expiring(not_after: DateTime<Utc>) -> Certificate {
+        Certificate {
+            path: PathBuf::from("/etc/ssl/example.com.crt"),
+            key_path: None,
+            references: Vec::new(),
+            info: Ok(CertificateInfo {
+                subject: "CN=example.com".to_string(),
+                common_name: Some("example.com".to_string()),
+                names: vec!["example.com".to_string()],
+                issuer: "CN=Test CA".to_string(),
+                not_before: not_after - Duration::days(90),
+                not_after,
+                key_type: "EC P-256".to_string(),
+                key_matches: Ok(true),
+            }),
+        }
+    }
+
+    fn warning(certificate: Certificate, now: DateTime<Utc>) -> String {
+        let line = expiry_warning(&[certificate], 14, now).unwrap();
+        line.spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn tells_expired_from_expiring_within_the_day() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+        let soon = expiring(now + Duration::hours(5));
+        assert_eq!(soon.days_left(now), Some(0));
+        assert!(!soon.expired(now));
+        assert!(warning(soon, now).contains("expires today"));
+
+        let gone = expiring(now - Duration::hours(5));
+        assert_eq!(gone.days_left(now), Some(0));
+        assert!(gone.expired(now));
+        assert!(gone.expires_within(14, now));
+        assert!(warning(gone, now).contains("expired today"));
+
+        let long_gone = expiring(now - Duration::days(3) - Duration::hours(1));
+        assert!(warning(long_gone, now).contains("expired 3 days ago"));
+
+        assert!(!expiring(now).expired(now));
+        assert!(expiry_warning(&[expiring(now + Duration::days(20))], 14, now).is_none());
+    }
+}
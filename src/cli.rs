@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+/// Usage shown for `--help` and invalid arguments.
+pub const USAGE: &str = "\
+Usage: lazynginx [OPTIONS]
+
+Options:
+      --service-manager <NAME>  How to control nginx: systemd, openrc, sysv or signal
+                                (nginx -s). Detected from the host by default.
+      --refresh <SECONDS>       How often live data such as the status is refreshed
+                                [default: 2]
+      --stub-status <URL>       http:// URL of the stub_status page to chart. Found in
+                                the config by default.
+      --expiry-warning <DAYS>   Certificates expiring within this many days are
+                                highlighted [default: 30]
+  -h, --help                    Print this help";
+
+/// Command line arguments.
+#[derive(Debug)]
+pub struct Args {
+    pub service_manager: Option<String>,
+    pub refresh_interval: Duration,
+    pub stub_status: Option<String>,
+    pub expiry_warning: u32,
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            service_manager: None,
+            refresh_interval: Duration::from_secs(2),
+            stub_status: None,
+            expiry_warning: 30,
+            help: false,
+        }
+    }
+}
+
+/// Parses the arguments following the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} needs a value", flag))
+        };
+        match flag.as_str() {
+            "--service-manager" => parsed.service_manager = Some(value()?),
+            "--refresh" => {
+                let seconds: f64 = value()?
+                    .parse()
+                    .map_err(|_| format!("{} needs a number of seconds", flag))?;
+                parsed.refresh_interval = Duration::try_from_secs_f64(seconds)
+                    .ok()
+                    .filter(|interval| !interval.is_zero())
+                    .ok_or_else(|| format!("{} needs a positive number of seconds", flag))?;
+            }
+            "--stub-status" => parsed.stub_status = Some(value()?),
+            "--expiry-warning" => {
+                parsed.expiry_warning = value()?
+                    .parse()
+                    .map_err(|_| format!("{} needs a number of days", flag))?;
+            }
+            "-h" | "--help" if inline.is_none() => parsed.help = true,
+            _ => return Err(format!("unexpected argument {}", flag)),
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.service_manager, None);
+        assert_eq!(args.refresh_interval, Duration::from_secs(2));
+        assert_eq!(args.stub_status, None);
+        assert_eq!(args.expiry_warning, 30);
+        assert!(!args.help);
+    }
+
+    #[test]
+    fn parses_each_flag() {
+        let args = parse(&[
+            "--service-manager",
+            "openrc",
+            "--refresh=0.5",
+            "--stub-status",
+            "http://127.0.0.1:8080/nginx_status",
+            "--expiry-warning=14",
+        ])
+        .unwrap();
+        assert_eq!(args.service_manager.as_deref(), Some("openrc"));
+        assert_eq!(args.refresh_interval, Duration::from_millis(500));
+        assert_eq!(
+            args.stub_status.as_deref(),
+            Some("http://127.0.0.1:8080/nginx_status")
+        );
+        assert_eq!(args.expiry_warning, 14);
+
+        // Values may contain `=` themselves.
+        let args = parse(&["--stub-status=http://localhost/status?a=b"]).unwrap();
+        assert_eq!(
+            args.stub_status.as_deref(),
+            Some("http://localhost/status?a=b")
+        );
+        assert!(parse(&["-h"]).unwrap().help);
+        assert!(parse(&["--refresh", "3", "--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn rejects_missing_and_invalid_values() {
+        assert_eq!(
+            parse(&["--service-manager"]).unwrap_err(),
+            "--service-manager needs a value"
+        );
+        assert_eq!(
+            parse(&["--expiry-warning"]).unwrap_err(),
+            "--expiry-warning needs a value"
+        );
+        for refresh in ["0", "-1", "NaN", "inf", "1e30"] {
+            assert_eq!(
+                parse(&["--refresh", refresh]).unwrap_err(),
+                "--refresh needs a positive number of seconds",
+                "{}",
+                refresh
+            );
+        }
+        assert_eq!(
+            parse(&["--refresh=soon"]).unwrap_err(),
+            "--refresh needs a number of seconds"
+        );
+        assert_eq!(
+            parse(&["--expiry-warning", "-3"]).unwrap_err(),
+            "--expiry-warning needs a number of days"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        assert_eq!(
+            parse(&["--verbose"]).unwrap_err(),
+            "unexpected argument --verbose"
+        );
+        assert_eq!(
+            parse(&["--refresh=1", "nginx.conf"]).unwrap_err(),
+            "unexpected argument nginx.conf"
+        );
+        assert_eq!(
+            parse(&["--help=yes"]).unwrap_err(),
+            "unexpected argument --help"
+        );
+        assert!(parse(&["-"]).is_err());
+        assert!(parse(&["="]).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    ffi::OsStr,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use tui::{
+    style::{Color, Style},
+    text::Line,
+    widgets::{Paragraph, Wrap},
+};
+
+/// Outcome of an external command run on behalf of the user.
+#[derive(Debug, Clone)]
+pub struct CommandResult {
+    /// The command line, as it would be typed in a shell.
+    pub command: String,
+    /// `None` when the process could not be started or was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration: Duration,
+    pub timestamp: DateTime<Local>,
+}
+
+impl CommandResult {
+    /// A command that could not be run at all, explained by `message`.
+    pub fn failed(command: &str, message: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            exit_code: None,
+            stdout: String::new(),
+            stderr: message.to_string(),
+            duration: Duration::ZERO,
+            timestamp: Local::now(),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Runs `program` with `args` to completion, capturing its output.
+pub fn run_command<S: AsRef<OsStr>>(program: &str, args: &[S]) -> CommandResult {
+    let command = std::iter::once(program.to_string())
+        .chain(
+            args.iter()
+                .map(|a| a.as_ref().to_string_lossy().into_owned()),
+        )
+        .collect::<Vec<_>>()
+        .join(" ");
+    let timestamp = Local::now();
+    let start = Instant::now();
+    let output = Command::new(program).args(args).output();
+    let duration = start.elapsed();
+
+    match output {
+        Ok(output) => CommandResult {
+            command,
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            duration,
+            timestamp,
+        },
+        Err(err) => CommandResult {
+            duration,
+            timestamp,
+            ..CommandResult::failed(&command, &format!("Failed to execute {}: {}", program, err))
+        },
+    }
+}
+
+/// Header line with the exit code and timing, followed by stdout and, in
+/// red, stderr.
+pub fn command_output_component(result: &CommandResult) -> Paragraph<'static> {
+    let exit = match result.exit_code {
+        Some(code) => format!("exit {}", code),
+        None => "not run".to_string(),
+    };
+    let header_color = if result.success() {
+        Color::Green
+    } else {
+        Color::Red
+    };
+    let mut lines = vec![Line::styled(
+        format!(
+            "$ {}  ({}, {} ms, {})",
+            result.command,
+            exit,
+            result.duration.as_millis(),
+            result.timestamp.format("%Y-%m-%d %H:%M:%S")
+        ),
+        Style::default().fg(header_color),
+    )];
+    lines.extend(result.stdout.lines().map(|l| Line::from(l.to_string())));
+    lines.extend(
+        result
+            .stderr
+            .lines()
+            .map(|l| Line::styled(l.to_string(), Style::default().fg(Color::Red))),
+    );
+
+    Paragraph::new(lines).wrap(Wrap { trim: false })
+}
@@ -0,0 +1,762 @@
+use std::{
+    collections::HashMap,
+    error, fmt,
+    fs::{read_dir, read_to_string},
+    path::{Component, Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::version::get_configure_argument;
+
+/// Location of a node in the source, spanning from its first token to its
+/// terminating `;` or `}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Index into [`Config::files`].
+    pub file: usize,
+    /// 1-based line of the first token.
+    pub start_line: usize,
+    /// 1-based line of the terminating token.
+    pub end_line: usize,
+    /// Byte offset of the first token.
+    pub start: usize,
+    /// Byte offset just past the terminating token.
+    pub end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    Directive(Directive),
+    Comment(Comment),
+}
+
+#[derive(Debug, Clone)]
+pub struct Directive {
+    pub name: String,
+    pub args: Vec<String>,
+    pub span: Span,
+    /// Child nodes, `None` for simple directives ending in `;`.
+    pub block: Option<Vec<Node>>,
+    /// Files pulled in by an `include` directive, as indexes into [`Config::files`].
+    pub includes: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Comment {
+    /// Comment text without the leading `#`.
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub source: String,
+    pub nodes: Vec<Node>,
+}
+
+/// A parsed nginx configuration, the main file first followed by every file
+/// reached through `include`.
+#[derive(Debug)]
+pub struct Config {
+    pub files: Vec<ConfigFile>,
+    /// Errors in included files. Those files are kept with no nodes.
+    pub errors: Vec<ConfigError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    /// 1-based line, 0 when the error is not tied to a line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.path.display(), self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.path.display(), self.line, self.message)
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+impl Directive {
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(String::as_str)
+    }
+
+    pub fn children(&self) -> &[Node] {
+        self.block.as_deref().unwrap_or(&[])
+    }
+
+    pub fn is_block(&self) -> bool {
+        self.block.is_some()
+    }
+}
+
+impl Config {
+    /// Parses the file at `path` and every file it includes.
+    ///
+    /// Fails if the main file cannot be read or parsed. Problems in included
+    /// files are collected in [`Config::errors`] instead.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let mut loader = Loader {
+            prefix: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            files: Vec::new(),
+            errors: Vec::new(),
+            seen: HashMap::new(),
+        };
+        let source = read_to_string(path).map_err(|err| ConfigError {
+            path: path.to_path_buf(),
+            line: 0,
+            message: err.to_string(),
+        })?;
+        let nodes = parse(&source, 0).map_err(|(line, message)| ConfigError {
+            path: path.to_path_buf(),
+            line,
+            message,
+        })?;
+        loader.seen.insert(path.to_path_buf(), 0);
+        loader.files.push(ConfigFile {
+            path: path.to_path_buf(),
+            source,
+            nodes,
+        });
+        loader.resolve_includes(0);
+
+        Ok(Config {
+            files: loader.files,
+            errors: loader.errors,
+        })
+    }
+
+    pub fn root(&self) -> &ConfigFile {
+        &self.files[0]
+    }
+
+    pub fn path(&self, span: &Span) -> &Path {
+        &self.files[span.file].path
+    }
+
+    /// Raw source text covered by `span`.
+    pub fn source(&self, span: &Span) -> &str {
+        &self.files[span.file].source[span.start..span.end]
+    }
+
+    /// Directives in `nodes`, with every `include` replaced by the directives
+    /// of the files it pulls in.
+    pub fn expand<'c>(&'c self, nodes: &'c [Node]) -> Vec<&'c Directive> {
+        let mut out = Vec::new();
+        self.expand_into(nodes, &mut Vec::new(), &mut out);
+        out
+    }
+
+    fn expand_into<'c>(
+        &'c self,
+        nodes: &'c [Node],
+        stack: &mut Vec<usize>,
+        out: &mut Vec<&'c Directive>,
+    ) {
+        for node in nodes {
+            let Node::Directive(directive) = node else {
+                continue;
+            };
+            if directive.name != "include" {
+                out.push(directive);
+                continue;
+            }
+            for &file in &directive.includes {
+                // An include cycle would never terminate, nginx rejects it too.
+                if stack.contains(&file) {
+                    continue;
+                }
+                stack.push(file);
+                self.expand_into(&self.files[file].nodes, stack, out);
+                stack.pop();
+            }
+        }
+    }
+
+    /// Visits every directive reachable from the main file, following
+    /// includes, along with the blocks enclosing it (outermost first).
+    pub fn walk<'c>(&'c self, mut visit: impl FnMut(&'c Directive, &[&'c Directive])) {
+        let mut parents = Vec::new();
+        self.walk_nodes(&self.root().nodes, &mut parents, &mut visit);
+    }
+
+    fn walk_nodes<'c>(
+        &'c self,
+        nodes: &'c [Node],
+        parents: &mut Vec<&'c Directive>,
+        visit: &mut impl FnMut(&'c Directive, &[&'c Directive]),
+    ) {
+        for directive in self.expand(nodes) {
+            visit(directive, parents);
+            if directive.is_block() {
+                parents.push(directive);
+                self.walk_nodes(directive.children(), parents, visit);
+                parents.pop();
+            }
+        }
+    }
+}
+
+/// Parses `source` on its own, such as a generated snippet, without
+/// following its includes.
+pub fn parse_snippet(source: &str) -> Result<Vec<Node>, String> {
+    parse(source, 0).map_err(|(line, message)| format!("line {}: {}", line, message))
+}
+
+/// Path of the main configuration file nginx was built with.
+pub fn default_config_path() -> PathBuf {
+    get_configure_argument("conf-path")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/etc/nginx/nginx.conf"))
+}
+
+struct Loader {
+    /// Directory relative include paths are resolved against.
+    prefix: PathBuf,
+    files: Vec<ConfigFile>,
+    errors: Vec<ConfigError>,
+    seen: HashMap<PathBuf, usize>,
+}
+
+impl Loader {
+    fn resolve_includes(&mut self, file: usize) {
+        let mut nodes = std::mem::take(&mut self.files[file].nodes);
+        self.resolve_nodes(file, &mut nodes);
+        self.files[file].nodes = nodes;
+    }
+
+    fn resolve_nodes(&mut self, file: usize, nodes: &mut [Node]) {
+        for node in nodes {
+            let Node::Directive(directive) = node else {
+                continue;
+            };
+            if let Some(block) = directive.block.as_mut() {
+                self.resolve_nodes(file, block);
+            }
+            if directive.name != "include" {
+                continue;
+            }
+            let Some(pattern) = directive.args.first() else {
+                continue;
+            };
+            let pattern = self.prefix.join(pattern);
+            match expand_glob(&pattern) {
+                Ok(paths) => {
+                    directive.includes = paths.iter().map(|path| self.load(path)).collect();
+                }
+                Err(message) => self.errors.push(ConfigError {
+                    path: self.files[file].path.clone(),
+                    line: directive.span.start_line,
+                    message,
+                }),
+            }
+        }
+    }
+
+    fn load(&mut self, path: &Path) -> usize {
+        if let Some(&index) = self.seen.get(path) {
+            return index;
+        }
+        let index = self.files.len();
+        self.seen.insert(path.to_path_buf(), index);
+
+        let (source, nodes) = match read_to_string(path) {
+            Ok(source) => match parse(&source, index) {
+                Ok(nodes) => (source, nodes),
+                Err((line, message)) => {
+                    self.errors.push(ConfigError {
+                        path: path.to_path_buf(),
+                        line,
+                        message,
+                    });
+                    (source, Vec::new())
+                }
+            },
+            Err(err) => {
+                self.errors.push(ConfigError {
+                    path: path.to_path_buf(),
+                    line: 0,
+                    message: err.to_string(),
+                });
+                (String::new(), Vec::new())
+            }
+        };
+        self.files.push(ConfigFile {
+            path: path.to_path_buf(),
+            source,
+            nodes,
+        });
+        self.resolve_includes(index);
+        index
+    }
+}
+
+/// Expands an `include` pattern the way glob(3) does for nginx: sorted, and
+/// wildcards never match a leading dot. A pattern without wildcards must name
+/// an existing file.
+fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>, String> {
+    let is_wild = |s: &str| s.contains(['*', '?', '[']);
+    if !is_wild(&pattern.to_string_lossy()) {
+        return if pattern.exists() {
+            Ok(vec![pattern.to_path_buf()])
+        } else {
+            Err(format!("included file {} not found", pattern.display()))
+        };
+    }
+
+    let mut matches = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let Component::Normal(part) = component else {
+            matches.iter_mut().for_each(|m| m.push(component));
+            continue;
+        };
+        let part = part.to_string_lossy();
+        if !is_wild(&part) {
+            matches.iter_mut().for_each(|m| m.push(part.as_ref()));
+            continue;
+        }
+        let re = glob_regex(&part);
+        let mut next = Vec::new();
+        for dir in &matches {
+            let Ok(entries) = read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if !name.starts_with('.') && re.is_match(&name) {
+                    next.push(dir.join(name));
+                }
+            }
+        }
+        next.sort();
+        matches = next;
+    }
+    matches.retain(|path| path.is_file());
+    Ok(matches)
+}
+
+fn glob_regex(pattern: &str) -> Regex {
+    let mut re = String::from("^");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '[' => {
+                re.push('[');
+                for c in chars.by_ref() {
+                    match c {
+                        '!' if re.ends_with('[') => re.push('^'),
+                        '\\' => re.push_str("\\\\"),
+                        ']' => break,
+                        c => re.push(c),
+                    }
+                }
+                re.push(']');
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).unwrap_or_else(|_| Regex::new("^$").unwrap())
+}
+
+#[derive(Debug, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Semicolon,
+    Open,
+    Close,
+    Comment(String),
+}
+
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    start: usize,
+    end: usize,
+}
+
+type ParseResult<T> = Result<T, (usize, String)>;
+
+fn tokenize(source: &str) -> ParseResult<Vec<Token>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let start_line = line;
+        let kind = match bytes[i] {
+            b'\n' => {
+                line += 1;
+                i += 1;
+                continue;
+            }
+            c if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b';' => {
+                i += 1;
+                TokenKind::Semicolon
+            }
+            b'{' => {
+                i += 1;
+                TokenKind::Open
+            }
+            b'}' => {
+                i += 1;
+                TokenKind::Close
+            }
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                TokenKind::Comment(source[start + 1..i].to_string())
+            }
+            quote @ (b'"' | b'\'') => {
+                let mut value = Vec::new();
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        None => {
+                            return Err((
+                                start_line,
+                                "unexpected end of file, expecting closing quote".into(),
+                            ))
+                        }
+                        Some(&c) if c == quote => break,
+                        Some(b'\\') if matches!(bytes.get(i + 1), Some(&c) if c == quote || c == b'\\') =>
+                        {
+                            value.push(bytes[i + 1]);
+                            i += 2;
+                        }
+                        Some(&c) => {
+                            if c == b'\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+                TokenKind::Word(String::from_utf8_lossy(&value).into_owned())
+            }
+            _ => {
+                let mut value = Vec::new();
+                while let Some(&c) = bytes.get(i) {
+                    match c {
+                        b'\\' if i + 1 < bytes.len() => {
+                            value.extend_from_slice(&bytes[i..i + 2]);
+                            i += 2;
+                        }
+                        // `${name}` keeps its braces, they do not open a block.
+                        b'{' if value.last() == Some(&b'$') => {
+                            while let Some(&c) = bytes.get(i) {
+                                value.push(c);
+                                i += 1;
+                                if c == b'}' {
+                                    break;
+                                }
+                            }
+                        }
+                        b';' | b'{' | b'}' => break,
+                        c if c.is_ascii_whitespace() => break,
+                        c => {
+                            value.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                TokenKind::Word(String::from_utf8_lossy(&value).into_owned())
+            }
+        };
+        tokens.push(Token {
+            kind,
+            line: start_line,
+            start,
+            end: i,
+        });
+    }
+    Ok(tokens)
+}
+
+fn parse(source: &str, file: usize) -> ParseResult<Vec<Node>> {
+    let mut tokens = tokenize(source)?.into_iter();
+    let (nodes, _) = parse_block(&mut tokens, file, None)?;
+    Ok(nodes)
+}
+
+/// Parses nodes up to the `}` closing a block opened on line `opened_at`, or
+/// to the end of input at the top level. Returns the nodes along with the
+/// line and end offset of the closing brace.
+fn parse_block(
+    tokens: &mut std::vec::IntoIter<Token>,
+    file: usize,
+    opened_at: Option<usize>,
+) -> ParseResult<(Vec<Node>, (usize, usize))> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        let name = match token.kind {
+            TokenKind::Comment(text) => {
+                nodes.push(Node::Comment(Comment {
+                    text,
+                    span: Span {
+                        file,
+                        start_line: token.line,
+                        end_line: token.line,
+                        start: token.start,
+                        end: token.end,
+                    },
+                }));
+                continue;
+            }
+            TokenKind::Close if opened_at.is_some() => return Ok((nodes, (token.line, token.end))),
+            TokenKind::Close => return Err((token.line, "unexpected \"}\"".into())),
+            TokenKind::Semicolon => return Err((token.line, "unexpected \";\"".into())),
+            TokenKind::Open => return Err((token.line, "unexpected \"{\"".into())),
+            TokenKind::Word(name) => name,
+        };
+
+        let mut args = Vec::new();
+        loop {
+            let Some(next) = tokens.next() else {
+                return Err((
+                    token.line,
+                    "unexpected end of file, expecting \";\" or \"}\"".into(),
+                ));
+            };
+            let mut span = Span {
+                file,
+                start_line: token.line,
+                end_line: next.line,
+                start: token.start,
+                end: next.end,
+            };
+            let block = match next.kind {
+                TokenKind::Word(arg) => {
+                    args.push(arg);
+                    continue;
+                }
+                // Comments between arguments belong to no node.
+                TokenKind::Comment(_) => continue,
+                TokenKind::Semicolon => None,
+                TokenKind::Open => {
+                    let (block, (end_line, end)) = parse_block(tokens, file, Some(next.line))?;
+                    span.end_line = end_line;
+                    span.end = end;
+                    Some(block)
+                }
+                TokenKind::Close => return Err((next.line, "unexpected \"}\"".into())),
+            };
+            nodes.push(Node::Directive(Directive {
+                name,
+                args,
+                span,
+                block,
+                includes: Vec::new(),
+            }));
+            break;
+        }
+    }
+
+    match opened_at {
+        Some(line) => Err((line, "unexpected end of file, expecting \"}\"".into())),
+        None => Ok((nodes, (0, 0))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// A fresh directory for the files of one test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("lazynginx-config-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn directives(nodes: &[Node]) -> Vec<&Directive> {
+        nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Directive(directive) => Some(directive),
+                Node::Comment(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn quoted_arguments_keep_special_characters() {
+        let nodes = parse(
+            r#"add_header X-Test "a;b{c}" always;
+return 200 'it\'s';
+"#,
+            0,
+        )
+        .unwrap();
+        let found = directives(&nodes);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].args, ["X-Test", "a;b{c}", "always"]);
+        assert_eq!(found[1].args, ["200", "it's"]);
+    }
+
+    #[test]
+    fn variables_in_braces_do_not_open_a_block() {
+        let nodes = parse("set $x ${host}_suffix;\n", 0).unwrap();
+        let found = directives(&nodes);
+        assert!(!found[0].is_block());
+        assert_eq!(found[0].args, ["$x", "${host}_suffix"]);
+    }
+
+    #[test]
+    fn comments_are_kept_as_nodes() {
+        let nodes = parse("# top\nworker_processes 1; # trailing\n", 0).unwrap();
+        assert!(matches!(&nodes[0], Node::Comment(c) if c.text == " top"));
+        assert_eq!(directives(&nodes)[0].args, ["1"]);
+    }
+
+    #[test]
+    fn spans_cover_blocks_from_name_to_closing_brace() {
+        let source = "http {\n    server {\n        listen 80;\n    }\n}\n";
+        let nodes = parse(source, 0).unwrap();
+        let http = directives(&nodes)[0];
+        assert_eq!((http.span.start_line, http.span.end_line), (1, 5));
+        assert_eq!(&source[http.span.start..http.span.end], source.trim_end());
+
+        let server = directives(http.children())[0];
+        assert_eq!((server.span.start_line, server.span.end_line), (2, 4));
+        let listen = directives(server.children())[0];
+        assert_eq!((listen.span.start_line, listen.span.end_line), (3, 3));
+        assert_eq!(&source[listen.span.start..listen.span.end], "listen 80;");
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_line() {
+        let cases = [
+            ("events {}\n}\n", 2, "unexpected \"}\""),
+            (
+                "http {\n    server {\n",
+                2,
+                "unexpected end of file, expecting \"}\"",
+            ),
+            (
+                "user nginx\n",
+                1,
+                "unexpected end of file, expecting \";\" or \"}\"",
+            ),
+            (
+                "\n\nreturn \"open;\n",
+                3,
+                "unexpected end of file, expecting closing quote",
+            ),
+            ("worker_processes 1;\n;\n", 2, "unexpected \";\""),
+        ];
+        for (source, line, message) in cases {
+            assert_eq!(
+                parse(source, 0).unwrap_err(),
+                (line, message.to_string()),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn includes_are_expanded_in_glob_order() {
+        let dir = scratch_dir("glob");
+        fs::create_dir(dir.join("conf.d")).unwrap();
+        fs::write(dir.join("conf.d/b.conf"), "b;\n").unwrap();
+        fs::write(dir.join("conf.d/a.conf"), "a;\n").unwrap();
+        fs::write(dir.join("conf.d/.hidden.conf"), "hidden;\n").unwrap();
+        fs::write(dir.join("conf.d/c.txt"), "c;\n").unwrap();
+        fs::write(dir.join("nginx.conf"), "include conf.d/*.conf;\nlast;\n").unwrap();
+
+        let config = Config::load(&dir.join("nginx.conf")).unwrap();
+        let names: Vec<_> = config
+            .expand(&config.root().nodes)
+            .iter()
+            .map(|directive| directive.name.as_str())
+            .collect();
+        assert_eq!(names, ["a", "b", "last"]);
+        assert!(config.errors.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn include_cycles_terminate() {
+        let dir = scratch_dir("cycle");
+        fs::write(dir.join("nginx.conf"), "include a.conf;\n").unwrap();
+        fs::write(dir.join("a.conf"), "a;\ninclude b.conf;\n").unwrap();
+        fs::write(dir.join("b.conf"), "b;\ninclude a.conf;\n").unwrap();
+
+        let config = Config::load(&dir.join("nginx.conf")).unwrap();
+        assert_eq!(config.files.len(), 3);
+        let mut names = Vec::new();
+        config.walk(|directive, _| names.push(directive.name.clone()));
+        assert_eq!(names, ["a", "b"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn errors_in_included_files_are_collected() {
+        let dir = scratch_dir("errors");
+        fs::write(
+            dir.join("nginx.conf"),
+            "include broken.conf;\ninclude missing.conf;\n",
+        )
+        .unwrap();
+        fs::write(dir.join("broken.conf"), "server {\n    listen 80\n}\n").unwrap();
+
+        let config = Config::load(&dir.join("nginx.conf")).unwrap();
+        assert_eq!(config.errors.len(), 2);
+        assert_eq!(config.errors[0].path, dir.join("broken.conf"));
+        assert_eq!(config.errors[0].line, 3);
+        assert_eq!(config.errors[1].path, dir.join("nginx.conf"));
+        assert_eq!(config.errors[1].line, 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn walk_passes_the_enclosing_blocks() {
+        let dir = scratch_dir("walk");
+        fs::write(
+            dir.join("nginx.conf"),
+            "http {\n    include site.conf;\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("site.conf"), "server {\n    listen 80;\n}\n").unwrap();
+
+        let config = Config::load(&dir.join("nginx.conf")).unwrap();
+        let mut listen = None;
+        config.walk(|directive, parents| {
+            if directive.name == "listen" {
+                let parents: Vec<_> = parents.iter().map(|p| p.name.clone()).collect();
+                listen = Some((config.path(&directive.span).to_path_buf(), parents));
+            }
+        });
+        assert_eq!(
+            listen,
+            Some((dir.join("site.conf"), vec!["http".into(), "server".into()]))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+use std::{collections::HashSet, path::Path};
+
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span as TextSpan},
+    widgets::{List, ListItem, ListState, Paragraph},
+};
+
+use crate::config::{Config, ConfigError, Directive, Node, Span};
+
+/// One visible row of the config tree.
+#[derive(Debug)]
+pub struct TreeRow {
+    /// Position of the block among its expanded siblings at every level.
+    pub key: Vec<usize>,
+    pub label: String,
+    pub span: Span,
+    pub expandable: bool,
+}
+
+/// Collapsible tree of the `http` → `server` → `location` blocks in the config.
+#[derive(Debug, Default)]
+pub struct ConfigTree {
+    pub list_state: ListState,
+    pub rows: Vec<TreeRow>,
+    /// File and line to show in the source pane instead of the selected block,
+    /// set when jumping to a diagnostic.
+    pub focus: Option<(usize, usize)>,
+    expanded: HashSet<Vec<usize>>,
+}
+
+impl ConfigTree {
+    /// Builds the tree with the top level blocks expanded.
+    pub fn new(config: &Result<Config, ConfigError>) -> Self {
+        let mut tree = Self::default();
+        if let Ok(config) = config {
+            let top = blocks(config.expand(&config.root().nodes));
+            tree.expanded = (0..top.len()).map(|i| vec![i]).collect();
+            tree.rebuild(config);
+        }
+        tree.list_state.select(Some(0));
+        tree
+    }
+
+    pub fn selected(&self) -> Option<&TreeRow> {
+        self.list_state.selected().and_then(|i| self.rows.get(i))
+    }
+
+    pub fn select_next(&mut self) {
+        self.focus = None;
+        let i = match self.list_state.selected() {
+            Some(i) if i + 1 < self.rows.len() => i + 1,
+            _ => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.focus = None;
+        let i = match self.list_state.selected() {
+            Some(0) | None => self.rows.len().saturating_sub(1),
+            Some(i) => i - 1,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    /// Expands or collapses the selected block.
+    pub fn toggle(&mut self, config: &Result<Config, ConfigError>) {
+        let Some(row) = self.selected() else {
+            return;
+        };
+        if !row.expandable {
+            return;
+        }
+        let key = row.key.clone();
+        self.focus = None;
+        if !self.expanded.remove(&key) {
+            self.expanded.insert(key);
+        }
+        if let Ok(config) = config {
+            self.rebuild(config);
+        }
+    }
+
+    /// Selects the innermost block containing `line` of the file at `path`,
+    /// expanding its parents, and focuses the source pane on that line.
+    pub fn reveal(&mut self, config: &Result<Config, ConfigError>, path: &Path, line: usize) {
+        let Ok(config) = config else {
+            return;
+        };
+        let Some(file) = config.files.iter().position(|f| f.path == path) else {
+            return;
+        };
+
+        let mut best = None;
+        let top = blocks(config.expand(&config.root().nodes));
+        locate(config, top, file, line, &mut Vec::new(), &mut best);
+        if let Some(key) = best {
+            for depth in 1..key.len() {
+                self.expanded.insert(key[..depth].to_vec());
+            }
+            self.rebuild(config);
+            let index = self.rows.iter().position(|row| row.key == key);
+            self.list_state.select(index);
+        }
+        self.focus = Some((file, line));
+    }
+
+    fn rebuild(&mut self, config: &Config) {
+        self.rows.clear();
+        let top = blocks(config.expand(&config.root().nodes));
+        self.push_rows(config, top, &mut Vec::new());
+    }
+
+    fn push_rows(&mut self, config: &Config, directives: Vec<&Directive>, key: &mut Vec<usize>) {
+        for (i, directive) in directives.into_iter().enumerate() {
+            key.push(i);
+            let children = blocks(config.expand(directive.children()));
+            self.rows.push(TreeRow {
+                key: key.clone(),
+                label: label(directive),
+                span: directive.span,
+                expandable: !children.is_empty(),
+            });
+            if self.expanded.contains(key) {
+                self.push_rows(config, children, key);
+            }
+            key.pop();
+        }
+    }
+}
+
+fn locate(
+    config: &Config,
+    directives: Vec<&Directive>,
+    file: usize,
+    line: usize,
+    key: &mut Vec<usize>,
+    best: &mut Option<Vec<usize>>,
+) {
+    for (i, directive) in directives.into_iter().enumerate() {
+        key.push(i);
+        let span = &directive.span;
+        if span.file == file
+            && (span.start_line..=span.end_line).contains(&line)
+            && best.as_ref().is_none_or(|best| best.len() < key.len())
+        {
+            *best = Some(key.clone());
+        }
+        locate(
+            config,
+            blocks(config.expand(directive.children())),
+            file,
+            line,
+            key,
+            best,
+        );
+        key.pop();
+    }
+}
+
+fn blocks(directives: Vec<&Directive>) -> Vec<&Directive> {
+    directives.into_iter().filter(|d| d.is_block()).collect()
+}
+
+/// `server` blocks are named after their `server_name` and `listen`
+/// directives, everything else after its arguments.
+pub fn label(directive: &Directive) -> String {
+    if directive.name != "server" {
+        return format!("{} {}", directive.name, directive.args.join(" "))
+            .trim_end()
+            .to_string();
+    }
+    let mut names = Vec::new();
+    let mut listens = Vec::new();
+    for node in directive.children() {
+        let Node::Directive(child) = node else {
+            continue;
+        };
+        match child.name.as_str() {
+            "server_name" => names.extend(child.args.iter().cloned()),
+            "listen" => listens.push(child.args.join(" ")),
+            _ => {}
+        }
+    }
+    let mut label = String::from("server");
+    if !names.is_empty() {
+        label.push(' ');
+        label.push_str(&names.join(" "));
+    }
+    if !listens.is_empty() {
+        label.push_str(&format!(" [{}]", listens.join(", ")));
+    }
+    label
+}
+
+pub fn config_tree_component(tree: &ConfigTree) -> List<'static> {
+    let items: Vec<_> = tree
+        .rows
+        .iter()
+        .map(|row| {
+            let marker = match (row.expandable, tree.expanded.contains(&row.key)) {
+                (false, _) => "  ",
+                (true, false) => "▸ ",
+                (true, true) => "▾ ",
+            };
+            ListItem::new(format!(
+                "{}{}{}",
+                "  ".repeat(row.key.len() - 1),
+                marker,
+                row.label
+            ))
+        })
+        .collect();
+
+    List::new(items).highlight_style(
+        Style::default()
+            .add_modifier(Modifier::BOLD)
+            .fg(Color::LightBlue),
+    )
+}
+
+/// Source lines `start_line..=end_line` of `file`, each prefixed with its
+/// line number, with `highlight` standing out.
+pub fn config_source_component(
+    config: &Config,
+    file: usize,
+    start_line: usize,
+    end_line: usize,
+    highlight: Option<usize>,
+) -> Paragraph<'_> {
+    let source = &config.files[file].source;
+    let lines: Vec<Line> = source
+        .lines()
+        .enumerate()
+        .skip(start_line - 1)
+        .take_while(|(i, _)| *i < end_line)
+        .map(|(i, line)| {
+            let mut line = Line::from(vec![
+                TextSpan::styled(
+                    format!("{:>5} │ ", i + 1),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                TextSpan::raw(line),
+            ]);
+            if highlight == Some(i + 1) {
+                line.patch_style(Style::default().bg(Color::Red));
+            }
+            line
+        })
+        .collect();
+
+    Paragraph::new(lines)
+}
+
+/// Config errors, one per line.
+pub fn config_errors_component(config: &Result<Config, ConfigError>) -> Paragraph<'static> {
+    let lines: Vec<Line> = match config {
+        Ok(config) => config
+            .errors
+            .iter()
+            .map(|err| err.to_string().into())
+            .collect(),
+        Err(err) => vec![err.to_string().into()],
+    };
+    Paragraph::new(lines).style(Style::default().fg(Color::Red))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn reveal_selects_the_innermost_block_of_a_line() {
+        let dir = std::env::temp_dir().join(format!("lazynginx-tree-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("nginx.conf"),
+            "http {\n    include site.conf;\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("site.conf"),
+            "server {\n    server_name a;\n    location / {\n        return 200;\n    }\n}\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&dir.join("nginx.conf"));
+        let mut tree = ConfigTree::new(&config);
+        tree.reveal(&config, &dir.join("site.conf"), 4);
+        let row = tree.selected().unwrap();
+        assert_eq!(row.key, [0, 0, 0]);
+        assert_eq!(row.label, "location /");
+        assert_eq!(tree.focus, Some((1, 4)));
+
+        tree.reveal(&config, &dir.join("site.conf"), 2);
+        assert_eq!(tree.selected().unwrap().key, [0, 0]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
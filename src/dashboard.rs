@@ -0,0 +1,509 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use chrono::Local;
+use tui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Row, Sparkline, Table},
+};
+
+use crate::{
+    access_log::{AccessRecord, LogFormat},
+    tail::LogTailer,
+};
+
+/// Most lines parsed in one tick, so catching up on a large log is spread
+/// over several ticks.
+const PARSE_BUDGET: usize = 50_000;
+
+/// Request times are counted in bins this much wider than the one before,
+/// so percentiles are accurate to within 10%.
+const BIN_GROWTH: f64 = 1.1;
+
+/// Upper bound of the first request time bin, in seconds.
+const FIRST_BIN: f64 = 0.0001;
+
+const BINS: usize = 200;
+
+/// Most paths, clients and user agents counted on their own in a minute;
+/// the rest only add to how many requests were not.
+const TOP_PER_MINUTE: usize = 50;
+
+/// How far back the dashboard looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    FiveMinutes,
+    Hour,
+    Day,
+}
+
+impl Window {
+    pub const ALL: [Window; 3] = [Window::FiveMinutes, Window::Hour, Window::Day];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Window::FiveMinutes => "5m",
+            Window::Hour => "1h",
+            Window::Day => "24h",
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Window::FiveMinutes => 5 * 60,
+            Window::Hour => 60 * 60,
+            Window::Day => 24 * 60 * 60,
+        }
+    }
+
+    pub fn next(&self) -> Window {
+        match self {
+            Window::FiveMinutes => Window::Hour,
+            Window::Hour => Window::Day,
+            Window::Day => Window::FiveMinutes,
+        }
+    }
+}
+
+/// What was logged in one minute.
+#[derive(Debug, Default)]
+struct Bucket {
+    requests: u64,
+    /// Responses by status class, 1xx to 5xx.
+    statuses: [u64; 5],
+    paths: TopCounts,
+    clients: TopCounts,
+    agents: TopCounts,
+    /// Request times, counted in bins growing by [`BIN_GROWTH`].
+    times: Vec<u64>,
+}
+
+/// How often each value was seen, keeping at most [`TOP_PER_MINUTE`]
+/// values: a new one pushes out the least frequent.
+#[derive(Debug, Default)]
+struct TopCounts {
+    counts: HashMap<String, u64>,
+    /// Requests whose value was pushed out.
+    other: u64,
+}
+
+impl TopCounts {
+    fn add(&mut self, value: String) {
+        if let Some(count) = self.counts.get_mut(&value) {
+            *count += 1;
+            return;
+        }
+        if self.counts.len() >= TOP_PER_MINUTE {
+            let least = self
+                .counts
+                .iter()
+                .min_by_key(|(_, count)| **count)
+                .map(|(value, _)| value.clone());
+            if let Some(count) = least.and_then(|least| self.counts.remove(&least)) {
+                self.other += count;
+            }
+        }
+        self.counts.insert(value, 1);
+    }
+}
+
+/// Aggregates of an access log over the last day, fed line by line from
+/// a [`LogTailer`] following it: the Logs tab's when it shows the same
+/// log, or one of its own otherwise.
+#[derive(Debug)]
+pub struct Dashboard {
+    files: Vec<PathBuf>,
+    name: String,
+    /// Only set while the Logs tab shows another log.
+    tailer: Option<LogTailer>,
+    /// Whether the Logs tab's tailer was read last time.
+    shared: bool,
+    error: Option<String>,
+    loading: bool,
+    format: LogFormat,
+    /// Next line of the tailer to parse.
+    cursor: usize,
+    /// The tailer generation `cursor` counts lines of.
+    generation: u64,
+    /// Requests per second over the last hour, by Unix time.
+    seconds: BTreeMap<i64, u64>,
+    /// Everything else over the last day, by Unix time of the minute.
+    minutes: BTreeMap<i64, Bucket>,
+    pub window: Window,
+}
+
+/// The dashboard over its current window.
+pub struct Stats {
+    pub requests: u64,
+    pub statuses: [u64; 5],
+    pub top_paths: Vec<(String, u64)>,
+    pub top_clients: Vec<(String, u64)>,
+    pub top_agents: Vec<(String, u64)>,
+    /// p50, p95 and p99 request time in seconds, `None` when not logged.
+    pub percentiles: Option<[f64; 3]>,
+}
+
+impl Dashboard {
+    /// Reads nothing until polled.
+    pub fn new(files: &[PathBuf], format: LogFormat) -> Self {
+        Self {
+            files: files.to_vec(),
+            name: String::new(),
+            tailer: None,
+            shared: false,
+            error: None,
+            loading: true,
+            format,
+            cursor: 0,
+            generation: 0,
+            seconds: BTreeMap::new(),
+            minutes: BTreeMap::new(),
+            window: Window::FiveMinutes,
+        }
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Whether lines written before the dashboard was opened are still
+    /// being read.
+    pub fn loading(&self) -> bool {
+        self.loading
+    }
+
+    /// Parses the lines appended since the last call, read from `shared`
+    /// when it follows the same log, which the caller has polled already.
+    pub fn poll(&mut self, shared: Option<&mut LogTailer>) {
+        let shared = shared.filter(|tailer| tailer.files == self.files && tailer.range.is_none());
+        let switched = shared.is_some() != self.shared;
+        self.shared = shared.is_some();
+        let tailer = match shared {
+            Some(tailer) => {
+                self.tailer = None;
+                tailer
+            }
+            None => {
+                let files = &self.files;
+                let tailer = self.tailer.get_or_insert_with(|| LogTailer::new(files));
+                if !switched {
+                    tailer.poll();
+                }
+                tailer
+            }
+        };
+        // Another tailer of the same files counts the same lines, so
+        // carry on where the last one was.
+        if switched {
+            self.generation = tailer.generation;
+        } else if tailer.generation != self.generation {
+            self.generation = tailer.generation;
+            self.cursor = 0;
+        }
+        self.name = tailer.name.clone();
+        self.error = tailer.error.clone();
+        let lines = tailer.lines_from(self.cursor, PARSE_BUDGET);
+        self.loading = tailer.indexing() || self.cursor < tailer.line_count;
+        let Ok(lines) = lines else {
+            return;
+        };
+        self.cursor += lines.len();
+
+        let now = Local::now().timestamp();
+        for line in lines {
+            if let Some(record) = self.format.parse(&line) {
+                self.add(record, now);
+            }
+        }
+        self.seconds = self.seconds.split_off(&(now - Window::Hour.seconds()));
+        self.minutes = self.minutes.split_off(&(now - Window::Day.seconds()));
+    }
+
+    fn add(&mut self, record: AccessRecord, now: i64) {
+        let time = record.time.map_or(now, |time| time.timestamp());
+        if time < now - Window::Day.seconds() {
+            return;
+        }
+        *self.seconds.entry(time).or_default() += 1;
+
+        let bucket = self.minutes.entry(time - time.rem_euclid(60)).or_default();
+        bucket.requests += 1;
+        if let Some(status @ 100..=599) = record.status {
+            bucket.statuses[status as usize / 100 - 1] += 1;
+        }
+        for (counts, value) in [
+            (&mut bucket.paths, record.path),
+            (&mut bucket.clients, record.remote_addr),
+            (&mut bucket.agents, record.user_agent),
+        ] {
+            if let Some(value) = value {
+                counts.add(value);
+            }
+        }
+        if let Some(time) = record.request_time {
+            if bucket.times.is_empty() {
+                bucket.times = vec![0; BINS];
+            }
+            bucket.times[time_bin(time)] += 1;
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        let since = Local::now().timestamp() - self.window.seconds();
+        let mut stats = Stats {
+            requests: 0,
+            statuses: [0; 5],
+            top_paths: Vec::new(),
+            top_clients: Vec::new(),
+            top_agents: Vec::new(),
+            percentiles: None,
+        };
+        let mut totals = [(); 3].map(|_| (HashMap::new(), 0));
+        let mut times = vec![0; BINS];
+        // A bucket counts when its minute started in the window.
+        for (_, bucket) in self.minutes.range(since - since.rem_euclid(60)..) {
+            stats.requests += bucket.requests;
+            for (total, count) in stats.statuses.iter_mut().zip(bucket.statuses) {
+                *total += count;
+            }
+            for ((totals, other), counts) in
+                totals
+                    .iter_mut()
+                    .zip([&bucket.paths, &bucket.clients, &bucket.agents])
+            {
+                for (value, count) in &counts.counts {
+                    *totals.entry(value.as_str()).or_insert(0) += count;
+                }
+                *other += counts.other;
+            }
+            for (total, count) in times.iter_mut().zip(&bucket.times) {
+                *total += count;
+            }
+        }
+        let [paths, clients, agents] = totals.map(|(counts, other)| top(counts, other));
+        stats.top_paths = paths;
+        stats.top_clients = clients;
+        stats.top_agents = agents;
+
+        let timed: u64 = times.iter().sum();
+        if timed > 0 {
+            stats.percentiles = Some([0.5, 0.95, 0.99].map(|quantile| {
+                let rank = (timed as f64 * quantile).ceil() as u64;
+                let mut seen = 0;
+                let bin = times
+                    .iter()
+                    .position(|count| {
+                        seen += count;
+                        seen >= rank
+                    })
+                    .unwrap_or(BINS - 1);
+                bin_upper_bound(bin)
+            }));
+        }
+        stats
+    }
+
+    /// Requests in each of the last `points` intervals of the window, and
+    /// the length of an interval in seconds.
+    pub fn series(&self, points: usize) -> (Vec<u64>, i64) {
+        let points = points.max(1) as i64;
+        let interval = (self.window.seconds() + points - 1) / points;
+        let now = Local::now().timestamp();
+        let start = now - interval * points;
+        let mut series = vec![0; points as usize];
+        let mut add = |time: i64, count: u64| {
+            if time > start && time <= now {
+                series[((time - start - 1) / interval) as usize] += count;
+            }
+        };
+        if interval < 60 {
+            for (&time, &count) in self.seconds.range(start..) {
+                add(time, count);
+            }
+        } else {
+            for (&time, bucket) in self.minutes.range(start - 60..) {
+                add(time + 59, bucket.requests);
+            }
+        }
+        (series, interval)
+    }
+}
+
+fn time_bin(seconds: f64) -> usize {
+    if seconds <= FIRST_BIN {
+        return 0;
+    }
+    ((seconds / FIRST_BIN).ln() / BIN_GROWTH.ln())
+        .ceil()
+        .min((BINS - 1) as f64) as usize
+}
+
+fn bin_upper_bound(bin: usize) -> f64 {
+    FIRST_BIN * BIN_GROWTH.powi(bin as i32)
+}
+
+/// The ten most frequent values, most frequent first, then how many
+/// requests had any other value.
+fn top(counts: HashMap<&str, u64>, other: u64) -> Vec<(String, u64)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let rest = other + counts.iter().skip(10).map(|(_, count)| count).sum::<u64>();
+    let mut top: Vec<_> = counts
+        .into_iter()
+        .take(10)
+        .map(|(value, count)| (value.to_string(), count))
+        .collect();
+    if rest > 0 {
+        top.push(("(other)".to_string(), rest));
+    }
+    top
+}
+
+pub fn requests_component(series: &[u64]) -> Sparkline<'_> {
+    Sparkline::default()
+        .data(series)
+        .style(Style::default().fg(Color::LightBlue))
+}
+
+/// Status classes and request time percentiles.
+pub fn summary_component(stats: &Stats, window: Window) -> Paragraph<'static> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Requests: ", bold),
+            Span::raw(format!(
+                "{} ({:.2}/s)",
+                stats.requests,
+                stats.requests as f64 / window.seconds() as f64
+            )),
+        ]),
+        Line::from(""),
+    ];
+    let colors = [
+        Color::Gray,
+        Color::Green,
+        Color::Cyan,
+        Color::Yellow,
+        Color::Red,
+    ];
+    for (i, (count, color)) in stats.statuses.iter().zip(colors).enumerate() {
+        let share = match stats.requests {
+            0 => 0.0,
+            requests => *count as f64 * 100.0 / requests as f64,
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{}xx ", i + 1), Style::default().fg(color)),
+            Span::raw(format!("{:>8} {:>5.1}%", count, share)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    match stats.percentiles {
+        Some([p50, p95, p99]) => {
+            for (name, value) in [("p50", p50), ("p95", p95), ("p99", p99)] {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{} ", name), bold),
+                    Span::raw(format!("{:>9.3}s", value)),
+                ]));
+            }
+        }
+        None => lines.push(Line::from("No $request_time in the log format")),
+    }
+    Paragraph::new(lines)
+}
+
+pub fn top_component(values: &[(String, u64)]) -> Table<'static> {
+    let rows: Vec<_> = values
+        .iter()
+        .map(|(value, count)| Row::new(vec![count.to_string(), value.clone()]))
+        .collect();
+    Table::new(rows).widths(&[Constraint::Length(8), Constraint::Percentage(100)])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn top_counts_push_out_the_least_frequent() {
+        let mut counts = TopCounts::default();
+        for _ in 0..3 {
+            counts.add("/".to_string());
+        }
+        for i in 0..TOP_PER_MINUTE * 2 {
+            counts.add(format!("/{}", i));
+        }
+        assert_eq!(counts.counts.len(), TOP_PER_MINUTE);
+        assert_eq!(counts.counts["/"], 3);
+        assert_eq!(counts.other, TOP_PER_MINUTE as u64 + 1);
+        let total: u64 = counts.counts.values().sum::<u64>() + counts.other;
+        assert_eq!(total, TOP_PER_MINUTE as u64 * 2 + 3);
+    }
+
+    #[test]
+    fn top_adds_up_the_rest() {
+        let values: Vec<_> = (0..12).map(|i| format!("/{}", i)).collect();
+        let counts = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (value.as_str(), 20 - i as u64))
+            .collect();
+        let shown = top(counts, 5);
+        assert_eq!(shown.len(), 11);
+        assert_eq!(shown[0], ("/0".to_string(), 20));
+        assert_eq!(shown[9], ("/9".to_string(), 11));
+        assert_eq!(shown[10], ("(other)".to_string(), 10 + 9 + 5));
+        assert_eq!(top(HashMap::from([("/", 1)]), 0).len(), 1);
+    }
+
+    #[test]
+    fn counts_each_line_once_across_shared_and_own_tailers() {
+        let path = std::env::temp_dir().join(format!(
+            "lazynginx-dashboard-{}-access.log",
+            std::process::id()
+        ));
+        let line = |path: &str| {
+            format!(
+                "10.0.0.1 - - [{}] \"GET {} HTTP/1.1\" 200 5 \"-\" \"curl\"\n",
+                Local::now().format("%d/%b/%Y:%H:%M:%S %z"),
+                path
+            )
+        };
+        fs::write(&path, line("/a") + &line("/b")).unwrap();
+        let files = vec![path.clone()];
+        let mut logs = LogTailer::new(&files);
+        let mut dashboard = Dashboard::new(&files, LogFormat::combined());
+
+        dashboard.poll(Some(&mut logs));
+        assert!(dashboard.tailer.is_none());
+        assert_eq!(dashboard.stats().requests, 2);
+
+        // The Logs tab moves on to another log.
+        fs::write(&path, line("/a") + &line("/b") + &line("/c")).unwrap();
+        dashboard.poll(None);
+        assert!(dashboard.tailer.is_some());
+        assert_eq!(dashboard.stats().requests, 3);
+
+        // And back.
+        let mut logs = LogTailer::new(&files);
+        dashboard.poll(Some(&mut logs));
+        assert!(dashboard.tailer.is_none());
+        assert_eq!(dashboard.stats().requests, 3);
+        assert_eq!(dashboard.name(), path.display().to_string());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
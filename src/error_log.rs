@@ -0,0 +1,195 @@
+use std::sync::OnceLock;
+
+use chrono::NaiveDateTime;
+use regex::Regex;
+use tui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    widgets::{Cell, Row, Table},
+};
+
+use crate::{access_log::TIME_FIELD, tail::LogTailer, validate::Level};
+
+/// A line of the error log, as written by `ngx_log_error`:
+///
+/// `2023/08/01 12:00:00 [error] 1234#1234: *56 message, client: ..., server: ...`
+#[derive(Debug, Clone)]
+pub struct ErrorRecord {
+    pub time: Option<NaiveDateTime>,
+    pub level: Level,
+    pub pid: u32,
+    pub tid: u32,
+    /// The `*N` connection number, for messages about a connection.
+    pub connection: Option<u64>,
+    pub message: String,
+    pub client: Option<String>,
+    pub server: Option<String>,
+    pub request: Option<String>,
+    pub upstream: Option<String>,
+}
+
+impl ErrorRecord {
+    /// Names [`ErrorRecord::field`] knows, for filters.
+    pub const FIELDS: [&'static str; 10] = [
+        "time",
+        "level",
+        "pid",
+        "connection",
+        "conn",
+        "message",
+        "client",
+        "server",
+        "request",
+        "upstream",
+    ];
+
+    /// The field called `name` as text, `None` when it was not logged.
+    pub fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "time" => self.time.map(|time| time.format(TIME_FIELD).to_string()),
+            "level" => Some(self.level.to_string()),
+            "pid" => Some(self.pid.to_string()),
+            "connection" | "conn" => self.connection.map(|c| c.to_string()),
+            "message" => Some(self.message.clone()),
+            "client" => self.client.clone(),
+            "server" => self.server.clone(),
+            "request" => self.request.clone(),
+            "upstream" => self.upstream.clone(),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_error_line(line: &str) -> Option<ErrorRecord> {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    let header = HEADER.get_or_init(|| {
+        Regex::new(
+            r"^(\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2}) \[(\w+)\] (\d+)#(\d+): (?:\*(\d+) )?(.*)$",
+        )
+        .unwrap()
+    });
+    let captures = header.captures(line)?;
+    let mut record = ErrorRecord {
+        time: NaiveDateTime::parse_from_str(&captures[1], "%Y/%m/%d %H:%M:%S").ok(),
+        level: Level::parse(&captures[2])?,
+        pid: captures[3].parse().ok()?,
+        tid: captures[4].parse().ok()?,
+        connection: captures.get(5).and_then(|c| c.as_str().parse().ok()),
+        message: captures[6].to_string(),
+        client: None,
+        server: None,
+        request: None,
+        upstream: None,
+    };
+
+    // nginx appends what it knows about the request after the message,
+    // starting with the client address.
+    if let Some(start) = record.message.find(", client: ") {
+        let context = record.message.split_off(start);
+        let field =
+            FIELD.get_or_init(|| Regex::new(r#"(\w+): ("(?:[^"\\]|\\.)*"|[^,]*)"#).unwrap());
+        for captures in field.captures_iter(&context) {
+            let value = captures[2].trim_matches('"').to_string();
+            match &captures[1] {
+                "client" => record.client = Some(value),
+                "server" => record.server = Some(value),
+                "request" => record.request = Some(value),
+                "upstream" => record.upstream = Some(value),
+                _ => {}
+            }
+        }
+    }
+    Some(record)
+}
+
+/// The level of an error log line, without parsing the rest of it.
+pub fn line_level(line: &str) -> Option<Level> {
+    let rest = line.get(20..)?.strip_prefix('[')?;
+    Level::parse(&rest[..rest.find(']')?])
+}
+
+/// The next, more severe, minimum level to filter by, wrapping around to
+/// showing everything after `emerg`.
+pub fn next_min_level(level: Level) -> Level {
+    let i = Level::ALL.iter().position(|&l| l == level).unwrap_or(0);
+    Level::ALL[(i + Level::ALL.len() - 1) % Level::ALL.len()]
+}
+
+/// The visible lines of an error log, colored by level. Lines that are not
+/// in the error log format, such as the continuation of a multi-line
+/// message, are shown raw in the message column. Rows matching `search` are
+/// reversed.
+pub fn error_log_component<'a>(tailer: &'a LogTailer, search: Option<&Regex>) -> Table<'a> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let rows: Vec<_> = tailer
+        .window()
+        .iter()
+        .map(|(number, line)| {
+            let matched = search.is_some_and(|regex| regex.is_match(line));
+            let row_style = if matched {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            let number = Cell::from(number.map(|n| n.to_string()).unwrap_or_default()).style(dim);
+            let Some(record) = parse_error_line(line) else {
+                return Row::new(vec![
+                    number,
+                    Cell::from(""),
+                    Cell::from(""),
+                    Cell::from(""),
+                    Cell::from(""),
+                    Cell::from(line.as_str()).style(dim),
+                ])
+                .style(row_style);
+            };
+            let level_style = Style::default().fg(record.level.color());
+            Row::new(vec![
+                number,
+                Cell::from(
+                    record
+                        .time
+                        .map(|time| time.format("%d/%b %H:%M:%S").to_string())
+                        .unwrap_or_default(),
+                ),
+                Cell::from(record.level.as_str().to_string())
+                    .style(level_style.add_modifier(Modifier::BOLD)),
+                Cell::from(format!("{}#{}", record.pid, record.tid)).style(dim),
+                Cell::from(
+                    record
+                        .connection
+                        .map(|c| format!("*{}", c))
+                        .unwrap_or_default(),
+                ),
+                Cell::from(record.message).style(level_style),
+                Cell::from(record.client.unwrap_or_default()),
+                Cell::from(record.server.unwrap_or_default()),
+                Cell::from(record.request.unwrap_or_default()),
+                Cell::from(record.upstream.unwrap_or_default()),
+            ])
+            .style(row_style)
+        })
+        .collect();
+
+    Table::new(rows)
+        .header(
+            Row::new(vec![
+                "Line", "Time", "Level", "PID#TID", "Conn", "Message", "Client", "Server",
+                "Request", "Upstream",
+            ])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .widths(&[
+            Constraint::Length(7),
+            Constraint::Length(15),
+            Constraint::Length(6),
+            Constraint::Length(11),
+            Constraint::Length(7),
+            Constraint::Min(30),
+            Constraint::Length(15),
+            Constraint::Length(15),
+            Constraint::Length(25),
+            Constraint::Length(25),
+        ])
+}
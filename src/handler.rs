@@ -1,17 +1,26 @@
 use crate::{
-    app::{App, AppResult},
-    status::get_nginx_status,
+    app::{Action, App, AppResult, ConfigFocus, TemplateFocus},
+    modal::Modal,
+    prompt::{Prompt, PromptKind},
+    tabs::{get_current_screen, Screen},
 };
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 /// Handles the key events and updates the state of [`App`].
 pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    if app.modal.is_some() {
+        return handle_modal_key_events(key_event, app);
+    }
+    if app.prompt.is_some() {
+        return handle_prompt_key_events(key_event, app);
+    }
     match key_event.code {
         // Exit application on `ESC` or `q`
         KeyCode::Esc | KeyCode::Char('q') => {
             app.quit();
         }
         // Exit application on `Ctrl-C`
+        #[allow(clippy::collapsible_match)]
         KeyCode::Char('c') | KeyCode::Char('C') => {
             if key_event.modifiers == KeyModifiers::CONTROL {
                 app.quit();
@@ -29,13 +38,174 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
         KeyCode::Down => {
             app.increment_selection();
         }
-        KeyCode::Enter => {
-            let command = app.selected_command();
-            command.execute(app);
-            app.status = get_nginx_status()
+        KeyCode::PageDown if get_current_screen(app) == Screen::Status => {
+            app.scroll_output_down(10);
+        }
+        KeyCode::PageUp if get_current_screen(app) == Screen::Status => {
+            app.scroll_output_up(10);
+        }
+        KeyCode::PageUp
+        | KeyCode::PageDown
+        | KeyCode::Char('k')
+        | KeyCode::Char('j')
+        | KeyCode::Home
+        | KeyCode::End
+            if get_current_screen(app) == Screen::Logs =>
+        {
+            if let Some(log_view) = app.log_view.as_mut() {
+                let page = log_view.height.saturating_sub(1).max(1);
+                match key_event.code {
+                    KeyCode::PageUp => log_view.scroll_up(page),
+                    KeyCode::PageDown => log_view.scroll_down(page),
+                    KeyCode::Char('k') => log_view.scroll_up(1),
+                    KeyCode::Char('j') => log_view.scroll_down(1),
+                    KeyCode::Home => log_view.scroll_to_top(),
+                    _ => log_view.scroll_to_bottom(),
+                }
+            }
+        }
+        KeyCode::Char('l') if get_current_screen(app) == Screen::Logs => {
+            app.cycle_error_level();
+        }
+        KeyCode::Char(':') if get_current_screen(app) == Screen::Logs => {
+            app.prompt = Some(Prompt::new(PromptKind::GotoLine));
+        }
+        KeyCode::Char('/') if get_current_screen(app) == Screen::Logs => {
+            app.prompt = Some(Prompt::new(PromptKind::Search));
+        }
+        KeyCode::Char('f') if get_current_screen(app) == Screen::Logs => {
+            let mut prompt = Prompt::new(PromptKind::Filter);
+            if let Some(filter) = &app.log_filter {
+                prompt.input = filter.input.clone();
+            }
+            app.prompt = Some(prompt);
+        }
+        KeyCode::Char('t') if get_current_screen(app) == Screen::Logs => {
+            let mut prompt = Prompt::new(PromptKind::TimeRange);
+            if let Some(range) = &app.log_range {
+                prompt.input = range.to_string();
+            }
+            app.prompt = Some(prompt);
+        }
+        KeyCode::Char('n') if get_current_screen(app) == Screen::Logs => {
+            app.search_next(true);
+        }
+        KeyCode::Char('N') if get_current_screen(app) == Screen::Logs => {
+            app.search_next(false);
+        }
+        KeyCode::Char('w') if get_current_screen(app) == Screen::Dashboard => {
+            if let Some(dashboard) = app.dashboard.as_mut() {
+                dashboard.window = dashboard.window.next();
+            }
+        }
+        KeyCode::Tab if get_current_screen(app) == Screen::Template => {
+            app.toggle_template_focus();
+        }
+        KeyCode::Enter | KeyCode::Char(' ') if get_current_screen(app) == Screen::Template => {
+            match app.template_focus {
+                TemplateFocus::List => app.toggle_template_focus(),
+                TemplateFocus::Form => app.edit_template_field(),
+                TemplateFocus::Users => app.change_htpasswd_password(),
+            }
+        }
+        KeyCode::Char('a')
+            if get_current_screen(app) == Screen::Template
+                && app.template_focus == TemplateFocus::Users =>
+        {
+            app.add_htpasswd_user();
+        }
+        KeyCode::Char('p')
+            if get_current_screen(app) == Screen::Template
+                && app.template_focus == TemplateFocus::Users =>
+        {
+            app.change_htpasswd_password();
+        }
+        KeyCode::Char('d')
+            if get_current_screen(app) == Screen::Template
+                && app.template_focus == TemplateFocus::Users =>
+        {
+            app.remove_htpasswd_user(false);
+        }
+        KeyCode::Char('i')
+            if get_current_screen(app) == Screen::Template
+                && app.templates[app.selected_template()]
+                    .value("directory")
+                    .is_some() =>
+        {
+            app.issue_certificate();
+        }
+        KeyCode::Char('w') if get_current_screen(app) == Screen::Template => {
+            app.write_template(false);
+        }
+        KeyCode::PageDown if get_current_screen(app) == Screen::Template => {
+            app.preview_scroll = app.preview_scroll.saturating_add(10);
+        }
+        KeyCode::PageUp if get_current_screen(app) == Screen::Template => {
+            app.preview_scroll = app.preview_scroll.saturating_sub(10);
+        }
+        KeyCode::Char('r') if get_current_screen(app) == Screen::Certificates => {
+            app.reload_certificates();
+        }
+        KeyCode::Tab if get_current_screen(app) == Screen::Config => {
+            app.toggle_config_focus();
+        }
+        KeyCode::Char('t') if get_current_screen(app) == Screen::Config => {
+            app.test_config();
+        }
+        KeyCode::Enter
+            if get_current_screen(app) == Screen::Config
+                && app.config_focus == ConfigFocus::Diagnostics =>
+        {
+            app.jump_to_diagnostic();
+        }
+        KeyCode::Enter | KeyCode::Char(' ') if get_current_screen(app) == Screen::Config => {
+            app.config_tree.toggle(&app.config);
+        }
+        KeyCode::Enter if get_current_screen(app) == Screen::Status => {
+            match app.selected_action() {
+                Action::Service(command) => {
+                    app.run_command(command);
+                }
+                Action::TestConfig => app.test_config(),
+            }
         }
         // Other handlers you could add here.
         _ => {}
     }
     Ok(())
 }
+
+/// Handles the key events while a [`Modal`] is open.
+fn handle_modal_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    match (key_event.code, app.modal.take()) {
+        (KeyCode::Char('f'), Some(Modal::ConfigTestFailed { command, .. })) => {
+            app.force_command(command);
+        }
+        (KeyCode::Char('y'), Some(Modal::OverwriteTemplate { .. })) => {
+            app.write_template(true);
+        }
+        (KeyCode::Char('y'), Some(Modal::RemoveHtpasswdUser { .. })) => {
+            app.remove_htpasswd_user(true);
+        }
+        (KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('n'), _) => {}
+        (_, modal) => app.modal = modal,
+    }
+    Ok(())
+}
+
+/// Handles the key events while a [`Prompt`] is open.
+fn handle_prompt_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    let Some(prompt) = app.prompt.as_mut() else {
+        return Ok(());
+    };
+    match key_event.code {
+        KeyCode::Esc => app.prompt = None,
+        KeyCode::Enter => app.submit_prompt(),
+        KeyCode::Backspace => {
+            prompt.input.pop();
+        }
+        KeyCode::Char(c) => prompt.input.push(c),
+        _ => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process::Command,
+};
+
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use tui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    widgets::{Row, Table},
+};
+
+use crate::command::CommandResult;
+
+/// How many past entries of the audit file are shown on startup.
+const HISTORY_LIMIT: usize = 1000;
+
+/// A command run from lazynginx, as recorded in the audit file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<FixedOffset>,
+    pub user: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u128,
+    /// Run despite a failed config test.
+    pub forced: bool,
+}
+
+impl HistoryEntry {
+    pub fn new(result: &CommandResult, forced: bool) -> Self {
+        Self {
+            timestamp: result.timestamp.fixed_offset(),
+            user: current_user(),
+            command: result.command.clone(),
+            exit_code: result.exit_code,
+            duration_ms: result.duration.as_millis(),
+            forced,
+        }
+    }
+
+    /// One tab separated line of the audit file, with tabs, line breaks
+    /// and backslashes in the user and command escaped.
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}ms{}",
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Secs, false),
+            escape(&self.user),
+            escape(&self.command),
+            self.exit_code
+                .map_or("exit=none".to_string(), |code| format!("exit={}", code)),
+            self.duration_ms,
+            if self.forced { "\tforced" } else { "" }
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let timestamp = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+        let user = unescape(fields.next()?);
+        let command = unescape(fields.next()?);
+        let exit_code = match fields.next()?.strip_prefix("exit=")? {
+            "none" => None,
+            code => Some(code.parse().ok()?),
+        };
+        let duration_ms = fields.next()?.strip_suffix("ms")?.parse().ok()?;
+        let forced = match fields.next() {
+            None => false,
+            Some("forced") => true,
+            Some(_) => return None,
+        };
+        Some(Self {
+            timestamp,
+            user,
+            command,
+            exit_code,
+            duration_ms,
+            forced,
+        })
+    }
+}
+
+/// Keeps `field` on one line and within its column of the audit file.
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Reverses [`escape`]. Other backslashes, as in lines written before
+/// fields were escaped, are kept.
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            ('\\', Some('\\')) => '\\',
+            ('\\', Some('t')) => '\t',
+            ('\\', Some('n')) => '\n',
+            ('\\', Some('r')) => '\r',
+            (c, _) => {
+                unescaped.push(c);
+                continue;
+            }
+        };
+        chars.next();
+        unescaped.push(escaped);
+    }
+    unescaped
+}
+
+/// The operator running lazynginx, looking through `sudo` to the real user.
+pub fn current_user() -> String {
+    ["SUDO_USER", "USER", "LOGNAME"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|user| !user.is_empty()))
+        .or_else(|| {
+            let output = Command::new("id").arg("-un").output().ok()?;
+            Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+        })
+        .filter(|user| !user.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// `$XDG_STATE_HOME/lazynginx`, defaulting to `~/.local/state/lazynginx`.
+pub fn state_dir() -> Option<PathBuf> {
+    let state_home = env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state_home.join("lazynginx"))
+}
+
+/// `audit.log` in the [`state_dir`].
+pub fn audit_path() -> Option<PathBuf> {
+    Some(state_dir()?.join("audit.log"))
+}
+
+/// Appends `entry` to the audit file, creating it if needed.
+pub fn append_audit(entry: &HistoryEntry) -> io::Result<()> {
+    let path = audit_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no state directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry.to_line())
+}
+
+/// The most recent entries of the audit file, oldest first.
+pub fn load_history() -> Vec<HistoryEntry> {
+    let Some(contents) = audit_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<_> = contents
+        .lines()
+        .filter_map(HistoryEntry::from_line)
+        .collect();
+    let excess = entries.len().saturating_sub(HISTORY_LIMIT);
+    entries.drain(..excess);
+    entries
+}
+
+/// History table, newest first.
+pub fn history_component(history: &[HistoryEntry]) -> Table<'static> {
+    let rows: Vec<_> = history
+        .iter()
+        .rev()
+        .map(|entry| {
+            let (result, color) = match entry.exit_code {
+                Some(0) => ("ok".to_string(), Color::Green),
+                Some(code) => (format!("exit {}", code), Color::Red),
+                None => ("not run".to_string(), Color::Red),
+            };
+            let result = if entry.forced {
+                format!("{} (forced)", result)
+            } else {
+                result
+            };
+            Row::new(vec![
+                entry.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                entry.user.clone(),
+                entry.command.clone(),
+                result,
+                format!("{} ms", entry.duration_ms),
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
+
+    Table::new(rows)
+        .header(
+            Row::new(vec!["Time", "User", "Command", "Result", "Duration"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .widths(&[
+            Constraint::Length(19),
+            Constraint::Length(12),
+            Constraint::Min(30),
+            Constraint::Length(16),
+            Constraint::Length(10),
+        ])
+        .column_spacing(2)
+        .highlight_symbol(">>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_lines_round_trip() {
+        let entry = HistoryEntry {
+            timestamp: DateTime::parse_from_rfc3339("2026-10-18T09:12:39+02:00").unwrap(),
+            user: "ops\tteam".to_string(),
+            command: "nginx -t -c /etc/nginx/odd\tname\nnginx.conf C:\\temp\\t".to_string(),
+            exit_code: Some(1),
+            duration_ms: 42,
+            forced: true,
+        };
+        let line = entry.to_line();
+        assert!(!line.contains('\n'));
+        assert_eq!(line.matches('\t').count(), 5);
+        assert_eq!(HistoryEntry::from_line(&line), Some(entry.clone()));
+
+        let entry = HistoryEntry {
+            exit_code: None,
+            forced: false,
+            ..entry
+        };
+        let line = entry.to_line();
+        assert!(line.ends_with("\texit=none\t42ms"), "{}", line);
+        assert_eq!(HistoryEntry::from_line(&line), Some(entry));
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        for line in [
+            "",
+            "not a line",
+            "yesterday\talice\tsystemctl reload nginx\texit=0\t12ms",
+            "2026-10-18T09:12:39+02:00\talice\tsystemctl reload nginx\texit=0",
+            "2026-10-18T09:12:39+02:00\talice\tsystemctl reload nginx\t0\t12ms",
+            "2026-10-18T09:12:39+02:00\talice\tsystemctl reload nginx\texit=zero\t12ms",
+            "2026-10-18T09:12:39+02:00\talice\tsystemctl reload nginx\texit=0\t12",
+            "2026-10-18T09:12:39+02:00\talice\tsystemctl reload nginx\texit=0\t12ms\tmaybe",
+        ] {
+            assert_eq!(HistoryEntry::from_line(line), None, "{:?}", line);
+        }
+        let entry = HistoryEntry::from_line(
+            "2026-10-18T09:12:39+02:00\talice\tsystemctl reload nginx\texit=0\t12ms",
+        )
+        .unwrap();
+        assert_eq!(entry.command, "systemctl reload nginx");
+        let entry =
+            HistoryEntry::from_line("2026-10-18T09:12:39+02:00\talice\tprintf a\\x\texit=0\t12ms")
+                .unwrap();
+        assert_eq!(entry.command, "printf a\\x");
+        assert_eq!(entry.exit_code, Some(0));
+        assert!(!entry.forced);
+    }
+}
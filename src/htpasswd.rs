@@ -0,0 +1,385 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::fs::{chown, MetadataExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+};
+
+use bcrypt::Version;
+use md5::{Digest, Md5};
+use tui::{
+    layout::Constraint,
+    style::{Modifier, Style},
+    widgets::{Row, Table},
+};
+
+/// Characters of the base64 variant used by crypt(3) hashes.
+const CRYPT_ALPHABET: &[u8] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// bcrypt cost, the default of Apache's `htpasswd -B`. nginx checks the
+/// password on every request, so it is kept low.
+const BCRYPT_COST: u32 = 5;
+
+/// How passwords are hashed. nginx checks apr1 itself and hands bcrypt to
+/// the system crypt(), which glibc's lacks, so apr1 is the default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scheme {
+    /// Apache's MD5 based `$apr1$`.
+    Apr1,
+    Bcrypt,
+}
+
+impl Scheme {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bcrypt" => Some(Scheme::Bcrypt),
+            "apr1" => Some(Scheme::Apr1),
+            _ => None,
+        }
+    }
+}
+
+/// An `auth_basic_user_file`: one `user:hash` line per user.
+#[derive(Debug, Clone)]
+pub struct Htpasswd {
+    pub path: PathBuf,
+    pub users: Vec<(String, String)>,
+    /// The lines of the file: `None` for the next user, the line itself
+    /// for comments and anything else that is written back as it was.
+    lines: Vec<Option<String>>,
+}
+
+impl Htpasswd {
+    /// Reads the file at `path`, empty when it does not exist yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let mut users = Vec::new();
+        let mut lines = Vec::new();
+        for line in contents.lines() {
+            match line.split_once(':') {
+                Some((user, hash)) if !user.is_empty() && !line.starts_with('#') => {
+                    users.push((user.to_string(), hash.to_string()));
+                    lines.push(None);
+                }
+                _ => lines.push(Some(line.to_string())),
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            users,
+            lines,
+        })
+    }
+
+    /// Adds `user`, or changes their password.
+    pub fn set(&mut self, user: &str, password: &str, scheme: Scheme) -> Result<(), String> {
+        if user.is_empty() || user.contains(':') || user.contains(char::is_whitespace) {
+            return Err(format!("{:?} is not a valid user name", user));
+        }
+        if password.is_empty() {
+            return Err("The password is empty".to_string());
+        }
+        let hash = hash_password(password, scheme)?;
+        match self.users.iter_mut().find(|(name, _)| name == user) {
+            Some(entry) => entry.1 = hash,
+            None => self.users.push((user.to_string(), hash)),
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, user: &str) {
+        while let Some(i) = self.users.iter().position(|(name, _)| name == user) {
+            self.users.remove(i);
+            if let Some(line) = self
+                .lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.is_none())
+                .nth(i)
+                .map(|(line, _)| line)
+            {
+                self.lines.remove(line);
+            }
+        }
+    }
+
+    /// The file as written back, users added since it was read at the end.
+    fn contents(&self) -> String {
+        let mut users = self.users.iter();
+        let mut contents = String::new();
+        for line in &self.lines {
+            match line {
+                Some(line) => contents.push_str(line),
+                None => match users.next() {
+                    Some((user, hash)) => contents.push_str(&format!("{}:{}", user, hash)),
+                    None => continue,
+                },
+            }
+            contents.push('\n');
+        }
+        for (user, hash) in users {
+            contents.push_str(&format!("{}:{}\n", user, hash));
+        }
+        contents
+    }
+
+    /// Writes the users back through a file renamed over the old one, so
+    /// nginx never reads a partly written file. The old file keeps its
+    /// owner and mode; a new one is made readable by the owner and `group`,
+    /// which should be the group of the nginx workers that read it.
+    pub fn save(&self, group: Option<u32>) -> io::Result<()> {
+        // Replace the file a link points to, not the link.
+        let path = fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a file path", path.display()),
+            ));
+        };
+        fs::create_dir_all(dir)?;
+        let existing = fs::metadata(&path).ok();
+        let staged = dir.join(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id()
+        ));
+        let write = || -> io::Result<()> {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(existing.as_ref().map_or(0o640, |meta| meta.mode() & 0o7777))
+                .open(&staged)?;
+            file.write_all(self.contents().as_bytes())?;
+            file.sync_all()?;
+            let _ = match &existing {
+                Some(meta) => chown(&staged, Some(meta.uid()), Some(meta.gid())),
+                None => chown(&staged, None, group),
+            };
+            fs::rename(&staged, &path)
+        };
+        write().inspect_err(|_| {
+            let _ = fs::remove_file(&staged);
+        })
+    }
+}
+
+/// What kind of hash an htpasswd line holds, for display.
+pub fn hash_scheme(hash: &str) -> &'static str {
+    match hash {
+        h if h.starts_with("$2y$") || h.starts_with("$2b$") || h.starts_with("$2a$") => "bcrypt",
+        h if h.starts_with("$apr1$") => "apr1",
+        h if h.starts_with("$1$") => "md5-crypt",
+        h if h.starts_with("$5$") => "sha256-crypt",
+        h if h.starts_with("$6$") => "sha512-crypt",
+        h if h.starts_with("{SHA}") => "sha1",
+        h if h.starts_with("{PLAIN}") => "plain",
+        _ => "crypt",
+    }
+}
+
+pub fn hash_password(password: &str, scheme: Scheme) -> Result<String, String> {
+    match scheme {
+        Scheme::Bcrypt => bcrypt::hash_with_result(password, BCRYPT_COST)
+            .map(|parts| parts.format_for_version(Version::TwoY))
+            .map_err(|err| format!("Failed to hash the password: {}", err)),
+        Scheme::Apr1 => {
+            let mut random = [0u8; 8];
+            File::open("/dev/urandom")
+                .and_then(|mut urandom| urandom.read_exact(&mut random))
+                .map_err(|err| format!("Failed to generate a salt: {}", err))?;
+            let salt: Vec<u8> = random
+                .iter()
+                .map(|byte| CRYPT_ALPHABET[(byte & 0x3f) as usize])
+                .collect();
+            Ok(apr1(password.as_bytes(), &salt))
+        }
+    }
+}
+
+/// Apache's variant of the MD5 based crypt(3), as in `htpasswd -m`.
+fn apr1(password: &[u8], salt: &[u8]) -> String {
+    const MAGIC: &[u8] = b"$apr1$";
+
+    let alternate = Md5::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+    let mut context = Md5::new()
+        .chain_update(password)
+        .chain_update(MAGIC)
+        .chain_update(salt);
+    for chunk in (0..password.len()).step_by(16) {
+        context.update(&alternate[..(password.len() - chunk).min(16)]);
+    }
+    let mut length = password.len();
+    while length > 0 {
+        if length & 1 == 1 {
+            context.update([0]);
+        } else {
+            context.update(&password[..1]);
+        }
+        length >>= 1;
+    }
+    let mut digest = context.finalize();
+
+    // Slows down brute forcing, by 1990s standards.
+    for round in 0..1000 {
+        let mut context = Md5::new();
+        if round % 2 == 1 {
+            context.update(password);
+        } else {
+            context.update(digest);
+        }
+        if round % 3 != 0 {
+            context.update(salt);
+        }
+        if round % 7 != 0 {
+            context.update(password);
+        }
+        if round % 2 == 1 {
+            context.update(digest);
+        } else {
+            context.update(password);
+        }
+        digest = context.finalize();
+    }
+
+    let mut hash = format!(
+        "{}{}$",
+        String::from_utf8_lossy(MAGIC),
+        String::from_utf8_lossy(salt)
+    );
+    let mut encode = |value: u32, chars: usize| {
+        for i in 0..chars {
+            hash.push(CRYPT_ALPHABET[(value >> (6 * i) & 0x3f) as usize] as char);
+        }
+    };
+    for (a, b, c) in [(0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5)] {
+        encode(
+            (digest[a] as u32) << 16 | (digest[b] as u32) << 8 | digest[c] as u32,
+            4,
+        );
+    }
+    encode(digest[11] as u32, 2);
+    hash
+}
+
+/// The users of `htpasswd` with the scheme of their hash.
+pub fn htpasswd_component(htpasswd: &Htpasswd) -> Table<'static> {
+    let rows: Vec<_> = htpasswd
+        .users
+        .iter()
+        .map(|(user, hash)| Row::new(vec![user.clone(), hash_scheme(hash).to_string()]))
+        .collect();
+    Table::new(rows)
+        .header(Row::new(vec!["User", "Hash"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .widths(&[Constraint::Percentage(70), Constraint::Percentage(30)])
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apr1_matches_known_hashes() {
+        // From `openssl passwd -apr1 -salt <salt> <password>`.
+        assert_eq!(
+            apr1(b"correct horse", b"rZ1tBLHq"),
+            "$apr1$rZ1tBLHq$2KqvPCApBtZWYAkPbMizT0"
+        );
+        assert_eq!(apr1(b"a", b"x"), "$apr1$x$16j9.5e7KiXmuYFAYpPJM/");
+        assert_eq!(
+            apr1(b"a-password-longer-than-sixteen-bytes", b"saltsalt"),
+            "$apr1$saltsalt$uaMpHzDBYOCRl59zFl.fr1"
+        );
+    }
+
+    #[test]
+    fn hashes_with_the_scheme_picked() {
+        let hash = hash_password("secret", Scheme::Apr1).unwrap();
+        let salt = &hash["$apr1$".len()..hash.rfind('$').unwrap()];
+        assert_eq!(salt.len(), 8);
+        assert_eq!(apr1(b"secret", salt.as_bytes()), hash);
+
+        let hash = hash_password("secret", Scheme::Bcrypt).unwrap();
+        assert!(hash.starts_with("$2y$05$"));
+        assert!(bcrypt::verify("secret", &hash).unwrap());
+    }
+
+    #[test]
+    fn keeps_lines_that_are_not_users() {
+        let dir = std::env::temp_dir().join(format!("lazynginx-htpasswd-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".htpasswd");
+        let contents = "# Staff\nalice:$apr1$x$16j9.5e7KiXmuYFAYpPJM/\n\n\
+                        not a user\nbob:{PLAIN}hunter2\n# Contractors\ncarol:$2y$05$abc\n";
+        fs::write(&path, contents).unwrap();
+
+        let mut htpasswd = Htpasswd::load(&path).unwrap();
+        let users: Vec<&str> = htpasswd
+            .users
+            .iter()
+            .map(|(user, _)| user.as_str())
+            .collect();
+        assert_eq!(users, ["alice", "bob", "carol"]);
+        htpasswd.save(None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+
+        htpasswd.remove("bob");
+        htpasswd.set("dave", "secret", Scheme::Apr1).unwrap();
+        htpasswd.save(None).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = saved.lines().collect();
+        assert_eq!(
+            lines[..6],
+            [
+                "# Staff",
+                "alice:$apr1$x$16j9.5e7KiXmuYFAYpPJM/",
+                "",
+                "not a user",
+                "# Contractors",
+                "carol:$2y$05$abc",
+            ]
+        );
+        assert!(lines[6].starts_with("dave:$apr1$"));
+        assert_eq!(lines.len(), 7);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replaces_the_file_through_a_link_keeping_its_mode() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir =
+            std::env::temp_dir().join(format!("lazynginx-htpasswd-link-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("users");
+        let link = dir.join(".htpasswd");
+        fs::write(&path, "alice:{PLAIN}old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&path, &link).unwrap();
+
+        let mut htpasswd = Htpasswd::load(&link).unwrap();
+        htpasswd.set("bob", "secret", Scheme::Apr1).unwrap();
+        htpasswd.save(Some(0)).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::metadata(&path).unwrap().mode() & 0o777, 0o600);
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(
+            saved.starts_with("alice:{PLAIN}old\nbob:$apr1$"),
+            "{}",
+            saved
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
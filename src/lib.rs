@@ -13,17 +13,77 @@ pub mod tui;
 /// Event handler.
 pub mod handler;
 
+/// Command line arguments.
+pub mod cli;
+
 // Version Getting Code
 mod version;
 
-// systemctl stuff
-mod systemctl;
+// Service manager backends
+mod service;
+
+// Running external commands
+mod command;
+
+// Command history and audit log
+mod history;
 
 // Get nginx status
 mod status;
 
+// Background refresh of live data
+mod refresh;
+
+// stub_status connection metrics
+mod stub_status;
+
 // Log
 mod logs;
 
+// Log file tailing
+mod tail;
+
+// Decompressing and joining rotated logs
+mod spool;
+
+// Access log parsing
+mod access_log;
+
+// Error log parsing
+mod error_log;
+
+// Log view filters
+mod log_filter;
+
+// Traffic dashboard
+mod dashboard;
+
+// Config templates
+mod template;
+
+// htpasswd files
+mod htpasswd;
+
+// TLS certificate inventory
+mod certificates;
+
+// ACME certificate issuance
+mod acme;
+
+// Text input prompts
+mod prompt;
+
 // tabs
 mod tabs;
+
+// nginx config parser
+mod config;
+
+// Config tree browser
+mod config_tree;
+
+// nginx -t diagnostics
+mod validate;
+
+// Modal dialogs
+mod modal;
@@ -0,0 +1,365 @@
+use std::cmp::Ordering;
+
+use regex::Regex;
+
+use crate::{
+    access_log::{AccessRecord, LogFormat},
+    error_log::{parse_error_line, ErrorRecord},
+    validate::Level,
+};
+
+/// How the lines of a log are parsed for field predicates.
+#[derive(Debug, Clone)]
+pub enum LogSource {
+    Access(LogFormat),
+    Error,
+    /// Lines that are only searched as text.
+    Raw,
+}
+
+impl LogSource {
+    /// When `line` was logged, formatted as
+    /// [`TIME_FIELD`](crate::access_log::TIME_FIELD).
+    pub fn time(&self, line: &str) -> Option<String> {
+        match self {
+            LogSource::Access(format) => format.parse(line)?.field("time"),
+            LogSource::Error => parse_error_line(line)?.field("time"),
+            LogSource::Raw => None,
+        }
+    }
+}
+
+/// Times a view of a log and its rotations is limited to, typed as
+/// `FROM..TO` with either side left out. Each side is a prefix of a time
+/// such as `2023-08-01T12`, and `TO` includes every time it is a prefix of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeRange {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl TimeRange {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let Some((from, to)) = input.split_once("..") else {
+            return Err("expected FROM..TO".to_string());
+        };
+        let time = Regex::new(r"^\d{4}(-\d{2}(-\d{2}(T\d{2}(:\d{2}(:\d{2})?)?)?)?)?$").unwrap();
+        let side = |value: &str| match value.trim() {
+            "" => Ok(None),
+            value if time.is_match(value) => Ok(Some(value.to_string())),
+            value => Err(format!("{} is not a time such as 2023-08-01T12:30", value)),
+        };
+        let range = Self {
+            from: side(from)?,
+            to: side(to)?,
+        };
+        if range.from.is_none() && range.to.is_none() {
+            return Err("expected FROM..TO".to_string());
+        }
+        Ok(range)
+    }
+
+    pub fn contains(&self, time: &str) -> bool {
+        !self.before(time) && !self.after(time)
+    }
+
+    /// Whether `time` comes before the start of the range.
+    pub fn before(&self, time: &str) -> bool {
+        self.from.as_deref().is_some_and(|from| time < from)
+    }
+
+    /// Whether `time` comes after the end of the range.
+    pub fn after(&self, time: &str) -> bool {
+        self.to
+            .as_deref()
+            .is_some_and(|to| time > to && !time.starts_with(to))
+    }
+}
+
+impl std::fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}..{}",
+            self.from.as_deref().unwrap_or_default(),
+            self.to.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    NotMatch,
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    /// Matches anywhere in the line.
+    Text(Regex),
+    /// Compares a parsed field, as in `status>=500` or `path~^/api`.
+    Field {
+        name: String,
+        op: Op,
+        value: String,
+        regex: Option<Regex>,
+    },
+}
+
+/// A filter typed into the log view: whitespace separated terms that must
+/// all match. A term naming a known field followed by one of `=`, `!=`,
+/// `<`, `<=`, `>`, `>=`, `~` or `!~` compares that field, anything else is
+/// a regex searched for in the whole line.
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    pub input: String,
+    terms: Vec<Term>,
+}
+
+enum Record {
+    Access(AccessRecord),
+    Error(ErrorRecord),
+}
+
+impl Record {
+    fn field(&self, name: &str) -> Option<String> {
+        match self {
+            Record::Access(record) => record.field(name),
+            Record::Error(record) => record.field(name),
+        }
+    }
+}
+
+impl LogFilter {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let predicate = Regex::new(r"^(\w+)(>=|<=|!=|!~|=|>|<|~)(.*)$").unwrap();
+        let mut terms = Vec::new();
+        for token in input.split_whitespace() {
+            let field = predicate.captures(token).filter(|captures| {
+                AccessRecord::FIELDS.contains(&&captures[1])
+                    || ErrorRecord::FIELDS.contains(&&captures[1])
+            });
+            let term = match field {
+                Some(captures) => {
+                    let op = match &captures[2] {
+                        "=" => Op::Eq,
+                        "!=" => Op::Ne,
+                        "<" => Op::Lt,
+                        "<=" => Op::Le,
+                        ">" => Op::Gt,
+                        ">=" => Op::Ge,
+                        "~" => Op::Match,
+                        _ => Op::NotMatch,
+                    };
+                    let value = captures[3].to_string();
+                    if &captures[1] == "level"
+                        && !matches!(op, Op::Match | Op::NotMatch)
+                        && Level::parse(&value).is_none()
+                    {
+                        return Err(format!("unknown level in {}", token));
+                    }
+                    let regex = match op {
+                        Op::Match | Op::NotMatch => Some(
+                            Regex::new(&value)
+                                .map_err(|err| format!("invalid regex in {}: {}", token, err))?,
+                        ),
+                        _ => None,
+                    };
+                    Term::Field {
+                        name: captures[1].to_string(),
+                        op,
+                        value,
+                        regex,
+                    }
+                }
+                None => Term::Text(
+                    Regex::new(token).map_err(|err| format!("invalid regex {}: {}", token, err))?,
+                ),
+            };
+            terms.push(term);
+        }
+        Ok(Self {
+            input: input.trim().to_string(),
+            terms,
+        })
+    }
+
+    pub fn matches(&self, line: &str, source: &LogSource) -> bool {
+        // Parsed on the first field term only.
+        let mut record: Option<Option<Record>> = None;
+        self.terms.iter().all(|term| match term {
+            Term::Text(regex) => regex.is_match(line),
+            Term::Field {
+                name,
+                op,
+                value,
+                regex,
+            } => {
+                let record = record.get_or_insert_with(|| match source {
+                    LogSource::Access(format) => format.parse(line).map(Record::Access),
+                    LogSource::Error => parse_error_line(line).map(Record::Error),
+                    LogSource::Raw => None,
+                });
+                let Some(field) = record.as_ref().and_then(|record| record.field(name)) else {
+                    return false;
+                };
+                match (op, regex) {
+                    (Op::Match, Some(regex)) => regex.is_match(&field),
+                    (Op::NotMatch, Some(regex)) => !regex.is_match(&field),
+                    _ => {
+                        let ordering = compare(name, &field, value);
+                        match op {
+                            Op::Eq => ordering == Ordering::Equal,
+                            Op::Ne => ordering != Ordering::Equal,
+                            Op::Lt => ordering == Ordering::Less,
+                            Op::Le => ordering != Ordering::Greater,
+                            Op::Gt => ordering == Ordering::Greater,
+                            _ => ordering != Ordering::Less,
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Compares levels by severity, so `level>=error` includes `crit`, and
+/// other fields as numbers when both sides are, as text otherwise.
+fn compare(name: &str, field: &str, value: &str) -> Ordering {
+    if name == "level" {
+        if let (Some(field), Some(value)) = (Level::parse(field), Level::parse(value)) {
+            // Levels are ordered most severe first.
+            return value.cmp(&field);
+        }
+    }
+    match (field.parse::<f64>(), value.parse::<f64>()) {
+        (Ok(field), Ok(value)) => field.partial_cmp(&value).unwrap_or(Ordering::Equal),
+        _ => field.cmp(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCESS: &str = r#"203.0.113.7 - - [10/Oct/2023:13:55:36 +0000] "GET /api/users?id=1 HTTP/1.1" 502 157 "-" "curl/8.0""#;
+
+    fn access(filter: &str) -> bool {
+        LogFilter::parse(filter)
+            .unwrap()
+            .matches(ACCESS, &LogSource::Access(LogFormat::combined()))
+    }
+
+    fn error_line(level: &str) -> String {
+        format!(
+            "2023/10/10 13:55:36 [{}] 12#12: *5 upstream timed out, client: 203.0.113.7, server: example.com",
+            level
+        )
+    }
+
+    fn error(filter: &str, level: &str) -> bool {
+        LogFilter::parse(filter)
+            .unwrap()
+            .matches(&error_line(level), &LogSource::Error)
+    }
+
+    #[test]
+    fn text_terms_are_regexes_over_the_whole_line() {
+        assert!(access("curl"));
+        assert!(access(r"users\?id=\d"));
+        assert!(!access("wget"));
+    }
+
+    #[test]
+    fn every_term_must_match() {
+        assert!(access("status>=500 method=GET curl"));
+        assert!(!access("status>=500 method=POST"));
+        assert!(LogFilter::parse("")
+            .unwrap()
+            .matches("anything", &LogSource::Raw));
+    }
+
+    #[test]
+    fn numeric_fields_compare_as_numbers() {
+        assert!(access("status=502"));
+        assert!(access("status>=500"));
+        assert!(access("status>99"));
+        assert!(access("status<=502"));
+        assert!(!access("status<500"));
+        assert!(!access("status!=502"));
+        assert!(access("bytes>100"));
+    }
+
+    #[test]
+    fn regex_operators_match_the_field_only() {
+        assert!(access("path~^/api/"));
+        assert!(!access("path~curl"));
+        assert!(access("path!~^/static"));
+        assert!(access("remote_addr~^203\\."));
+    }
+
+    #[test]
+    fn unknown_names_are_text_terms() {
+        // `foo` is not a field, so `foo=1` is searched for as text.
+        assert!(!access("foo=1"));
+        assert!(access("id=1"));
+    }
+
+    #[test]
+    fn fields_missing_from_the_source_never_match() {
+        assert!(!LogFilter::parse("status=200")
+            .unwrap()
+            .matches("status=200", &LogSource::Raw));
+        assert!(!access("upstream=backend"));
+    }
+
+    #[test]
+    fn levels_compare_by_severity() {
+        assert!(error("level>=error", "error"));
+        assert!(error("level>=error", "crit"));
+        assert!(error("level>=error", "emerg"));
+        assert!(!error("level>=error", "warn"));
+        assert!(!error("level>=error", "notice"));
+        assert!(error("level<warn", "info"));
+        assert!(!error("level<warn", "error"));
+        assert!(error("level=crit", "crit"));
+        assert!(error("level~^(crit|alert)$", "alert"));
+    }
+
+    #[test]
+    fn error_fields_are_parsed() {
+        assert!(error("server=example.com client~^203", "error"));
+        assert!(error("pid=12 connection=5", "error"));
+    }
+
+    #[test]
+    fn time_ranges_include_every_time_their_end_is_a_prefix_of() {
+        let range = TimeRange::parse("2023-08-01T12..2023-08-02").unwrap();
+        assert!(range.before("2023-08-01T11:59:59"));
+        assert!(range.contains("2023-08-01T12:00:00"));
+        assert!(range.contains("2023-08-02T23:59:59"));
+        assert!(range.after("2023-08-03T00:00:00"));
+        assert_eq!(range.to_string(), "2023-08-01T12..2023-08-02");
+
+        let open = TimeRange::parse(" ..2023-08").unwrap();
+        assert_eq!(open.from, None);
+        assert!(open.contains("1999-01-01T00:00:00"));
+        assert!(TimeRange::parse("2023-08-01").is_err());
+        assert!(TimeRange::parse("..").is_err());
+        assert!(TimeRange::parse("yesterday..").is_err());
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        assert!(LogFilter::parse("status~(").is_err());
+        assert!(LogFilter::parse("[unclosed").is_err());
+        assert!(LogFilter::parse("level>=err").is_err());
+        assert!(LogFilter::parse("level~err").is_ok());
+    }
+}
@@ -1,57 +1,277 @@
-use std::{fs::read_to_string, path::PathBuf};
+use std::{
+    fs::read_dir,
+    path::{Path, PathBuf},
+};
 
+use regex::Regex;
 use tui::{
     style::{Color, Modifier, Style},
-    text::{Span, Text},
-    widgets::{List, ListItem, Paragraph, Wrap},
+    text::{Line, Text},
+    widgets::{List, ListItem},
+};
+
+use crate::{
+    config::{Config, ConfigError, Directive},
+    config_tree::label,
+    version::get_configure_argument,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogKind {
+    Access,
+    Error,
+}
+
+impl LogKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogKind::Access => "access",
+            LogKind::Error => "error",
+        }
+    }
+}
+
+/// A log named by an `access_log` or `error_log` directive.
+#[derive(Debug, Clone)]
 pub struct LogLocation {
-    access_log: PathBuf,
-    error_log: PathBuf,
+    pub kind: LogKind,
+    /// The block the directive applies to, such as `http` or a server.
+    pub context: String,
+    /// The location block the directive is in, if any.
+    pub location: Option<String>,
+    /// The first argument as written in the config.
+    pub target: String,
+    /// The file written to, `None` for `off`, syslog, stderr, memory
+    /// buffers and paths built from variables.
+    pub path: Option<PathBuf>,
+    /// The `log_format` used by an access log.
+    pub format: Option<String>,
+    /// The files viewed for this entry, oldest first: the log itself, one
+    /// of its rotations, or every rotation followed by the log.
+    pub files: Vec<PathBuf>,
+    /// How a rotated file or a stream of them is named in the picker.
+    pub rotation: Option<String>,
 }
-pub fn get_log_locations() -> LogLocation {
-    LogLocation {
-        access_log: PathBuf::from("/var/log/nginx/access.log"),
-        error_log: PathBuf::from("/var/log/nginx/error.log"),
+
+impl LogLocation {
+    /// An entry for the log itself, `files` is filled in from `path`.
+    fn new(kind: LogKind, context: String, target: &str, path: Option<PathBuf>) -> Self {
+        Self {
+            kind,
+            context,
+            location: None,
+            target: target.to_string(),
+            files: path.iter().cloned().collect(),
+            path,
+            format: None,
+            rotation: None,
+        }
     }
 }
 
-pub fn log_locations_component() -> List<'static> {
-    let log_locations: LogLocation = get_log_locations();
-    let log_location_vec: Vec<_> = vec![log_locations.access_log, log_locations.error_log]
-        .into_iter()
-        .map(|path| path.to_string_lossy().into_owned())
-        .map(ListItem::new)
-        .collect();
+/// Every log the config writes to, grouped by the block it is declared in.
+///
+/// Without a config, or when the main context and `http` declare none, the
+/// paths nginx was built with are listed, as nginx falls back to them.
+pub fn get_log_locations(config: &Result<Config, ConfigError>) -> Vec<LogLocation> {
+    let prefix = get_configure_argument("prefix")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/usr/local/nginx"));
 
-    List::new(log_location_vec).highlight_style(
-        Style::default()
-            .add_modifier(Modifier::BOLD)
-            .fg(Color::LightBlue),
-    )
+    let mut logs = Vec::new();
+    if let Ok(config) = config {
+        config.walk(|directive, parents| {
+            let kind = match directive.name.as_str() {
+                "access_log" => LogKind::Access,
+                "error_log" => LogKind::Error,
+                _ => return,
+            };
+            let Some(target) = directive.arg(0) else {
+                return;
+            };
+            let context = match parents.iter().rev().find(|p| p.name != "location") {
+                Some(parent) if parent.name == "server" => label(parent),
+                Some(parent) => parent.name.clone(),
+                None => "main".to_string(),
+            };
+            let location = parents
+                .iter()
+                .rev()
+                .find(|p| p.name == "location")
+                .map(|p| label(p));
+            let format = match kind {
+                LogKind::Access if target != "off" => Some(access_log_format(directive)),
+                _ => None,
+            };
+            logs.push(LogLocation {
+                location,
+                format,
+                ..LogLocation::new(kind, context, target, log_path(kind, target, &prefix))
+            });
+        });
+    }
+
+    let declared = |kind: LogKind| {
+        logs.iter()
+            .any(|log| log.kind == kind && (log.context == "main" || log.context == "http"))
+    };
+    let mut defaults = Vec::new();
+    if !declared(LogKind::Access) {
+        let path = get_configure_argument("http-log-path")
+            .unwrap_or_else(|| "/var/log/nginx/access.log".to_string());
+        defaults.push(default_log(LogKind::Access, path, &prefix));
+    }
+    if !declared(LogKind::Error) {
+        let path = get_configure_argument("error-log-path")
+            .unwrap_or_else(|| "/var/log/nginx/error.log".to_string());
+        defaults.push(default_log(LogKind::Error, path, &prefix));
+    }
+    logs.splice(0..0, defaults);
+
+    // Keep the blocks together, in the order they first appear.
+    let mut contexts: Vec<String> = Vec::new();
+    for log in &logs {
+        if !contexts.contains(&log.context) {
+            contexts.push(log.context.clone());
+        }
+    }
+    logs.sort_by_key(|log| contexts.iter().position(|c| *c == log.context));
+
+    logs.into_iter().flat_map(with_rotations).collect()
 }
-pub fn access_log() -> Paragraph<'static> {
-    let log_location = get_log_locations().access_log;
-    let log_contents = read_log(&log_location);
 
-    let log_str = log_contents.content.to_string();
+fn default_log(kind: LogKind, path: String, prefix: &Path) -> LogLocation {
+    LogLocation {
+        format: (kind == LogKind::Access).then(|| "combined".to_string()),
+        ..LogLocation::new(
+            kind,
+            "built-in default".to_string(),
+            &path,
+            log_path(kind, &path, prefix),
+        )
+    }
+}
 
-    Paragraph::new(Text::from(log_str)).wrap(Wrap { trim: false })
+/// `log` followed by an entry for each of its rotated files, newest first,
+/// and one viewing them all as a single stream.
+fn with_rotations(log: LogLocation) -> Vec<LogLocation> {
+    let rotated = log.path.as_deref().map(rotated_files).unwrap_or_default();
+    if rotated.is_empty() {
+        return vec![log];
+    }
+    let mut entries = Vec::new();
+    for file in &rotated {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        entries.push(LogLocation {
+            files: vec![file.clone()],
+            rotation: Some(name.into_owned()),
+            ..log.clone()
+        });
+    }
+    entries.push(LogLocation {
+        files: rotated.iter().rev().chain(&log.files).cloned().collect(),
+        rotation: Some(format!("all {} files as one stream", rotated.len() + 1)),
+        ..log.clone()
+    });
+    entries.insert(0, log);
+    entries
 }
 
-pub fn error_log() -> Paragraph<'static> {
-    let log_location = get_log_locations().error_log;
-    let log_contents = read_log(&log_location);
+/// The files of `log` and all of its rotations, oldest first.
+pub fn log_files(log: &LogLocation) -> Vec<PathBuf> {
+    let Some(path) = log.path.as_deref() else {
+        return Vec::new();
+    };
+    let mut files = rotated_files(path);
+    files.reverse();
+    files.push(path.to_path_buf());
+    files
+}
 
-    let log_str = log_contents.content.to_string();
+/// Files next to `path` that logrotate moved it to, newest first: numbered
+/// ones such as `access.log.1` and `access.log.2.gz`, then dated ones such
+/// as `access.log-20230801.gz` from `dateext`.
+fn rotated_files(path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Vec::new();
+    };
+    let Ok(entries) = read_dir(dir) else {
+        return Vec::new();
+    };
+    let suffix = Regex::new(r"^(?:\.(\d+)|-(\d{8,10}))(?:\.gz)?$").unwrap();
+    let name = name.to_string_lossy();
+    let mut rotated: Vec<((bool, u64), PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let captures = suffix.captures(file_name.to_str()?.strip_prefix(&*name)?)?;
+            let key = match (captures.get(1), captures.get(2)) {
+                (Some(number), _) => (false, number.as_str().parse().ok()?),
+                (_, Some(date)) => (true, u64::MAX - date.as_str().parse::<u64>().ok()?),
+                _ => return None,
+            };
+            Some((key, entry.path()))
+        })
+        .collect();
+    rotated.sort();
+    rotated.into_iter().map(|(_, path)| path).collect()
+}
 
-    Paragraph::new(Text::from(log_str)).wrap(Wrap { trim: false })
+/// The format named after the path, skipping the `buffer=`, `gzip` and
+/// `if=` style parameters.
+fn access_log_format(directive: &Directive) -> String {
+    directive
+        .args
+        .get(1)
+        .filter(|arg| !arg.contains('=') && *arg != "gzip")
+        .cloned()
+        .unwrap_or_else(|| "combined".to_string())
 }
 
-fn read_log(path: &PathBuf) -> Span<'static> {
-    match read_to_string(path) {
-        Ok(contents) => Span::raw(contents),
-        Err(_) => Span::raw("Failed to read log file."),
+fn log_path(kind: LogKind, target: &str, prefix: &Path) -> Option<PathBuf> {
+    if target.contains('$')
+        || target.starts_with("syslog:")
+        || (kind == LogKind::Access && target == "off")
+        || (kind == LogKind::Error && (target == "stderr" || target.starts_with("memory:")))
+    {
+        return None;
     }
+    // `error_log off` is not special, nginx writes to a file called `off`.
+    Some(prefix.join(target))
+}
+
+/// The logs as a list, with a header line above the first log of each block.
+pub fn log_locations_component(logs: &[LogLocation]) -> List<'static> {
+    let header = Style::default()
+        .fg(Color::DarkGray)
+        .add_modifier(Modifier::BOLD);
+    let items: Vec<_> = logs
+        .iter()
+        .enumerate()
+        .map(|(i, log)| {
+            let mut lines = Vec::new();
+            if i == 0 || logs[i - 1].context != log.context {
+                lines.push(Line::styled(log.context.clone(), header));
+            }
+            let mut entry = match &log.rotation {
+                Some(rotation) => format!("    {}", rotation),
+                None => format!("  {:<6} {}", log.kind.as_str(), log.target),
+            };
+            if let Some(location) = &log.location {
+                entry.push_str(&format!(" ({})", location));
+            }
+            if log.path.is_none() {
+                lines.push(Line::styled(entry, Style::default().fg(Color::DarkGray)));
+            } else {
+                lines.push(Line::from(entry));
+            }
+            ListItem::new(Text::from(lines))
+        })
+        .collect();
+
+    List::new(items).highlight_style(
+        Style::default()
+            .add_modifier(Modifier::BOLD)
+            .fg(Color::LightBlue),
+    )
 }
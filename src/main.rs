@@ -1,14 +1,33 @@
 use lazynginx::app::{App, AppResult};
+use lazynginx::cli::{parse_args, USAGE};
 use lazynginx::event::{Event, EventHandler};
 use lazynginx::handler::handle_key_events;
 use lazynginx::tui::Tui;
-use std::io;
+use std::{env, io, process};
 use tui::backend::CrosstermBackend;
 use tui::Terminal;
 
 fn main() -> AppResult<()> {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
     // Create an application.
-    let mut app = App::new();
+    let mut app = match App::with_args(&args) {
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
 
     // Initialize the terminal user interface.
     let backend = CrosstermBackend::new(io::stderr());
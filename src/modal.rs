@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Wrap},
+};
+
+use crate::{service::ServiceCommand, validate::ConfigTest};
+
+/// Dialogs drawn over the current tab. While one is open it receives every key.
+#[derive(Debug)]
+pub enum Modal {
+    /// `command` was refused because the config test failed.
+    ConfigTestFailed {
+        command: ServiceCommand,
+        test: ConfigTest,
+    },
+    /// A template is about to replace the file at `path`.
+    OverwriteTemplate { path: PathBuf },
+    /// `user` is about to be removed from an htpasswd file.
+    RemoveHtpasswdUser { user: String },
+}
+
+impl Modal {
+    pub fn title(&self) -> String {
+        match self {
+            Modal::ConfigTestFailed { command, .. } => {
+                format!("Refusing to {}: config test failed", command.as_str())
+            }
+            Modal::OverwriteTemplate { .. } => "File exists".to_string(),
+            Modal::RemoveHtpasswdUser { .. } => "Remove user".to_string(),
+        }
+    }
+}
+
+pub fn modal_component(modal: &Modal) -> Paragraph<'static> {
+    match modal {
+        Modal::ConfigTestFailed { command, test } => {
+            let mut lines: Vec<Line> = test
+                .diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    let location = match (&diagnostic.file, diagnostic.line) {
+                        (Some(file), Some(line)) => format!(" in {}:{}", file.display(), line),
+                        _ => String::new(),
+                    };
+                    Line::from(vec![
+                        Span::styled(
+                            format!("[{}] ", diagnostic.level),
+                            Style::default().fg(diagnostic.level.color()),
+                        ),
+                        Span::raw(format!("{}{}", diagnostic.message, location)),
+                    ])
+                })
+                .collect();
+            if lines.is_empty() {
+                lines = test
+                    .result
+                    .stderr
+                    .lines()
+                    .map(|l| Line::from(l.to_string()))
+                    .collect();
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![
+                Span::styled("f", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {} anyway   ", command.as_str())),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": cancel"),
+            ]));
+            Paragraph::new(lines).wrap(Wrap { trim: false })
+        }
+        Modal::OverwriteTemplate { path } => Paragraph::new(vec![
+            Line::from(format!("{} already exists.", path.display())),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": replace it   "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": cancel"),
+            ]),
+        ])
+        .wrap(Wrap { trim: false }),
+        Modal::RemoveHtpasswdUser { user } => Paragraph::new(vec![
+            Line::from(format!("{} will no longer be able to log in.", user)),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": remove   "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": cancel"),
+            ]),
+        ])
+        .wrap(Wrap { trim: false }),
+    }
+}
+
+/// A rectangle of `percent_x` by `percent_y` of `area`, centered in it.
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(rows[1])[1]
+}
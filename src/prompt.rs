@@ -0,0 +1,72 @@
+use tui::{
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+/// What a [`Prompt`] asks for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptKind {
+    /// A line number to jump to in the log view.
+    GotoLine,
+    /// A regex to search the log view for.
+    Search,
+    /// A [`LogFilter`](crate::log_filter::LogFilter) for the log view.
+    Filter,
+    /// A [`TimeRange`](crate::log_filter::TimeRange) across the rotations
+    /// of the picked log.
+    TimeRange,
+    /// The value of the selected template field, labelled as given.
+    TemplateField(&'static str),
+    /// The name of a user to add to an htpasswd file.
+    HtpasswdUser,
+    /// A password, echoed as asterisks.
+    HtpasswdPassword,
+}
+
+impl PromptKind {
+    pub fn label(&self) -> String {
+        match self {
+            PromptKind::GotoLine => "Go to line: ".to_string(),
+            PromptKind::Search => "/".to_string(),
+            PromptKind::Filter => {
+                "Filter (regex, status>=500, path~^/api, time>=2023-08-01T12): ".to_string()
+            }
+            PromptKind::TimeRange => {
+                "Time range (2023-08-01T12..2023-08-02, empty for all): ".to_string()
+            }
+            PromptKind::TemplateField(label) => format!("{}: ", label),
+            PromptKind::HtpasswdUser => "User: ".to_string(),
+            PromptKind::HtpasswdPassword => "Password: ".to_string(),
+        }
+    }
+}
+
+/// A line of input typed at the bottom of a pane. While one is open it
+/// receives every key.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Self {
+            kind,
+            input: String::new(),
+        }
+    }
+}
+
+pub fn prompt_component(prompt: &Prompt) -> Paragraph<'_> {
+    Paragraph::new(Line::from(vec![
+        Span::styled(prompt.kind.label(), Style::default().fg(Color::LightBlue)),
+        if prompt.kind == PromptKind::HtpasswdPassword {
+            Span::raw("*".repeat(prompt.input.chars().count()))
+        } else {
+            Span::raw(prompt.input.as_str())
+        },
+        Span::styled("_", Style::default().fg(Color::DarkGray)),
+    ]))
+}
@@ -0,0 +1,82 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+
+/// Runs a job on a background thread every `interval`, so slow commands never
+/// block drawing or key handling.
+#[derive(Debug)]
+pub struct Poller<T> {
+    requests: Sender<()>,
+    results: Receiver<T>,
+    interval: Duration,
+    in_flight: bool,
+    /// A run was requested while another was under way.
+    queued: bool,
+    last_request: Option<Instant>,
+    /// When the last result arrived.
+    pub last_refresh: Option<DateTime<Local>>,
+}
+
+impl<T: Send + 'static> Poller<T> {
+    /// Spawns the thread running `job`. The first run happens on the first
+    /// [`Poller::poll`].
+    pub fn new(interval: Duration, mut job: impl FnMut() -> T + Send + 'static) -> Self {
+        let (requests, request_receiver) = mpsc::channel::<()>();
+        let (result_sender, results) = mpsc::channel();
+        thread::spawn(move || {
+            // Ends once the poller, and with it the request sender, is dropped.
+            while request_receiver.recv().is_ok() {
+                if result_sender.send(job()).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            requests,
+            results,
+            interval,
+            in_flight: false,
+            queued: false,
+            last_request: None,
+            last_refresh: None,
+        }
+    }
+
+    /// Returns a result if one arrived since the last call, and starts the
+    /// next run once `interval` has passed.
+    pub fn poll(&mut self) -> Option<T> {
+        let result = match self.results.try_recv() {
+            Ok(result) => {
+                self.in_flight = false;
+                self.last_refresh = Some(Local::now());
+                Some(result)
+            }
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => None,
+        };
+        if std::mem::take(&mut self.queued)
+            || self
+                .last_request
+                .is_none_or(|last| last.elapsed() >= self.interval)
+        {
+            self.request();
+        }
+        result
+    }
+
+    /// Starts a run now, or right after the one under way so its result
+    /// reflects everything that happened before this call.
+    pub fn request(&mut self) {
+        if self.in_flight {
+            self.queued = true;
+            return;
+        }
+        if self.requests.send(()).is_ok() {
+            self.in_flight = true;
+            self.last_request = Some(Instant::now());
+        }
+    }
+}
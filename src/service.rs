@@ -0,0 +1,464 @@
+use std::{
+    env,
+    fmt::Debug,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    command::{run_command, CommandResult},
+    config::{Config, ConfigError},
+    validate::{test_config, ConfigTest},
+    version::get_configure_argument,
+};
+
+/// Lifecycle commands offered on the Status tab.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServiceCommand {
+    Start,
+    Stop,
+    Restart,
+    Reload,
+    Status,
+    Enable,
+    Disable,
+}
+
+impl ServiceCommand {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ServiceCommand::Start => "start",
+            ServiceCommand::Stop => "stop",
+            ServiceCommand::Restart => "restart",
+            ServiceCommand::Reload => "reload",
+            ServiceCommand::Status => "status",
+            ServiceCommand::Enable => "enable",
+            ServiceCommand::Disable => "disable",
+        }
+    }
+
+    /// Commands that load the config, and take nginx down if it is broken.
+    pub fn needs_config_test(&self) -> bool {
+        matches!(self, ServiceCommand::Restart | ServiceCommand::Reload)
+    }
+}
+
+/// A way of controlling the nginx service on this host.
+pub trait ServiceManager: Debug + Send + Sync {
+    /// Short name shown in the UI and accepted by `--service-manager`.
+    fn name(&self) -> &'static str;
+
+    fn run(&self, command: ServiceCommand) -> CommandResult;
+
+    /// State of the service, such as `active` or `inactive`, `None` if it
+    /// cannot be determined.
+    fn state(&self) -> Option<String>;
+
+    /// Extra details the init system keeps about the service.
+    fn properties(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+}
+
+/// What the [`CommandRunner`] is doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Job {
+    Service(ServiceCommand),
+    /// `nginx -t` on its own, as asked for or after an edit to the config.
+    TestConfig,
+}
+
+impl Job {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Job::Service(command) => command.as_str(),
+            Job::TestConfig => "nginx -t",
+        }
+    }
+}
+
+/// What a command run by the [`CommandRunner`] came to.
+#[derive(Debug)]
+pub enum CommandOutcome {
+    /// `nginx -t` failed, so the command was not run.
+    TestFailed {
+        command: ServiceCommand,
+        test: ConfigTest,
+    },
+    Ran {
+        /// The `nginx -t` run before the command, if it loads the config.
+        test: Option<CommandResult>,
+        result: CommandResult,
+        forced: bool,
+    },
+    /// `nginx -t` run on its own.
+    Tested(ConfigTest),
+}
+
+enum CommandJob {
+    Service {
+        manager: Arc<dyn ServiceManager>,
+        config_path: PathBuf,
+        command: ServiceCommand,
+        forced: bool,
+    },
+    TestConfig {
+        config_path: PathBuf,
+    },
+}
+
+impl CommandJob {
+    fn job(&self) -> Job {
+        match self {
+            CommandJob::Service { command, .. } => Job::Service(*command),
+            CommandJob::TestConfig { .. } => Job::TestConfig,
+        }
+    }
+
+    fn run(self) -> CommandOutcome {
+        let (manager, config_path, command, forced) = match self {
+            CommandJob::Service {
+                manager,
+                config_path,
+                command,
+                forced,
+            } => (manager, config_path, command, forced),
+            CommandJob::TestConfig { config_path } => {
+                return CommandOutcome::Tested(test_config(&config_path))
+            }
+        };
+        let mut test = None;
+        if command.needs_config_test() && !forced {
+            let result = test_config(&config_path);
+            if !result.success {
+                return CommandOutcome::TestFailed {
+                    command,
+                    test: result,
+                };
+            }
+            test = Some(result.result);
+        }
+        CommandOutcome::Ran {
+            test,
+            result: manager.run(command),
+            forced,
+        }
+    }
+}
+
+/// Runs service commands and config tests on a background thread, one at
+/// a time, as some take seconds to finish.
+#[derive(Debug)]
+pub struct CommandRunner {
+    jobs: Sender<CommandJob>,
+    outcomes: Receiver<CommandOutcome>,
+    /// The command under way.
+    pub running: Option<Job>,
+}
+
+impl Default for CommandRunner {
+    fn default() -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<CommandJob>();
+        let (outcome_sender, outcomes) = mpsc::channel();
+        thread::spawn(move || {
+            // Ends once the runner, and with it the job sender, is dropped.
+            while let Ok(job) = job_receiver.recv() {
+                if outcome_sender.send(job.run()).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            jobs,
+            outcomes,
+            running: None,
+        }
+    }
+}
+
+impl CommandRunner {
+    /// Starts `command`, after testing the config if it loads it, unless
+    /// `forced`. Returns false, doing nothing, while another command is
+    /// under way.
+    pub fn start(
+        &mut self,
+        manager: Arc<dyn ServiceManager>,
+        config_path: &Path,
+        command: ServiceCommand,
+        forced: bool,
+    ) -> bool {
+        self.send(CommandJob::Service {
+            manager,
+            config_path: config_path.to_path_buf(),
+            command,
+            forced,
+        })
+    }
+
+    /// Starts `nginx -t -c <config_path>`, coming back as
+    /// [`CommandOutcome::Tested`]. Returns false, doing nothing, while
+    /// another command is under way.
+    pub fn test(&mut self, config_path: &Path) -> bool {
+        self.send(CommandJob::TestConfig {
+            config_path: config_path.to_path_buf(),
+        })
+    }
+
+    fn send(&mut self, job: CommandJob) -> bool {
+        if self.running.is_some() {
+            return false;
+        }
+        let running = job.job();
+        if self.jobs.send(job).is_err() {
+            return false;
+        }
+        self.running = Some(running);
+        true
+    }
+
+    /// The outcome of the command under way, once it finished.
+    pub fn poll(&mut self) -> Option<CommandOutcome> {
+        let outcome = self.outcomes.try_recv().ok()?;
+        self.running = None;
+        Some(outcome)
+    }
+}
+
+#[derive(Debug)]
+pub struct Systemd;
+
+impl ServiceManager for Systemd {
+    fn name(&self) -> &'static str {
+        "systemd"
+    }
+
+    fn run(&self, command: ServiceCommand) -> CommandResult {
+        let mut args = vec![command.as_str(), "nginx"];
+        if command == ServiceCommand::Status {
+            args.push("--no-pager");
+        }
+        run_command("systemctl", &args)
+    }
+
+    fn state(&self) -> Option<String> {
+        let result = run_command("systemctl", &["is-active", "nginx"]);
+        let state = result.stdout.trim();
+        (!state.is_empty()).then(|| state.to_string())
+    }
+
+    fn properties(&self) -> Vec<(String, String)> {
+        let result = run_command(
+            "systemctl",
+            &[
+                "show",
+                "nginx",
+                "--property=MainPID,ActiveEnterTimestamp,NRestarts",
+            ],
+        );
+        result
+            .stdout
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct OpenRc;
+
+impl ServiceManager for OpenRc {
+    fn name(&self) -> &'static str {
+        "openrc"
+    }
+
+    fn run(&self, command: ServiceCommand) -> CommandResult {
+        match command {
+            ServiceCommand::Enable => run_command("rc-update", &["add", "nginx", "default"]),
+            ServiceCommand::Disable => run_command("rc-update", &["del", "nginx", "default"]),
+            _ => run_command("rc-service", &["nginx", command.as_str()]),
+        }
+    }
+
+    fn state(&self) -> Option<String> {
+        lsb_state(&run_command("rc-service", &["nginx", "status"]))
+    }
+}
+
+#[derive(Debug)]
+pub struct SysV;
+
+impl ServiceManager for SysV {
+    fn name(&self) -> &'static str {
+        "sysv"
+    }
+
+    fn run(&self, command: ServiceCommand) -> CommandResult {
+        // Debian derivatives ship update-rc.d, Red Hat derivatives chkconfig.
+        match command {
+            ServiceCommand::Enable if in_path("update-rc.d") => {
+                run_command("update-rc.d", &["nginx", "enable"])
+            }
+            ServiceCommand::Disable if in_path("update-rc.d") => {
+                run_command("update-rc.d", &["nginx", "disable"])
+            }
+            ServiceCommand::Enable => run_command("chkconfig", &["nginx", "on"]),
+            ServiceCommand::Disable => run_command("chkconfig", &["nginx", "off"]),
+            _ => run_command("service", &["nginx", command.as_str()]),
+        }
+    }
+
+    fn state(&self) -> Option<String> {
+        lsb_state(&run_command("service", &["nginx", "status"]))
+    }
+}
+
+/// Talks to the nginx master directly with `nginx -s`, for hosts without an
+/// init system such as containers.
+#[derive(Debug)]
+pub struct Signal {
+    config_path: PathBuf,
+    pid_path: PathBuf,
+}
+
+impl Signal {
+    pub fn new(config_path: &Path, config: &Result<Config, ConfigError>) -> Self {
+        Self {
+            config_path: config_path.to_path_buf(),
+            pid_path: pid_path(config),
+        }
+    }
+
+    fn pid(&self) -> Option<u32> {
+        let pid: u32 = read_to_string(&self.pid_path).ok()?.trim().parse().ok()?;
+        Path::new("/proc")
+            .join(pid.to_string())
+            .exists()
+            .then_some(pid)
+    }
+
+    fn signal(&self, signal: &str) -> CommandResult {
+        let config = self.config_path.to_string_lossy();
+        run_command("nginx", &["-c", &config, "-s", signal])
+    }
+
+    fn start(&self) -> CommandResult {
+        run_command("nginx", &["-c", &*self.config_path.to_string_lossy()])
+    }
+}
+
+impl ServiceManager for Signal {
+    fn name(&self) -> &'static str {
+        "signal"
+    }
+
+    fn run(&self, command: ServiceCommand) -> CommandResult {
+        match command {
+            ServiceCommand::Start => self.start(),
+            ServiceCommand::Stop => self.signal("quit"),
+            ServiceCommand::Reload => self.signal("reload"),
+            ServiceCommand::Restart => {
+                let stop = self.signal("quit");
+                if !stop.success() {
+                    return stop;
+                }
+                // The master exits once its workers finish their requests.
+                let deadline = Instant::now() + Duration::from_secs(10);
+                while self.pid().is_some() && Instant::now() < deadline {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                let mut start = self.start();
+                start.command = format!("{} && {}", stop.command, start.command);
+                start.duration += stop.duration;
+                start.timestamp = stop.timestamp;
+                start
+            }
+            ServiceCommand::Status => match self.pid() {
+                Some(pid) => run_command("ps", &["-p", &pid.to_string(), "-o", "pid,etime,args"]),
+                None => CommandResult::failed(
+                    &format!("cat {}", self.pid_path.display()),
+                    "nginx is not running",
+                ),
+            },
+            ServiceCommand::Enable | ServiceCommand::Disable => CommandResult::failed(
+                &format!("nginx {}", command.as_str()),
+                "enable and disable need an init system, not supported by the signal backend",
+            ),
+        }
+    }
+
+    fn state(&self) -> Option<String> {
+        Some(match self.pid() {
+            Some(_) => "active".to_string(),
+            None => "inactive".to_string(),
+        })
+    }
+}
+
+/// Maps the LSB exit codes of an init script `status` action.
+fn lsb_state(result: &CommandResult) -> Option<String> {
+    match result.exit_code {
+        Some(0) => Some("active".to_string()),
+        Some(3) => Some("inactive".to_string()),
+        _ => None,
+    }
+}
+
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// The `pid` directive of the main context, or the path nginx was built with.
+pub fn pid_path(config: &Result<Config, ConfigError>) -> PathBuf {
+    let mut pid = None;
+    if let Ok(config) = config {
+        config.walk(|directive, parents| {
+            if parents.is_empty() && directive.name == "pid" {
+                pid = directive.arg(0).map(PathBuf::from);
+            }
+        });
+    }
+    pid.or_else(|| get_configure_argument("pid-path").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("/run/nginx.pid"))
+}
+
+/// Picks the backend by `name`, as given to `--service-manager`.
+pub fn service_manager_by_name(
+    name: &str,
+    config_path: &Path,
+    config: &Result<Config, ConfigError>,
+) -> Option<Arc<dyn ServiceManager>> {
+    match name {
+        "systemd" => Some(Arc::new(Systemd)),
+        "openrc" => Some(Arc::new(OpenRc)),
+        "sysv" => Some(Arc::new(SysV)),
+        "signal" => Some(Arc::new(Signal::new(config_path, config))),
+        _ => None,
+    }
+}
+
+/// Picks the backend matching the init system this host was booted with.
+pub fn detect_service_manager(
+    config_path: &Path,
+    config: &Result<Config, ConfigError>,
+) -> Arc<dyn ServiceManager> {
+    if Path::new("/run/systemd/system").is_dir() {
+        Arc::new(Systemd)
+    } else if in_path("rc-service") {
+        Arc::new(OpenRc)
+    } else if in_path("service") && Path::new("/etc/init.d/nginx").exists() {
+        Arc::new(SysV)
+    } else {
+        Arc::new(Signal::new(config_path, config))
+    }
+}
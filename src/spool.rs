@@ -0,0 +1,359 @@
+use std::{
+    collections::VecDeque,
+    env,
+    fmt::{self, Debug},
+    fs::{remove_file, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::log_filter::{LogSource, TimeRange};
+
+/// Numbers the spool files of this process.
+static NEXT_SPOOL: AtomicUsize = AtomicUsize::new(0);
+
+/// Most bytes a spool holds, so a large rotated log does not fill the disk.
+pub const SPOOL_LIMIT: u64 = 1024 * 1024 * 1024;
+
+/// Copies log files, decompressing `.gz` ones, one after the other into a
+/// temporary file that can be viewed like any other log. The last file,
+/// unless compressed, keeps being followed as it grows. With a time range,
+/// only the lines in it are copied, and copying stops at the first line
+/// past it. Copying stops for good once [`SPOOL_LIMIT`] bytes are written.
+///
+/// The temporary file is only readable by the current user, as logs hold
+/// client addresses and request paths, and is removed on drop.
+pub struct Spool {
+    pub path: PathBuf,
+    out: File,
+    /// Files not copied in full yet, the first one being read.
+    pending: VecDeque<PathBuf>,
+    reader: Option<Box<dyn BufRead + Send>>,
+    range: Option<(TimeRange, LogSource)>,
+    /// Start of a line of the followed file still being written.
+    partial: Vec<u8>,
+    /// Whether the last line with a time was copied, which decides for the
+    /// lines without one, such as the rest of a multi-line message.
+    keeping: bool,
+    /// Most bytes to write, and those written so far.
+    limit: u64,
+    written: u64,
+    /// Whether copying stopped at `limit` with data left to copy.
+    pub full: bool,
+}
+
+impl Spool {
+    pub fn new(files: &[PathBuf], range: Option<(TimeRange, LogSource)>) -> io::Result<Self> {
+        Self::with_limit(files, range, SPOOL_LIMIT)
+    }
+
+    /// Like [`Spool::new`], holding at most `limit` bytes.
+    pub fn with_limit(
+        files: &[PathBuf],
+        range: Option<(TimeRange, LogSource)>,
+        limit: u64,
+    ) -> io::Result<Self> {
+        let path = env::temp_dir().join(format!(
+            "lazynginx-{}-{}.log",
+            process::id(),
+            NEXT_SPOOL.fetch_add(1, Ordering::Relaxed)
+        ));
+        let out = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            out,
+            pending: files.iter().cloned().collect(),
+            reader: None,
+            range,
+            partial: Vec::new(),
+            keeping: false,
+            limit,
+            written: 0,
+            full: false,
+        })
+    }
+
+    /// Copies up to `budget` more bytes.
+    pub fn fill(&mut self, budget: u64) -> io::Result<()> {
+        let mut copied = 0;
+        while copied < budget {
+            let Some(file) = self.pending.front() else {
+                break;
+            };
+            let following = self.pending.len() == 1 && !is_gzip(file);
+            if self.reader.is_none() {
+                self.reader = Some(open(file)?);
+            }
+            let read = match (self.reader.as_mut(), self.range.is_some()) {
+                (Some(reader), false) => {
+                    let room = (budget - copied).min(self.limit - self.written);
+                    if self.written == self.limit && !reader.fill_buf()?.is_empty() {
+                        self.stop_full();
+                        break;
+                    }
+                    let read = io::copy(&mut reader.by_ref().take(room), &mut self.out)?;
+                    self.written += read;
+                    read
+                }
+                _ => self.copy_line(following)?,
+            };
+            copied += read;
+            if read == 0 {
+                if following {
+                    break;
+                }
+                self.pending.pop_front();
+                self.reader = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives up on the rest, the limit being reached.
+    fn stop_full(&mut self) {
+        self.full = true;
+        self.pending.clear();
+        self.reader = None;
+        self.partial.clear();
+    }
+
+    /// Reads the next line, copying it if it is in range. Returns the bytes
+    /// read.
+    fn copy_line(&mut self, following: bool) -> io::Result<u64> {
+        let (Some(reader), Some((range, source))) = (self.reader.as_mut(), &self.range) else {
+            return Ok(0);
+        };
+        let read = reader.read_until(b'\n', &mut self.partial)? as u64;
+        // A line still being written is decided on once the rest is.
+        let complete = self.partial.ends_with(b"\n") || (read == 0 && !following);
+        if self.partial.is_empty() || !complete {
+            return Ok(read);
+        }
+        let line = String::from_utf8_lossy(&self.partial);
+        match source.time(line.trim_end()) {
+            Some(time) if range.after(&time) => {
+                // Logs only move forward, nothing later is in range.
+                self.pending.clear();
+                self.reader = None;
+                self.partial.clear();
+                return Ok(0);
+            }
+            Some(time) => self.keeping = range.contains(&time),
+            None => {}
+        }
+        if self.keeping {
+            let len = self.partial.len() as u64 + u64::from(!self.partial.ends_with(b"\n"));
+            if self.written + len > self.limit {
+                // Only whole lines are copied.
+                self.stop_full();
+                return Ok(0);
+            }
+            self.out.write_all(&self.partial)?;
+            if !self.partial.ends_with(b"\n") {
+                self.out.write_all(b"\n")?;
+            }
+            self.written += len;
+        }
+        self.partial.clear();
+        Ok(read)
+    }
+}
+
+fn open(path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path)?;
+    Ok(if is_gzip(path) {
+        Box::new(BufReader::new(MultiGzDecoder::new(BufReader::new(file))))
+    } else {
+        Box::new(BufReader::new(file))
+    })
+}
+
+/// The first time logged in `path`, reading only the start of the file.
+fn first_time(path: &Path, source: &LogSource) -> Option<String> {
+    open(path)
+        .ok()?
+        .lines()
+        .take(100)
+        .map_while(Result::ok)
+        .find_map(|line| source.time(&line))
+}
+
+/// Those of `files`, oldest first, that may hold lines in `range`. Only the
+/// start of each is read: a rotated file ends where the next one starts.
+pub fn files_in_range(files: &[PathBuf], range: &TimeRange, source: &LogSource) -> Vec<PathBuf> {
+    let firsts: Vec<_> = files.iter().map(|file| first_time(file, source)).collect();
+    files
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| {
+            let next = firsts[i + 1..].iter().flatten().next();
+            !next.is_some_and(|next| range.before(next))
+                && !firsts[i].as_ref().is_some_and(|first| range.after(first))
+        })
+        .map(|(_, file)| file.clone())
+        .collect()
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.path);
+    }
+}
+
+impl Debug for Spool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spool")
+            .field("path", &self.path)
+            .field("pending", &self.pending)
+            .field("written", &self.written)
+            .finish()
+    }
+}
+
+pub fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    fn line(day: u32, hour: u32) -> String {
+        format!(
+            "2023/08/{:02} {:02}:00:00 [error] 1#1: day {} hour {}\n",
+            day, hour, day, hour
+        )
+    }
+
+    /// Rotations `.2.gz` and `.1` and the live log, each holding one day.
+    fn write_logs(name: &str) -> (PathBuf, Vec<PathBuf>) {
+        let dir = env::temp_dir().join(format!("lazynginx-spool-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let day = |day| (0..24).map(|hour| line(day, hour)).collect::<String>();
+
+        let oldest = dir.join("error.log.2.gz");
+        let mut gzip = GzEncoder::new(File::create(&oldest).unwrap(), Compression::default());
+        gzip.write_all(day(1).as_bytes()).unwrap();
+        gzip.finish().unwrap();
+        let middle = dir.join("error.log.1");
+        fs::write(&middle, day(2) + "  continued without a time\n").unwrap();
+        let live = dir.join("error.log");
+        fs::write(&live, day(3)).unwrap();
+        (dir, vec![oldest, middle, live])
+    }
+
+    fn spool(files: &[PathBuf], range: &str) -> String {
+        let range = TimeRange::parse(range).unwrap();
+        let mut spool = Spool::new(files, Some((range, LogSource::Error))).unwrap();
+        spool.fill(u64::MAX).unwrap();
+        fs::read_to_string(&spool.path).unwrap()
+    }
+
+    #[test]
+    fn files_are_picked_by_their_first_times() {
+        let (dir, files) = write_logs("pick");
+        let pick = |range: &str| {
+            let range = TimeRange::parse(range).unwrap();
+            files_in_range(&files, &range, &LogSource::Error)
+        };
+        assert_eq!(pick("2023-08-01..2023-08-01"), files[..1]);
+        assert_eq!(pick("2023-08-01T23..2023-08-02T01"), files[..2]);
+        assert_eq!(pick("2023-08-02T12.."), files[1..]);
+        assert_eq!(pick("..2023-08-02T00"), files[..2]);
+        assert_eq!(pick("2023-08-03T05.."), files[2..]);
+        // The live log may always have lines later than its first.
+        assert_eq!(pick("2023-09.."), files[2..]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn lines_are_trimmed_to_the_range() {
+        let (dir, files) = write_logs("trim");
+        let spooled = spool(&files, "2023-08-01T22..2023-08-02T01");
+        let expected = [line(1, 22), line(1, 23), line(2, 0), line(2, 1)].concat();
+        assert_eq!(spooled, expected);
+
+        // Lines without a time go with the line before them.
+        let spooled = spool(&files, "2023-08-02T23..2023-08-03T00");
+        let expected = [
+            line(2, 23),
+            "  continued without a time\n".to_string(),
+            line(3, 0),
+        ]
+        .concat();
+        assert_eq!(spooled, expected);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn copying_stops_past_the_range() {
+        let (dir, files) = write_logs("stop");
+        let range = TimeRange::parse("..2023-08-01T02").unwrap();
+        let mut spool = Spool::new(&files, Some((range, LogSource::Error))).unwrap();
+        spool.fill(u64::MAX).unwrap();
+        assert!(spool.pending.is_empty());
+        assert_eq!(fs::read_to_string(&spool.path).unwrap().lines().count(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn the_live_log_is_followed_a_whole_line_at_a_time() {
+        let (dir, files) = write_logs("follow");
+        let range = TimeRange::parse("2023-08-03T23..").unwrap();
+        let mut spool = Spool::new(&files[2..], Some((range, LogSource::Error))).unwrap();
+        spool.fill(u64::MAX).unwrap();
+
+        let mut live = OpenOptions::new().append(true).open(&files[2]).unwrap();
+        let next = line(4, 0);
+        live.write_all(&next.as_bytes()[..20]).unwrap();
+        spool.fill(u64::MAX).unwrap();
+        assert_eq!(fs::read_to_string(&spool.path).unwrap(), line(3, 23));
+        live.write_all(&next.as_bytes()[20..]).unwrap();
+        spool.fill(u64::MAX).unwrap();
+        assert_eq!(
+            fs::read_to_string(&spool.path).unwrap(),
+            line(3, 23) + &next
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn copying_stops_at_the_limit() {
+        let (dir, files) = write_logs("limit");
+        let mut spool = Spool::with_limit(&files, None, 100).unwrap();
+        spool.fill(u64::MAX).unwrap();
+        assert!(spool.full);
+        assert!(spool.pending.is_empty());
+        assert_eq!(fs::metadata(&spool.path).unwrap().len(), 100);
+
+        let range = TimeRange::parse("2023-08-02..").unwrap();
+        let mut spool = Spool::with_limit(&files, Some((range, LogSource::Error)), 100).unwrap();
+        spool.fill(u64::MAX).unwrap();
+        assert!(spool.full);
+        assert_eq!(
+            fs::read_to_string(&spool.path).unwrap(),
+            line(2, 0) + &line(2, 1)
+        );
+
+        // Copying everything up to the limit is not hitting it.
+        let day: usize = (0..24).map(|hour| line(1, hour).len()).sum();
+        let mut spool = Spool::with_limit(&files[..1], None, day as u64).unwrap();
+        spool.fill(u64::MAX).unwrap();
+        assert!(!spool.full);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
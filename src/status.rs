@@ -1,17 +1,342 @@
-use std::process::Command;
+use std::{
+    fs::{read_dir, read_link, read_to_string},
+    net::{Ipv4Addr, Ipv6Addr},
+    path::Path,
+    time::Duration,
+};
 
-pub fn get_nginx_status() -> String {
-    let output = Command::new("systemctl")
-        .arg("is-active")
-        .arg("nginx")
-        .output()
-        .expect("Failed to execute command");
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Wrap},
+};
 
-    let status = String::from_utf8(output.stdout).unwrap().trim().to_string();
+use crate::service::ServiceManager;
 
-    if status == "active" || status == "inactive" {
-        status
+/// Clock ticks per second used by `/proc/<pid>/stat`. `USER_HZ` is 100 on
+/// every architecture Linux exposes to userspace.
+const CLOCK_TICKS: f64 = 100.0;
+
+/// Everything the Status tab shows about the running nginx.
+#[derive(Debug, Clone, Default)]
+pub struct NginxStatus {
+    pub state: String,
+    pub master_pid: Option<u32>,
+    pub uptime: Option<Duration>,
+    pub workers: Vec<ProcessInfo>,
+    /// Addresses the master listens on, `None` when its sockets cannot be read.
+    pub listening: Option<Vec<String>>,
+    /// Properties reported by the service manager.
+    pub properties: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub title: String,
+    pub rss_kb: u64,
+    /// Average CPU usage since the process started.
+    pub cpu_percent: f64,
+}
+
+/// `/proc/<pid>/stat` fields needed here.
+struct Stat {
+    ppid: u32,
+    cpu_ticks: u64,
+    start_ticks: u64,
+}
+
+pub fn get_nginx_status(service_manager: &dyn ServiceManager, pid_path: &Path) -> NginxStatus {
+    let state = service_manager
+        .state()
+        .unwrap_or_else(|| "Error while getting status".to_string());
+    let properties = service_manager.properties();
+
+    let master_pid = properties
+        .iter()
+        .find(|(key, _)| key == "MainPID")
+        .and_then(|(_, value)| value.parse::<u32>().ok())
+        .filter(|&pid| pid != 0)
+        .or_else(|| read_to_string(pid_path).ok()?.trim().parse().ok())
+        .filter(|pid| Path::new("/proc").join(pid.to_string()).exists());
+
+    let Some(master_pid) = master_pid else {
+        return NginxStatus {
+            state,
+            properties,
+            ..NginxStatus::default()
+        };
+    };
+
+    let system_uptime = read_to_string("/proc/uptime")
+        .ok()
+        .and_then(|uptime| uptime.split_whitespace().next()?.parse::<f64>().ok());
+    let uptime = system_uptime
+        .zip(read_stat(master_pid))
+        .map(|(uptime, stat)| {
+            Duration::from_secs_f64((uptime - stat.start_ticks as f64 / CLOCK_TICKS).max(0.0))
+        });
+
+    NginxStatus {
+        state,
+        master_pid: Some(master_pid),
+        uptime,
+        workers: workers(master_pid, system_uptime.unwrap_or(0.0)),
+        listening: listening(master_pid),
+        properties,
+    }
+}
+
+fn read_stat(pid: u32) -> Option<Stat> {
+    parse_stat(&read_to_string(format!("/proc/{}/stat", pid)).ok()?)
+}
+
+fn parse_stat(stat: &str) -> Option<Stat> {
+    // The command name may contain spaces and parentheses, skip past it.
+    let fields: Vec<&str> = stat
+        .get(stat.rfind(')')? + 2..)?
+        .split_whitespace()
+        .collect();
+    // `fields[0]` is field 3 of proc(5).
+    Some(Stat {
+        ppid: fields.get(1)?.parse().ok()?,
+        cpu_ticks: fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?,
+        start_ticks: fields.get(19)?.parse().ok()?,
+    })
+}
+
+fn workers(master_pid: u32, system_uptime: f64) -> Vec<ProcessInfo> {
+    let Ok(entries) = read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut workers: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| {
+            let stat = read_stat(pid)?;
+            if stat.ppid != master_pid {
+                return None;
+            }
+            let running = system_uptime - stat.start_ticks as f64 / CLOCK_TICKS;
+            let cpu_percent = if running > 0.0 {
+                stat.cpu_ticks as f64 / CLOCK_TICKS / running * 100.0
+            } else {
+                0.0
+            };
+            let title = read_to_string(format!("/proc/{}/cmdline", pid))
+                .map(|cmdline| cmdline.replace('\0', " ").trim().to_string())
+                .unwrap_or_default();
+            Some(ProcessInfo {
+                pid,
+                title,
+                rss_kb: rss_kb(pid).unwrap_or(0),
+                cpu_percent,
+            })
+        })
+        .collect();
+    workers.sort_by_key(|worker| worker.pid);
+    workers
+}
+
+fn rss_kb(pid: u32) -> Option<u64> {
+    let status = read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Listening TCP sockets held open by `pid`. Reading another user's file
+/// descriptors needs root, in which case this returns `None`.
+fn listening(pid: u32) -> Option<Vec<String>> {
+    let inodes: Vec<String> = read_dir(format!("/proc/{}/fd", pid))
+        .ok()?
+        .flatten()
+        .filter_map(|fd| {
+            let target = read_link(fd.path()).ok()?;
+            let target = target.to_str()?;
+            Some(
+                target
+                    .strip_prefix("socket:[")?
+                    .strip_suffix(']')?
+                    .to_string(),
+            )
+        })
+        .collect();
+
+    let mut sockets = Vec::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        if let Ok(contents) = read_to_string(table) {
+            sockets.extend(listening_sockets(&contents, &inodes));
+        }
+    }
+    sockets.sort();
+    sockets.dedup();
+    Some(sockets)
+}
+
+/// Addresses of the listening sockets in a `/proc/net/tcp{,6}` table
+/// whose inode is one of `inodes`.
+fn listening_sockets(table: &str, inodes: &[String]) -> Vec<String> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // `0A` is TCP_LISTEN.
+            if fields.len() > 9 && fields[3] == "0A" && inodes.iter().any(|i| i == fields[9]) {
+                parse_address(fields[1])
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Decodes an `ADDRESS:PORT` column of `/proc/net/tcp{,6}`, where the
+/// address is printed as native endian 32 bit words.
+fn parse_address(hex: &str) -> Option<String> {
+    let (address, port) = hex.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let mut bytes = Vec::new();
+    for i in 0..address.len() / 8 {
+        let word = u32::from_str_radix(address.get(i * 8..i * 8 + 8)?, 16).ok()?;
+        bytes.extend(word.to_ne_bytes());
+    }
+    match bytes.len() {
+        4 => Some(format!(
+            "{}:{}",
+            Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]),
+            port
+        )),
+        16 => {
+            let bytes: [u8; 16] = bytes.try_into().ok()?;
+            Some(format!("[{}]:{}", Ipv6Addr::from(bytes), port))
+        }
+        _ => None,
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
     } else {
-        "Error while getting status".to_string()
+        format!("{}m {}s", minutes, secs % 60)
+    }
+}
+
+pub fn status_component(status: &NginxStatus) -> Paragraph<'static> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let state_color = match status.state.as_str() {
+        "active" => Color::Green,
+        "inactive" => Color::Yellow,
+        _ => Color::Red,
+    };
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{}: ", name), bold),
+            Span::raw(value),
+        ])
+    };
+
+    let mut lines = vec![Line::from(vec![
+        Span::styled("State: ", bold),
+        Span::styled(status.state.clone(), Style::default().fg(state_color)),
+    ])];
+    if let Some(pid) = status.master_pid {
+        lines.push(field("Master PID", pid.to_string()));
+    }
+    if let Some(uptime) = status.uptime {
+        lines.push(field("Uptime", format_duration(uptime)));
+    }
+    if status.master_pid.is_some() {
+        lines.push(field("Workers", status.workers.len().to_string()));
+        for worker in &status.workers {
+            lines.push(Line::from(format!(
+                "  {:>7} {:>7.1} MB {:>5.1}% CPU  {}",
+                worker.pid,
+                worker.rss_kb as f64 / 1024.0,
+                worker.cpu_percent,
+                worker.title.trim_start_matches("nginx: ")
+            )));
+        }
+        lines.push(Line::styled("Listening:", bold));
+        match &status.listening {
+            Some(sockets) if sockets.is_empty() => lines.push(Line::from("  none")),
+            Some(sockets) => lines.extend(
+                sockets
+                    .iter()
+                    .map(|socket| Line::from(format!("  {}", socket))),
+            ),
+            None => lines.push(Line::from("  unavailable, run as root")),
+        }
+    }
+    for (key, value) in &status.properties {
+        lines.push(field(key, value.clone()));
+    }
+
+    Paragraph::new(lines).wrap(Wrap { trim: false })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stat_lines() {
+        // The command name of a process can be anything, `)` and spaces too.
+        let stat = parse_stat(
+            "4321 (nginx: worker (a) b) S 1000 1000 1000 0 -1 4194624 811 0 0 0 15 7 0 0 20 0 1 \
+             0 5000 58830848 1412 18446744073709551615 1 1 0 0 0 0 0 1073745920 402745863 0 0 \
+             0 17 3 0 0 0 0 0\n",
+        )
+        .unwrap();
+        assert_eq!(stat.ppid, 1000);
+        assert_eq!(stat.cpu_ticks, 22);
+        assert_eq!(stat.start_ticks, 5000);
+
+        assert!(parse_stat("4321 (nginx) S 1000").is_none());
+        assert!(parse_stat("4321 nginx S 1000").is_none());
+    }
+
+    // `/proc/net/tcp` prints addresses as native endian words, these are
+    // from a little endian host.
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn parses_listening_sockets() {
+        let tcp = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0050 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 20481 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 20482 1 0000000000000000 100 0 0 10 0
+   2: 0100007F:1F90 0100007F:D2F0 01 00000000:00000000 00:00000000 00000000     0        0 20482 1 0000000000000000 20 4 30 10 -1
+   3: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 999 1 0000000000000000 100 0 0 10 0
+";
+        let tcp6 = "\
+  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:01BB 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 20483 1 0000000000000000 100 0 0 10 0
+   1: B80D0120000000000000000001000000:0050 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 20484 1 0000000000000000 100 0 0 10 0
+";
+        let inodes: Vec<String> = ["20481", "20482", "20483", "20484"]
+            .iter()
+            .map(|inode| inode.to_string())
+            .collect();
+        // The established connection and the socket of another process
+        // are left out.
+        assert_eq!(
+            listening_sockets(tcp, &inodes),
+            ["0.0.0.0:80", "127.0.0.1:8080"]
+        );
+        assert_eq!(
+            listening_sockets(tcp6, &inodes),
+            ["[::]:443", "[2001:db8::1]:80"]
+        );
+        assert_eq!(
+            parse_address("00000000000000000000000001000000:1F90").as_deref(),
+            Some("[::1]:8080")
+        );
+        assert_eq!(parse_address("0100007F"), None);
+        assert_eq!(parse_address("0100:0050"), None);
     }
 }
@@ -0,0 +1,539 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use tui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    symbols::Marker,
+    text::Span,
+    widgets::{Axis, Chart, Dataset, GraphType},
+};
+use ureq::Agent;
+
+use crate::{
+    config::{Config, ConfigError, Directive},
+    refresh::Poller,
+};
+
+/// How long connecting to nginx and reading its answer may take.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Responses longer than this are not stub_status output.
+const MAX_RESPONSE: u64 = 64 * 1024;
+
+/// Samples kept for the charts.
+const HISTORY: usize = 150;
+
+/// Reads one counter of a sample.
+type Counter = fn(&StubStatus) -> u64;
+
+/// The counters `ngx_http_stub_status_module` reports.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StubStatus {
+    pub active: u64,
+    pub accepts: u64,
+    pub handled: u64,
+    pub requests: u64,
+    pub reading: u64,
+    pub writing: u64,
+    pub waiting: u64,
+}
+
+impl StubStatus {
+    /// Parses the plain text page:
+    ///
+    /// ```text
+    /// Active connections: 291
+    /// server accepts handled requests
+    ///  16630948 16630948 31070465
+    /// Reading: 6 Writing: 179 Waiting: 106
+    /// ```
+    pub fn parse(text: &str) -> Option<Self> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let after = |word: &str, offset: usize| -> Option<u64> {
+            let i = words.iter().position(|w| *w == word)?;
+            words.get(i + offset)?.parse().ok()
+        };
+        Some(Self {
+            active: after("connections:", 1)?,
+            accepts: after("requests", 1)?,
+            handled: after("requests", 2)?,
+            requests: after("requests", 3)?,
+            reading: after("Reading:", 1)?,
+            writing: after("Writing:", 1)?,
+            waiting: after("Waiting:", 1)?,
+        })
+    }
+}
+
+/// Where the stub_status page is served.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    /// `host:port` connected to.
+    pub address: String,
+    /// Sent as the `Host` header, to reach the right server block.
+    pub host: String,
+    pub path: String,
+}
+
+impl Endpoint {
+    /// Parses a `http://host[:port][/path]` URL.
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("{} is not a http:// URL", url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if authority.is_empty() {
+            return Err(format!("{} has no host", url));
+        }
+        let has_port = match authority.rfind(']') {
+            Some(bracket) => authority[bracket..].contains(':'),
+            None => authority.contains(':'),
+        };
+        Ok(Self {
+            address: if has_port {
+                authority.to_string()
+            } else {
+                format!("{}:80", authority)
+            },
+            host: authority.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}{}", self.address, self.path)
+    }
+}
+
+/// The first `stub_status` location of the config, reached over loopback
+/// through the first plain HTTP `listen` of its server.
+pub fn find_stub_status(config: &Result<Config, ConfigError>) -> Option<Endpoint> {
+    let config = config.as_ref().ok()?;
+    let mut found = None;
+    config.walk(|directive, parents| {
+        if found.is_some() || directive.name != "stub_status" {
+            return;
+        }
+        let Some(server) = parents.iter().rev().find(|p| p.name == "server") else {
+            return;
+        };
+        let path = match parents.last() {
+            Some(location) if location.name == "location" => match location.args.as_slice() {
+                [path] | [_, path] if !path.starts_with('@') => match location.arg(0) {
+                    Some("~" | "~*") => return,
+                    _ => path.clone(),
+                },
+                _ => return,
+            },
+            _ => "/".to_string(),
+        };
+        let children = config.expand(server.children());
+        let listens: Vec<&Directive> = children
+            .iter()
+            .filter(|d| d.name == "listen")
+            .copied()
+            .collect();
+        let address = if listens.is_empty() {
+            Some("127.0.0.1:80".to_string())
+        } else {
+            listens.iter().find_map(|listen| loopback_address(listen))
+        };
+        let Some(address) = address else {
+            return;
+        };
+        let host = children
+            .iter()
+            .filter(|d| d.name == "server_name")
+            .flat_map(|d| d.args.iter())
+            .find(|name| {
+                !name.is_empty() && *name != "_" && !name.contains('*') && !name.starts_with('~')
+            })
+            .cloned()
+            .unwrap_or_else(|| "localhost".to_string());
+        found = Some(Endpoint {
+            address,
+            host,
+            path,
+        });
+    });
+    found
+}
+
+/// Where to connect for a `listen` directive, `None` for TLS, QUIC and
+/// unix sockets. Wildcard addresses are reached over loopback.
+fn loopback_address(listen: &Directive) -> Option<String> {
+    if listen
+        .args
+        .iter()
+        .skip(1)
+        .any(|arg| arg == "ssl" || arg == "quic")
+    {
+        return None;
+    }
+    let target = listen.arg(0)?;
+    if target.starts_with("unix:") {
+        return None;
+    }
+    if target.chars().all(|c| c.is_ascii_digit()) {
+        return Some(format!("127.0.0.1:{}", target));
+    }
+    if let Some(rest) = target.strip_prefix('[') {
+        let (address, port) = rest.split_once(']')?;
+        let port = port.strip_prefix(':').unwrap_or("80");
+        let address = if address == "::" { "::1" } else { address };
+        return Some(format!("[{}]:{}", address, port));
+    }
+    let (address, port) = target.split_once(':').unwrap_or((target, "80"));
+    let address = match address {
+        "*" | "0.0.0.0" => "127.0.0.1",
+        address => address,
+    };
+    Some(format!("{}:{}", address, port))
+}
+
+/// Requests the stub_status page once.
+pub fn fetch(endpoint: &Endpoint) -> Result<StubStatus, String> {
+    let body = http_get(endpoint)?;
+    StubStatus::parse(&body).ok_or_else(|| format!("{} is not a stub_status page", endpoint.url()))
+}
+
+/// The body of a plain HTTP page, which must answer 200.
+pub fn http_get(endpoint: &Endpoint) -> Result<String, String> {
+    let url = endpoint.url();
+    // Straight to nginx: a proxy or redirect would reach something else.
+    let agent: Agent = Agent::config_builder()
+        .http_status_as_error(false)
+        .max_redirects(0)
+        .proxy(None)
+        .timeout_global(Some(TIMEOUT))
+        .user_agent("lazynginx")
+        .build()
+        .into();
+    let mut response = agent
+        .get(&url)
+        .header("Host", &endpoint.host)
+        .call()
+        .map_err(|err| format!("cannot request {}: {}", url, err))?;
+    if response.status() != 200 {
+        return Err(format!("{} answered {}", url, response.status()));
+    }
+    response
+        .body_mut()
+        .with_config()
+        .limit(MAX_RESPONSE)
+        .read_to_string()
+        .map_err(|err| format!("cannot read {}: {}", url, err))
+}
+
+/// Polls a stub_status page in the background and keeps the recent samples.
+#[derive(Debug)]
+pub struct StubStatusMonitor {
+    pub endpoint: Endpoint,
+    /// Found in the config rather than given with `--stub-status`.
+    pub detected: bool,
+    poller: Poller<Result<StubStatus, String>>,
+    samples: VecDeque<(Instant, StubStatus)>,
+    /// Why the last request failed.
+    pub error: Option<String>,
+}
+
+impl StubStatusMonitor {
+    pub fn new(endpoint: Endpoint, detected: bool, interval: Duration) -> Self {
+        let poller = {
+            let endpoint = endpoint.clone();
+            Poller::new(interval, move || fetch(&endpoint))
+        };
+        Self {
+            endpoint,
+            detected,
+            poller,
+            samples: VecDeque::new(),
+            error: None,
+        }
+    }
+
+    pub fn poll(&mut self) {
+        match self.poller.poll() {
+            Some(Ok(status)) => {
+                self.error = None;
+                if self.samples.len() == HISTORY {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back((Instant::now(), status));
+            }
+            Some(Err(err)) => self.error = Some(err),
+            None => {}
+        }
+    }
+
+    pub fn latest(&self) -> Option<&StubStatus> {
+        self.samples.back().map(|(_, status)| status)
+    }
+
+    /// Seconds covered by the samples.
+    pub fn span(&self) -> f64 {
+        self.samples
+            .front()
+            .map_or(0.0, |(time, _)| time.elapsed().as_secs_f64())
+    }
+
+    /// Active, reading, writing and waiting connections over time, as
+    /// seconds before now.
+    pub fn connections(&self) -> Vec<Series> {
+        let now = Instant::now();
+        let latest = self.latest().copied().unwrap_or_default();
+        let gauges: [(&str, Color, u64, Counter); 4] = [
+            ("active", Color::LightBlue, latest.active, |s| s.active),
+            ("reading", Color::Green, latest.reading, |s| s.reading),
+            ("writing", Color::Yellow, latest.writing, |s| s.writing),
+            ("waiting", Color::DarkGray, latest.waiting, |s| s.waiting),
+        ];
+        gauges
+            .into_iter()
+            .map(|(name, color, current, value)| Series {
+                name: format!("{} {}", name, current),
+                color,
+                points: self
+                    .samples
+                    .iter()
+                    .map(|(time, status)| (-(now - *time).as_secs_f64(), value(status) as f64))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Accepted and handled connections and requests per second, between
+    /// consecutive samples. Counters going back, as after a restart, leave
+    /// a gap.
+    pub fn rates(&self) -> Vec<Series> {
+        let now = Instant::now();
+        let counters: [(&str, Color, Counter); 3] = [
+            ("accepts", Color::Cyan, |s| s.accepts),
+            ("handled", Color::Green, |s| s.handled),
+            ("requests", Color::Magenta, |s| s.requests),
+        ];
+        counters
+            .into_iter()
+            .map(|(name, color, value)| {
+                let points: Vec<(f64, f64)> = self
+                    .samples
+                    .iter()
+                    .zip(self.samples.iter().skip(1))
+                    .filter_map(|((before, old), (after, new))| {
+                        let elapsed = (*after - *before).as_secs_f64();
+                        let delta = value(new).checked_sub(value(old))?;
+                        (elapsed > 0.0)
+                            .then(|| (-(now - *after).as_secs_f64(), delta as f64 / elapsed))
+                    })
+                    .collect();
+                let current = points.last().map_or(0.0, |(_, rate)| *rate);
+                Series {
+                    name: format!("{} {:.1}/s", name, current),
+                    color,
+                    points,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A line of a chart, named with its latest value.
+pub struct Series {
+    pub name: String,
+    pub color: Color,
+    pub points: Vec<(f64, f64)>,
+}
+
+/// Lines over the last `span` seconds, from zero to their peak.
+pub fn series_component(series: &[Series], span: f64) -> Chart<'_> {
+    let peak = series
+        .iter()
+        .flat_map(|s| s.points.iter().map(|(_, y)| *y))
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let datasets = series
+        .iter()
+        .map(|s| {
+            Dataset::default()
+                .name(s.name.clone())
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(s.color))
+                .data(&s.points)
+        })
+        .collect();
+    let labels = Style::default().fg(Color::DarkGray);
+    Chart::new(datasets)
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 1)))
+        .x_axis(Axis::default().bounds([-span.max(1.0), 0.0]).labels(vec![
+            Span::styled(format!("-{:.0}s", span), labels),
+            Span::styled("now", labels),
+        ]))
+        .y_axis(Axis::default().bounds([0.0, peak * 1.1]).labels(vec![
+            Span::styled("0", labels),
+            Span::styled(format!("{:.0}", peak), labels.add_modifier(Modifier::BOLD)),
+        ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    const PAGE: &str = "Active connections: 291 \nserver accepts handled requests\n \
+                        16630948 16630947 31070465 \nReading: 6 Writing: 179 Waiting: 106 \n";
+
+    #[test]
+    fn parses_the_stub_status_page() {
+        let status = StubStatus::parse(PAGE).unwrap();
+        assert_eq!(status.active, 291);
+        assert_eq!(status.accepts, 16630948);
+        assert_eq!(status.handled, 16630947);
+        assert_eq!(status.requests, 31070465);
+        assert_eq!(status.reading, 6);
+        assert_eq!(status.writing, 179);
+        assert_eq!(status.waiting, 106);
+    }
+
+    #[test]
+    fn rejects_other_pages() {
+        assert!(StubStatus::parse("<html>Welcome to nginx!</html>").is_none());
+        assert!(StubStatus::parse(&PAGE.replace("Waiting: 106", "Waiting:")).is_none());
+        assert!(StubStatus::parse(&PAGE.replace("291", "many")).is_none());
+    }
+
+    #[test]
+    fn parses_endpoints() {
+        let endpoint = Endpoint::parse("http://status.local").unwrap();
+        assert_eq!(endpoint.address, "status.local:80");
+        assert_eq!(endpoint.host, "status.local");
+        assert_eq!(endpoint.path, "/");
+
+        let endpoint = Endpoint::parse("http://[::1]:8080/nginx_status").unwrap();
+        assert_eq!(endpoint.address, "[::1]:8080");
+        assert_eq!(endpoint.path, "/nginx_status");
+        assert_eq!(
+            Endpoint::parse("http://[::1]/").unwrap().address,
+            "[::1]:80"
+        );
+
+        assert!(Endpoint::parse("https://example.com/").is_err());
+        assert!(Endpoint::parse("http:///status").is_err());
+    }
+
+    #[test]
+    fn finds_the_stub_status_location() {
+        let dir =
+            std::env::temp_dir().join(format!("lazynginx-stub-status-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("nginx.conf");
+        fs::write(
+            &path,
+            "http {
+    server { listen 443 ssl; location /status { stub_status; } }
+    server {
+        listen [::]:8080;
+        server_name _ status.example.com;
+        location = /basic_status { stub_status; }
+    }
+}
+",
+        )
+        .unwrap();
+        let endpoint = find_stub_status(&Config::load(&path)).unwrap();
+        assert_eq!(
+            endpoint,
+            Endpoint {
+                address: "[::1]:8080".to_string(),
+                host: "status.example.com".to_string(),
+                path: "/basic_status".to_string(),
+            }
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Answers one request with `chunks` of a response, written apart so
+    /// that each arrives in a read of its own, and hands back the request.
+    fn serve(chunks: Vec<&'static str>) -> (Endpoint, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            while !request.ends_with("\r\n\r\n") {
+                if reader.read_line(&mut request).unwrap() == 0 {
+                    break;
+                }
+            }
+            let mut stream = stream;
+            for chunk in chunks {
+                stream.write_all(chunk.as_bytes()).unwrap();
+                stream.flush().unwrap();
+                thread::sleep(Duration::from_millis(20));
+            }
+            request
+        });
+        let endpoint = Endpoint {
+            address,
+            host: "status.example.com".to_string(),
+            path: "/status".to_string(),
+        };
+        (endpoint, server)
+    }
+
+    #[test]
+    fn reads_a_chunked_response() {
+        let (endpoint, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+            "19\r\nActive connections: 291",
+            " \n\r\n",
+            "7\r\nReading\r\n0\r\n\r\n",
+        ]);
+        let body = http_get(&endpoint).unwrap();
+        assert_eq!(body, "Active connections: 291 \nReading");
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /status HTTP/1.1\r\n"));
+        assert!(request.contains("\r\nhost: status.example.com\r\n"));
+    }
+
+    #[test]
+    fn reads_a_response_arriving_in_pieces() {
+        let (endpoint, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-",
+            "Length: 124\r\n\r\nActive connections: 291 \nserver accepts handled requests\n",
+            " 16630948 16630947 31070465 \nReading: 6 ",
+            "Writing: 179 Waiting: 106 \n",
+        ]);
+        let status = fetch(&endpoint).unwrap();
+        assert_eq!(status.requests, 31070465);
+        assert_eq!(status.waiting, 106);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn fails_on_other_statuses_and_cut_responses() {
+        let (endpoint, server) = serve(vec!["HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"]);
+        let err = http_get(&endpoint).unwrap_err();
+        assert!(err.ends_with("answered 404 Not Found"), "{}", err);
+        server.join().unwrap();
+
+        let (endpoint, server) =
+            serve(vec!["HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nActive"]);
+        let err = http_get(&endpoint).unwrap_err();
+        assert!(err.starts_with("cannot read"), "{}", err);
+        server.join().unwrap();
+    }
+}
@@ -5,7 +5,10 @@ pub enum Screen {
     Status,
     Config,
     Logs,
+    Dashboard,
     Template,
+    Certificates,
+    History,
     Unknown,
 }
 
@@ -14,7 +17,23 @@ pub fn get_current_screen(app: &App) -> Screen {
         0 => Screen::Status,
         1 => Screen::Config,
         2 => Screen::Logs,
-        3 => Screen::Template,
+        3 => Screen::Dashboard,
+        4 => Screen::Template,
+        5 => Screen::Certificates,
+        6 => Screen::History,
         _ => Screen::Unknown,
     }
 }
+
+pub fn get_screen_index(screen: Screen) -> usize {
+    match screen {
+        Screen::Status => 0,
+        Screen::Config => 1,
+        Screen::Logs => 2,
+        Screen::Dashboard => 3,
+        Screen::Template => 4,
+        Screen::Certificates => 5,
+        Screen::History => 6,
+        Screen::Unknown => 0,
+    }
+}
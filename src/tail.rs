@@ -0,0 +1,762 @@
+use std::{
+    fmt::{self, Debug},
+    fs::{metadata, File},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    os::unix::fs::MetadataExt,
+    path::PathBuf,
+};
+
+use crate::{
+    log_filter::{LogSource, TimeRange},
+    spool::{is_gzip, Spool},
+};
+use regex::Regex;
+
+use tui::{
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+/// A checkpoint is kept every this many lines, so finding a line means
+/// seeking to the checkpoint before it and skipping at most this many lines.
+const CHECKPOINT_EVERY: usize = 1024;
+
+/// Most bytes scanned for newlines in one poll, so indexing a large file is
+/// spread over several ticks instead of stalling a frame.
+const INDEX_BUDGET: u64 = 32 * 1024 * 1024;
+
+/// How far back from the end the view reads while the file is still being
+/// indexed.
+const TAIL_READ: u64 = 1024 * 1024;
+
+/// Besides every [`CHECKPOINT_EVERY`]th match, the filter keeps a
+/// checkpoint every this many bytes, so a sparse filter never rescans more
+/// than this to find a match.
+const FILTER_CHECKPOINT_BYTES: u64 = 1024 * 1024;
+
+/// Decides which lines a filtered view shows.
+pub type Predicate = Box<dyn Fn(&str) -> bool + Send>;
+
+/// A place the filter can resume scanning from.
+#[derive(Debug, Clone, Copy)]
+struct FilterCheckpoint {
+    /// Matches before this line.
+    row: usize,
+    /// Number of the line, counted from 0.
+    line: usize,
+    offset: u64,
+}
+
+/// The lines of a file a [`Predicate`] keeps, found by scanning the indexed
+/// part of the file a budget at a time. Like the line index, only sparse
+/// checkpoints are kept, and the matches near one are found again by
+/// rescanning from it.
+pub struct LineFilter {
+    predicate: Predicate,
+    checkpoints: Vec<FilterCheckpoint>,
+    /// Lines kept so far.
+    matches: usize,
+    /// Lines scanned so far.
+    scanned: usize,
+    /// Offset of the first line not scanned yet.
+    offset: u64,
+}
+
+impl LineFilter {
+    fn new(predicate: Predicate) -> Self {
+        let mut filter = Self {
+            predicate,
+            checkpoints: Vec::new(),
+            matches: 0,
+            scanned: 0,
+            offset: 0,
+        };
+        filter.reset();
+        filter
+    }
+
+    fn reset(&mut self) {
+        self.checkpoints = vec![FilterCheckpoint {
+            row: 0,
+            line: 0,
+            offset: 0,
+        }];
+        self.matches = 0;
+        self.scanned = 0;
+        self.offset = 0;
+    }
+
+    /// Takes in the scanned line at [`LineFilter::offset`], `len` bytes long.
+    fn push(&mut self, line: &str, len: usize) {
+        let matched = (self.predicate)(line);
+        let last = self.checkpoints[self.checkpoints.len() - 1];
+        if (matched && self.matches.is_multiple_of(CHECKPOINT_EVERY) && last.row < self.matches)
+            || self.offset - last.offset >= FILTER_CHECKPOINT_BYTES
+        {
+            self.checkpoints.push(FilterCheckpoint {
+                row: self.matches,
+                line: self.scanned,
+                offset: self.offset,
+            });
+        }
+        if matched {
+            self.matches += 1;
+        }
+        self.scanned += 1;
+        self.offset += len as u64;
+    }
+}
+
+impl Debug for LineFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineFilter")
+            .field("matches", &self.matches)
+            .field("scanned", &self.scanned)
+            .field("checkpoints", &self.checkpoints.len())
+            .finish()
+    }
+}
+
+/// A view of a log file that only keeps the visible lines in memory.
+///
+/// The file is scanned once for line breaks, remembering the offset of every
+/// [`CHECKPOINT_EVERY`]th line, then followed like `tail -F`: appended data
+/// is indexed as it arrives and the file is reopened when it is rotated or
+/// truncated.
+#[derive(Debug)]
+pub struct LogTailer {
+    /// The files shown, oldest first.
+    pub files: Vec<PathBuf>,
+    /// The times the lines shown are limited to.
+    pub range: Option<TimeRange>,
+    /// How the files are named in the UI.
+    pub name: String,
+    /// The file read: the log itself, or the spool of compressed or
+    /// several files.
+    path: PathBuf,
+    spool: Option<Spool>,
+    file: Option<File>,
+    /// Device and inode of the open file, to notice when the path is renamed.
+    identity: Option<(u64, u64)>,
+    /// Size of the file when it was last polled.
+    len: u64,
+    /// Bytes scanned for line breaks so far.
+    indexed: u64,
+    /// Complete lines found so far.
+    pub line_count: usize,
+    /// Bumped whenever the file is reopened or truncated and lines are
+    /// counted from the start again.
+    pub generation: u64,
+    /// Offset of line `i * CHECKPOINT_EVERY` at index `i`.
+    checkpoints: Vec<u64>,
+    /// Only lines kept by the filter are shown when set.
+    filter: Option<LineFilter>,
+    /// Keep the view at the bottom as lines arrive.
+    pub follow: bool,
+    /// First visible row when not following.
+    pub scroll: usize,
+    /// Height of the view when it was last drawn, used for paging.
+    pub height: usize,
+    /// The visible lines with their numbers, `None` while the file is still
+    /// being indexed and the lines were read from its end.
+    window: Vec<(Option<usize>, String)>,
+    /// What [`LogTailer::window`] was read for: top, height, lines and length.
+    window_key: Option<(usize, usize, usize, u64)>,
+    pub error: Option<String>,
+}
+
+impl LogTailer {
+    /// Views `files` as one log, oldest first. Compressed files and more
+    /// than one file are spooled into a temporary file.
+    pub fn new(files: &[PathBuf]) -> Self {
+        Self::with_range(files, None)
+    }
+
+    /// Views the lines of `files` logged in a time range, as told by
+    /// parsing them as `source`, through a spool holding only those.
+    pub fn with_range(files: &[PathBuf], range: Option<(TimeRange, LogSource)>) -> Self {
+        let mut name = match files {
+            [] => String::new(),
+            [file] => file.display().to_string(),
+            [first, .., last] => format!("{} to {}", first.display(), last.display()),
+        };
+        if let Some((range, _)) = &range {
+            name.push_str(&format!(", {}", range));
+        }
+        let shown_range = range.as_ref().map(|(range, _)| range.clone());
+        let (path, spool, error) = match files {
+            [file] if !is_gzip(file) && range.is_none() => (file.clone(), None, None),
+            _ => match Spool::new(files, range) {
+                Ok(spool) => (spool.path.clone(), Some(spool), None),
+                Err(err) => (
+                    PathBuf::new(),
+                    None,
+                    Some(format!("Failed to create a temporary file: {}", err)),
+                ),
+            },
+        };
+        let mut tailer = Self {
+            files: files.to_vec(),
+            range: shown_range,
+            name,
+            path,
+            spool,
+            file: None,
+            identity: None,
+            len: 0,
+            indexed: 0,
+            line_count: 0,
+            generation: 0,
+            checkpoints: vec![0],
+            filter: None,
+            follow: true,
+            scroll: 0,
+            height: 0,
+            window: Vec::new(),
+            window_key: None,
+            error: None,
+        };
+        if error.is_some() {
+            tailer.error = error;
+        } else {
+            tailer.poll();
+        }
+        tailer
+    }
+
+    fn open(&mut self) -> bool {
+        match File::open(&self.path).and_then(|file| Ok((file.metadata()?, file))) {
+            Ok((meta, file)) => {
+                self.identity = Some((meta.dev(), meta.ino()));
+                self.file = Some(file);
+                self.error = None;
+                self.reset();
+                true
+            }
+            Err(err) => {
+                self.error = Some(format!("Failed to read log file: {}", err));
+                false
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.len = 0;
+        self.indexed = 0;
+        self.line_count = 0;
+        self.generation += 1;
+        self.checkpoints = vec![0];
+        self.scroll = 0;
+        self.window_key = None;
+        if let Some(filter) = self.filter.as_mut() {
+            filter.reset();
+        }
+    }
+
+    /// Shows only the lines `predicate` keeps, or every line for `None`.
+    pub fn set_filter(&mut self, predicate: Option<Predicate>) {
+        self.filter = predicate.map(LineFilter::new);
+        self.follow = true;
+        self.scroll = 0;
+        self.window_key = None;
+        self.scan();
+    }
+
+    /// Picks up data appended since the last call.
+    pub fn poll(&mut self) {
+        if let Some(spool) = self.spool.as_mut() {
+            if let Err(err) = spool.fill(INDEX_BUDGET) {
+                self.error = Some(format!("Failed to read log file: {}", err));
+                return;
+            }
+        }
+        if self.file.is_none() && !self.open() {
+            return;
+        }
+
+        // Rotated: follow whatever now lives at the path. While nothing
+        // does, keep showing the old file.
+        let current = metadata(&self.path).ok().map(|m| (m.dev(), m.ino()));
+        if current.is_some() && current != self.identity && !self.open() {
+            return;
+        }
+
+        let Some(len) = self
+            .file
+            .as_ref()
+            .and_then(|f| f.metadata().ok())
+            .map(|m| m.len())
+        else {
+            return;
+        };
+        // Truncated in place, as with logrotate's copytruncate.
+        if len < self.indexed {
+            self.reset();
+        }
+        self.len = len;
+        if let Err(err) = self.index() {
+            self.error = Some(format!("Failed to read log file: {}", err));
+        }
+        self.scan();
+    }
+
+    fn scan(&mut self) {
+        if let Err(err) = self.scan_filter() {
+            self.error = Some(format!("Failed to read log file: {}", err));
+        }
+    }
+
+    /// Runs the filter over up to [`INDEX_BUDGET`] more bytes of indexed
+    /// lines.
+    fn scan_filter(&mut self) -> std::io::Result<()> {
+        let (Some(file), Some(filter)) = (self.file.as_mut(), self.filter.as_mut()) else {
+            return Ok(());
+        };
+        if filter.scanned >= self.line_count {
+            return Ok(());
+        }
+        file.seek(SeekFrom::Start(filter.offset))?;
+        let mut reader = BufReader::new(file.take(self.indexed - filter.offset));
+        let mut buf = Vec::new();
+        let budget_end = filter.offset + INDEX_BUDGET;
+        while filter.scanned < self.line_count && filter.offset < budget_end {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            filter.push(&decode_line(&buf), read);
+        }
+        Ok(())
+    }
+
+    /// Scans up to [`INDEX_BUDGET`] more bytes for line breaks.
+    fn index(&mut self) -> std::io::Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let end = self.len.min(self.indexed + INDEX_BUDGET);
+        if end <= self.indexed {
+            return Ok(());
+        }
+        file.seek(SeekFrom::Start(self.indexed))?;
+        let mut reader = BufReader::with_capacity(64 * 1024, file.take(end - self.indexed));
+        loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            for (i, _) in buf.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+                self.line_count += 1;
+                if self.line_count.is_multiple_of(CHECKPOINT_EVERY) {
+                    self.checkpoints.push(self.indexed + i as u64 + 1);
+                }
+            }
+            let read = buf.len();
+            self.indexed += read as u64;
+            reader.consume(read);
+        }
+        Ok(())
+    }
+
+    /// Whether part of the file has not been scanned for line breaks yet.
+    pub fn indexing(&self) -> bool {
+        self.indexed < self.len
+    }
+
+    /// Share of the file scanned so far, in percent.
+    pub fn indexed_percent(&self) -> u64 {
+        (self.indexed * 100).checked_div(self.len).unwrap_or(100)
+    }
+
+    /// Whether the spool stopped at [`crate::spool::SPOOL_LIMIT`] before
+    /// the end of the files.
+    pub fn spool_full(&self) -> bool {
+        self.spool.as_ref().is_some_and(|spool| spool.full)
+    }
+
+    pub fn filtered(&self) -> bool {
+        self.filter.is_some()
+    }
+
+    /// Whether the filter has not seen every indexed line yet.
+    pub fn filtering(&self) -> bool {
+        self.filter
+            .as_ref()
+            .is_some_and(|filter| filter.scanned < self.line_count)
+    }
+
+    /// Share of the indexed lines the filter has seen, in percent.
+    pub fn filtered_percent(&self) -> u64 {
+        let scanned = self.filter.as_ref().map_or(0, |filter| filter.offset);
+        (scanned * 100).checked_div(self.indexed).unwrap_or(100)
+    }
+
+    /// Rows the view can show: every line, or only those the filter kept.
+    pub fn rows(&self) -> usize {
+        match &self.filter {
+            Some(filter) => filter.matches,
+            None => self.line_count,
+        }
+    }
+
+    /// Index of the first row shown.
+    pub fn top(&self) -> usize {
+        if self.follow {
+            self.rows().saturating_sub(self.height)
+        } else {
+            self.scroll
+        }
+    }
+
+    /// Reads the lines that are visible now, unless they are already loaded.
+    pub fn load_window(&mut self) {
+        let key = (self.top(), self.height, self.rows(), self.len);
+        if self.window_key == Some(key) || self.file.is_none() {
+            return;
+        }
+        let window = if self.follow && self.indexing() && self.filter.is_none() {
+            self.read_tail(self.height)
+                .map(|lines| lines.into_iter().map(|line| (None, line)).collect())
+        } else {
+            self.read_rows(key.0, self.height)
+        };
+        match window {
+            Ok(window) => {
+                self.window = window;
+                self.window_key = Some(key);
+            }
+            Err(err) => self.error = Some(format!("Failed to read log file: {}", err)),
+        }
+    }
+
+    /// The lines loaded by [`LogTailer::load_window`], with their numbers.
+    pub fn window(&self) -> &[(Option<usize>, String)] {
+        &self.window
+    }
+
+    /// Reads up to `max` lines from line `first` on, ignoring the filter.
+    pub fn lines_from(&mut self, first: usize, max: usize) -> std::io::Result<Vec<String>> {
+        let count = max.min(self.line_count.saturating_sub(first));
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        self.read_lines(first, count)
+    }
+
+    /// Reads `count` rows starting at row `first`, with their line numbers.
+    fn read_rows(
+        &mut self,
+        first: usize,
+        count: usize,
+    ) -> std::io::Result<Vec<(Option<usize>, String)>> {
+        if self.filter.is_some() {
+            return self.read_matches(first, count);
+        }
+        Ok(self
+            .read_lines(first, count)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| (Some(first + i + 1), line))
+            .collect())
+    }
+
+    /// The first row after `from`, or before it when searching backwards,
+    /// whose line `predicate` keeps. Reads the file a block of lines at a
+    /// time until one is found.
+    pub fn find(
+        &mut self,
+        from: usize,
+        forward: bool,
+        predicate: impl Fn(&str) -> bool,
+    ) -> std::io::Result<Option<usize>> {
+        let rows = self.rows();
+        if forward {
+            let mut start = from + 1;
+            while start < rows {
+                let count = CHECKPOINT_EVERY - start % CHECKPOINT_EVERY;
+                let lines = self.read_rows(start, count)?;
+                if let Some(i) = lines.iter().position(|(_, line)| predicate(line)) {
+                    return Ok(Some(start + i));
+                }
+                start += count;
+            }
+        } else {
+            let mut end = from.min(rows);
+            while end > 0 {
+                let start = (end - 1) / CHECKPOINT_EVERY * CHECKPOINT_EVERY;
+                let lines = self.read_rows(start, end - start)?;
+                if let Some(i) = lines.iter().rposition(|(_, line)| predicate(line)) {
+                    return Ok(Some(start + i));
+                }
+                end = start;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads `count` lines kept by the filter, starting at match `first`.
+    fn read_matches(
+        &mut self,
+        first: usize,
+        count: usize,
+    ) -> std::io::Result<Vec<(Option<usize>, String)>> {
+        let mut lines = Vec::new();
+        self.rescan(
+            |checkpoint| checkpoint.row <= first,
+            |row, line, text| {
+                if row >= first {
+                    lines.push((Some(line + 1), text.to_string()));
+                }
+                lines.len() < count
+            },
+        )?;
+        Ok(lines)
+    }
+
+    /// Runs the filter again from the last checkpoint `before` holds for,
+    /// calling `visit` with the row, the line number and the text of every
+    /// line it keeps for as long as `visit` returns true.
+    fn rescan(
+        &mut self,
+        before: impl Fn(&FilterCheckpoint) -> bool,
+        mut visit: impl FnMut(usize, usize, &str) -> bool,
+    ) -> std::io::Result<()> {
+        let (Some(file), Some(filter)) = (self.file.as_mut(), self.filter.as_ref()) else {
+            return Ok(());
+        };
+        let index = filter.checkpoints.partition_point(before).saturating_sub(1);
+        let checkpoint = filter.checkpoints[index];
+        file.seek(SeekFrom::Start(checkpoint.offset))?;
+        let mut reader = BufReader::new(file.take(filter.offset - checkpoint.offset));
+        let mut buf = Vec::new();
+        let (mut row, mut line) = (checkpoint.row, checkpoint.line);
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                return Ok(());
+            }
+            let text = decode_line(&buf);
+            if (filter.predicate)(&text) {
+                if !visit(row, line, &text) {
+                    return Ok(());
+                }
+                row += 1;
+            }
+            line += 1;
+        }
+    }
+
+    /// Reads `count` indexed lines starting at line `first`.
+    fn read_lines(&mut self, first: usize, count: usize) -> std::io::Result<Vec<String>> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(Vec::new());
+        };
+        let checkpoint = (first / CHECKPOINT_EVERY).min(self.checkpoints.len() - 1);
+        file.seek(SeekFrom::Start(self.checkpoints[checkpoint]))?;
+        let mut reader = BufReader::new(file.take(self.indexed - self.checkpoints[checkpoint]));
+
+        let mut buf = Vec::new();
+        for _ in checkpoint * CHECKPOINT_EVERY..first {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                return Ok(Vec::new());
+            }
+        }
+        let mut lines = Vec::with_capacity(count);
+        for _ in 0..count.min(self.line_count.saturating_sub(first)) {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            lines.push(decode_line(&buf));
+        }
+        Ok(lines)
+    }
+
+    /// Reads the last `count` complete lines from the end of the file.
+    fn read_tail(&mut self, count: usize) -> std::io::Result<Vec<String>> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(Vec::new());
+        };
+        let start = self.len.saturating_sub(TAIL_READ);
+        let mut buf = Vec::new();
+        file.seek(SeekFrom::Start(start))?;
+        file.take(self.len - start).read_to_end(&mut buf)?;
+
+        // Drop the unterminated last line and, unless reading from the
+        // start, the line the read began in the middle of.
+        let end = buf.iter().rposition(|&b| b == b'\n').unwrap_or(0);
+        let mut lines: Vec<&[u8]> = buf[..end].split(|&b| b == b'\n').collect();
+        if start > 0 && !lines.is_empty() {
+            lines.remove(0);
+        }
+        let skip = lines.len().saturating_sub(count);
+        Ok(lines[skip..].iter().map(|line| decode_line(line)).collect())
+    }
+
+    /// Scrolls towards older lines, pausing follow mode.
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = self.top().saturating_sub(lines);
+        self.follow = false;
+    }
+
+    /// Scrolls towards newer lines, resuming follow mode at the bottom.
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.top() + lines;
+        if self.scroll + self.height >= self.rows() {
+            self.follow = true;
+        }
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll = 0;
+        self.follow = false;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.follow = true;
+    }
+
+    /// Shows row `row` at the top of the view.
+    pub fn goto_row(&mut self, row: usize) {
+        self.scroll = row.min(self.rows().saturating_sub(1));
+        self.follow = false;
+    }
+
+    /// Shows line `line`, counted from 1, at the top of the view. When
+    /// filtered, the first line kept at or after it.
+    pub fn goto_line(&mut self, line: usize) {
+        let target = line.saturating_sub(1);
+        let Some(matches) = self.filter.as_ref().map(|filter| filter.matches) else {
+            self.goto_row(target);
+            return;
+        };
+        let mut row = matches;
+        let found = self.rescan(
+            |checkpoint| checkpoint.line <= target,
+            |kept, number, _| {
+                if number < target {
+                    return true;
+                }
+                row = kept;
+                false
+            },
+        );
+        match found {
+            Ok(()) => self.goto_row(row),
+            Err(err) => self.error = Some(format!("Failed to read log file: {}", err)),
+        }
+    }
+}
+
+fn decode_line(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(line).into_owned()
+}
+
+/// The lines loaded by [`LogTailer::load_window`], with line numbers and
+/// matches of `search` highlighted.
+pub fn log_view_component<'a>(tailer: &'a LogTailer, search: Option<&Regex>) -> Paragraph<'a> {
+    if let Some(err) = &tailer.error {
+        return Paragraph::new(err.as_str());
+    }
+    let width = tailer.line_count.max(1).ilog10() as usize + 1;
+    let gutter = Style::default().fg(Color::DarkGray);
+    let highlight = Style::default().fg(Color::Black).bg(Color::Yellow);
+    let lines: Vec<Line> = tailer
+        .window()
+        .iter()
+        .map(|(number, line)| {
+            let number = match number {
+                Some(number) => format!("{:>width$} ", number, width = width),
+                None => format!("{:>width$} ", "", width = width),
+            };
+            let mut spans = vec![Span::styled(number, gutter)];
+            let mut last = 0;
+            for found in search.into_iter().flat_map(|regex| regex.find_iter(line)) {
+                spans.push(Span::raw(&line[last..found.start()]));
+                spans.push(Span::styled(found.as_str(), highlight));
+                last = found.end();
+            }
+            spans.push(Span::raw(&line[last..]));
+            Line::from(spans)
+        })
+        .collect();
+    Paragraph::new(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// A log of `count` lines of about 80 bytes, numbered from 0.
+    fn write_log(name: &str, count: usize) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "lazynginx-tail-{}-{}.log",
+            std::process::id(),
+            name
+        ));
+        let contents: String = (0..count)
+            .map(|i| format!("line {:06} {}\n", i, "x".repeat(68)))
+            .collect();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn number(line: &str) -> usize {
+        line[5..11].parse().unwrap()
+    }
+
+    fn check_filter(name: &str, keep: fn(usize) -> bool) {
+        let path = write_log(name, 40_000);
+        let mut tailer = LogTailer::new(std::slice::from_ref(&path));
+        tailer.set_filter(Some(Box::new(move |line| keep(number(line)))));
+        let expected: Vec<usize> = (0..40_000).filter(|&i| keep(i)).collect();
+        assert!(!tailer.filtering());
+        assert_eq!(tailer.rows(), expected.len());
+
+        for first in [0, 1, 1023, 1024, 1500, expected.len() - 3] {
+            let rows = tailer.read_rows(first, 5).unwrap();
+            let want: Vec<_> = expected.iter().skip(first).take(5).collect();
+            assert_eq!(rows.len(), want.len());
+            for ((number_shown, line), &&n) in rows.iter().zip(&want) {
+                assert_eq!(*number_shown, Some(n + 1));
+                assert_eq!(number(line), n);
+            }
+        }
+
+        for line in [1, 2, 5000, 20_001, 39_999] {
+            tailer.goto_line(line);
+            let want = expected.partition_point(|&n| n + 1 < line);
+            assert_eq!(tailer.scroll, want.min(expected.len() - 1), "line {}", line);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn dense_filter_matches_a_full_scan() {
+        check_filter("dense", |i| i % 3 == 0);
+    }
+
+    #[test]
+    fn sparse_filter_matches_a_full_scan() {
+        check_filter("sparse", |i| i % 997 == 5);
+    }
+
+    #[test]
+    fn filter_keeps_only_sparse_checkpoints() {
+        let path = write_log("checkpoints", 40_000);
+        let mut tailer = LogTailer::new(std::slice::from_ref(&path));
+        tailer.set_filter(Some(Box::new(|_| true)));
+        let filter = tailer.filter.as_ref().unwrap();
+        assert_eq!(filter.matches, 40_000);
+        // One per 1024 matches plus one per MiB scanned.
+        assert!(filter.checkpoints.len() < 40_000 / CHECKPOINT_EVERY + 5);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,1887 @@
+use std::{
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::{
+    config::{parse_snippet, Config, ConfigError, Directive, Node},
+    version::{built_with_module, get_configure_argument, NginxVersion},
+};
+
+/// How a form field is edited and what it holds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    /// A single word, such as a path.
+    Text,
+    /// Words separated by spaces or commas, such as server names.
+    List,
+    /// Words quoted in the template, such as a realm.
+    Phrase,
+    Number,
+    /// `on` or `off`.
+    Toggle,
+    /// One of the given values.
+    Choice(&'static [&'static str]),
+}
+
+/// A parameter of a [`Template`].
+#[derive(Debug)]
+pub struct Field {
+    /// Name used in the template source.
+    pub name: &'static str,
+    pub label: &'static str,
+    pub kind: FieldKind,
+    pub default: &'static str,
+    pub required: bool,
+    /// Shown under the form while the field is selected.
+    pub help: &'static str,
+}
+
+/// A config file generated from a source with `{{field}}` placeholders,
+/// see [`render`].
+#[derive(Debug)]
+pub struct Template {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub fields: &'static [Field],
+    pub source: &'static str,
+    /// Checks the values beyond what their kinds allow and adds values
+    /// computed from them, before the source is rendered.
+    pub prepare: fn(&mut Values, &Context) -> Result<(), String>,
+}
+
+/// What templates know about the nginx they are written for.
+#[derive(Debug, Clone)]
+pub struct Context {
+    /// The user and group worker processes run as.
+    pub user: String,
+    pub group: String,
+    /// ID of `group`, `None` when it is unknown to `/etc/group`.
+    pub gid: Option<u32>,
+    /// Whether the ngx_brotli module is built in or loaded.
+    pub brotli: bool,
+    /// Whether HTTP/2 is turned on with `http2 on`, as from nginx 1.25.1,
+    /// rather than with a `listen` parameter.
+    pub http2_directive: bool,
+    /// Prefix locations of the config, which templates can add to.
+    pub locations: Vec<ConfigLocation>,
+}
+
+/// A `location` block of the config.
+#[derive(Debug, Clone)]
+pub struct ConfigLocation {
+    /// Names of the server it is in.
+    pub server_names: Vec<String>,
+    /// Ports the server listens on.
+    pub ports: Vec<String>,
+    /// The prefix, or the exact path of a `location =`.
+    pub path: String,
+    pub file: PathBuf,
+    /// 1-based line of the closing `}`.
+    pub line: usize,
+    /// Byte offset of the closing `}` in `file`.
+    pub end: usize,
+    /// Whether it asks for a password already.
+    pub auth_basic: bool,
+}
+
+impl Context {
+    pub fn new(config: &Result<Config, ConfigError>, version: Option<&NginxVersion>) -> Self {
+        let mut user = None;
+        let mut brotli = false;
+        let mut locations = Vec::new();
+        if let Ok(config) = config {
+            config.walk(|directive, parents| match directive.name.as_str() {
+                "user" if parents.is_empty() => user = Some(directive.args.clone()),
+                "load_module" if parents.is_empty() => {
+                    brotli |= directive.arg(0).is_some_and(|m| m.contains("brotli"))
+                }
+                "location" => locations.extend(config_location(config, directive, parents)),
+                _ => {}
+            });
+        }
+        let (user, group) = match user.as_deref() {
+            Some([user, group, ..]) => (user.clone(), group.clone()),
+            // Without a group nginx uses the one named like the user.
+            Some([user]) => (user.clone(), user.clone()),
+            _ => {
+                let user = get_configure_argument("user").unwrap_or_else(|| "nobody".to_string());
+                let group = get_configure_argument("group").unwrap_or_else(|| user.clone());
+                (user, group)
+            }
+        };
+        let gid = fs::read_to_string("/etc/group")
+            .unwrap_or_default()
+            .lines()
+            .find_map(|line| {
+                let fields: Vec<&str> = line.split(':').collect();
+                (fields.len() > 2 && fields[0] == group).then(|| fields[2].parse().ok())?
+            });
+        Self {
+            user,
+            group,
+            gid,
+            brotli: brotli || built_with_module("brotli"),
+            http2_directive: version.is_some_and(|version| version.at_least(1, 25, 1)),
+            locations,
+        }
+    }
+}
+
+/// Where a prefix or exact `location` is and which server it is in.
+fn config_location(
+    config: &Config,
+    location: &Directive,
+    parents: &[&Directive],
+) -> Option<ConfigLocation> {
+    let path = match location.args.as_slice() {
+        [path] | [_, path] if path.starts_with('/') => path.clone(),
+        _ => return None,
+    };
+    if matches!(location.arg(0), Some("~" | "~*")) {
+        return None;
+    }
+    let server = parents
+        .iter()
+        .rev()
+        .find(|parent| parent.name == "server")?;
+    let children = config.expand(server.children());
+    let names = children.iter().filter(|d| d.name == "server_name");
+    let listens: Vec<&str> = children
+        .iter()
+        .filter(|d| d.name == "listen")
+        .filter_map(|d| d.arg(0))
+        .collect();
+    let ports = if listens.is_empty() {
+        vec!["80".to_string()]
+    } else {
+        listens
+            .into_iter()
+            .filter_map(listen_port)
+            .map(str::to_string)
+            .collect()
+    };
+    Some(ConfigLocation {
+        server_names: names.flat_map(|d| d.args.iter().cloned()).collect(),
+        ports,
+        path,
+        file: config.files.get(location.span.file)?.path.clone(),
+        line: location.span.end_line,
+        end: location.span.end - 1,
+        auth_basic: config
+            .expand(location.children())
+            .iter()
+            .any(|d| d.name == "auth_basic"),
+    })
+}
+
+/// The port a `listen` address names, 80 when it names none. `None` for
+/// unix sockets.
+fn listen_port(address: &str) -> Option<&str> {
+    if address.starts_with("unix:") {
+        return None;
+    }
+    Some(match address.rsplit_once(':') {
+        Some((_, port)) if !port.contains(']') => port,
+        _ if address.bytes().all(|b| b.is_ascii_digit()) => address,
+        _ => "80",
+    })
+}
+
+/// The location of `context` that the basic auth template adds to.
+fn find_location<'c>(values: &Values, context: &'c Context) -> Result<&'c ConfigLocation, String> {
+    let names = values.items("server_name");
+    let port = values.get("port").unwrap_or_default();
+    let path = values.get("location").unwrap_or_default();
+    let location = context
+        .locations
+        .iter()
+        .find(|location| {
+            location.path == path
+                && location.ports.iter().any(|p| p == port)
+                && location
+                    .server_names
+                    .iter()
+                    .any(|name| names.contains(&name.as_str()))
+        })
+        .ok_or_else(|| {
+            format!(
+                "No server named {} listening on {} has a location {}",
+                names.join(" "),
+                port,
+                path
+            )
+        })?;
+    if location.auth_basic {
+        return Err(format!(
+            "location {} in {} asks for a password already",
+            path,
+            location.file.display()
+        ));
+    }
+    Ok(location)
+}
+
+/// `source` with `lines` added at the end of the block closed by the `}`
+/// at byte `end`, one level deeper than the brace.
+pub fn insert_in_block(source: &str, end: usize, lines: &str) -> Result<String, String> {
+    if source.as_bytes().get(end) != Some(&b'}') {
+        return Err("the file changed since the config was read".to_string());
+    }
+    let line_start = source[..end].rfind('\n').map_or(0, |i| i + 1);
+    let indent: String = source[line_start..end]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+    // A brace sharing its line with the block is moved to a line of its own.
+    let alone = source[line_start..end].trim().is_empty();
+    let (before, after) = if alone {
+        (&source[..line_start], &source[line_start..])
+    } else {
+        (source[..end].trim_end(), &source[end..])
+    };
+    let mut out = before.to_string();
+    if !alone {
+        out.push('\n');
+    }
+    for line in lines.lines() {
+        if !line.is_empty() {
+            out.push_str(&indent);
+            out.push_str("    ");
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    if !alone {
+        out.push_str(&indent);
+    }
+    out.push_str(after);
+    Ok(out)
+}
+
+const SERVER_NAME: Field = Field {
+    name: "server_name",
+    label: "Server names",
+    kind: FieldKind::List,
+    default: "example.com www.example.com",
+    required: true,
+    help: "Host names this server answers to, separated by spaces.",
+};
+
+const PORT: Field = Field {
+    name: "port",
+    label: "Port",
+    kind: FieldKind::Number,
+    default: "80",
+    required: true,
+    help: "Port to listen on, for IPv4 and IPv6.",
+};
+
+/// The basic auth template's choice to add to a location of the config.
+const EXISTING_LOCATION: &str = "existing location";
+
+/// Templates offered on the Templates tab.
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "Server block",
+        description: "A plain server serving files from a directory.",
+        fields: &[
+            SERVER_NAME,
+            PORT,
+            Field {
+                name: "root",
+                label: "Root",
+                kind: FieldKind::Text,
+                default: "/var/www/html",
+                required: true,
+                help: "Directory files are served from.",
+            },
+            Field {
+                name: "index",
+                label: "Index files",
+                kind: FieldKind::List,
+                default: "index.html index.htm",
+                required: false,
+                help: "Files tried, in order, for requests ending with a slash.",
+            },
+        ],
+        source: include_str!("templates/server.conf"),
+        prepare: |values, _| check_port(values, "port"),
+    },
+    Template {
+        name: "Reverse proxy",
+        description:
+            "Passes every request to one or more backends, balancing the load between them.",
+        fields: &[
+            SERVER_NAME,
+            PORT,
+            Field {
+                name: "backends",
+                label: "Backends",
+                kind: FieldKind::List,
+                default: "127.0.0.1:3000",
+                required: true,
+                help: "host:port or unix:/path of each backend, separated by spaces.",
+            },
+            Field {
+                name: "balance",
+                label: "Load balancing",
+                kind: FieldKind::Choice(&["round-robin", "least_conn", "ip_hash", "random"]),
+                default: "round-robin",
+                required: true,
+                help: "round-robin takes turns, least_conn picks the least busy backend, ip_hash \
+                       keeps a client on one backend, random picks any.",
+            },
+            Field {
+                name: "websocket",
+                label: "WebSocket",
+                kind: FieldKind::Toggle,
+                default: "off",
+                required: true,
+                help: "Passes the Upgrade and Connection headers so WebSocket connections work.",
+            },
+            Field {
+                name: "headers",
+                label: "Forward headers",
+                kind: FieldKind::Toggle,
+                default: "on",
+                required: true,
+                help: "Sets Host, X-Real-IP, X-Forwarded-For and X-Forwarded-Proto so backends \
+                       see the original request.",
+            },
+            Field {
+                name: "connect_timeout",
+                label: "Connect timeout",
+                kind: FieldKind::Number,
+                default: "5",
+                required: true,
+                help: "Seconds to wait for a backend to accept the connection.",
+            },
+            Field {
+                name: "send_timeout",
+                label: "Send timeout",
+                kind: FieldKind::Number,
+                default: "60",
+                required: true,
+                help: "Seconds a backend may take to read each part of the request.",
+            },
+            Field {
+                name: "read_timeout",
+                label: "Read timeout",
+                kind: FieldKind::Number,
+                default: "60",
+                required: true,
+                help: "Seconds a backend may take between two reads of the response.",
+            },
+        ],
+        source: include_str!("templates/reverse_proxy.conf"),
+        prepare: prepare_reverse_proxy,
+    },
+    Template {
+        name: "Static site",
+        description: "Serves the files of a directory, with compression and browser caching.",
+        fields: &[
+            SERVER_NAME,
+            PORT,
+            Field {
+                name: "root",
+                label: "Root",
+                kind: FieldKind::Text,
+                default: "/var/www/html",
+                required: true,
+                help: "Directory files are served from. It must exist and be readable by the \
+                       user nginx workers run as.",
+            },
+            Field {
+                name: "index",
+                label: "Index files",
+                kind: FieldKind::List,
+                default: "index.html",
+                required: false,
+                help: "Files tried, in order, for requests ending with a slash.",
+            },
+            Field {
+                name: "spa",
+                label: "SPA fallback",
+                kind: FieldKind::Toggle,
+                default: "off",
+                required: true,
+                help: "Answers paths that are not files with /index.html, for single page apps \
+                       routing in the browser.",
+            },
+            Field {
+                name: "gzip",
+                label: "gzip",
+                kind: FieldKind::Toggle,
+                default: "on",
+                required: true,
+                help: "Compresses text responses with gzip.",
+            },
+            Field {
+                name: "brotli",
+                label: "Brotli",
+                kind: FieldKind::Toggle,
+                default: "off",
+                required: true,
+                help: "Compresses text responses with Brotli, for browsers that support it. \
+                       Needs the ngx_brotli module.",
+            },
+            Field {
+                name: "cache",
+                label: "Cache rules",
+                kind: FieldKind::List,
+                default: "css|js=7d png|jpg|jpeg|gif|svg|webp|ico=30d woff|woff2=1y html=no-cache",
+                required: false,
+                help: "extensions=time for how long browsers may cache files, such as css|js=7d. \
+                       Times take s, m, h, d, w, M and y suffixes, or are no-cache or max.",
+            },
+            Field {
+                name: "autoindex",
+                label: "Directory listing",
+                kind: FieldKind::Toggle,
+                default: "off",
+                required: true,
+                help: "Lists the files of directories without an index file.",
+            },
+        ],
+        source: include_str!("templates/static_site.conf"),
+        prepare: prepare_static_site,
+    },
+    Template {
+        name: "Basic auth",
+        description: "Asks for a user name and password before serving a location, in a new \
+                      server or one already in the config. Users are managed in the user file \
+                      below.",
+        fields: &[
+            SERVER_NAME,
+            PORT,
+            Field {
+                name: "location",
+                label: "Location",
+                kind: FieldKind::Text,
+                default: "/",
+                required: true,
+                help: "Path prefix that needs a password, / for the whole site.",
+            },
+            Field {
+                name: "target",
+                label: "Add to",
+                kind: FieldKind::Choice(&["new server", EXISTING_LOCATION]),
+                default: "new server",
+                required: true,
+                help: "Write a new server, or add to this location of a server in the config \
+                       with one of these names listening on the port. Content is only used \
+                       for a new server.",
+            },
+            Field {
+                name: "content",
+                label: "Content",
+                kind: FieldKind::Text,
+                default: "http://127.0.0.1:3000",
+                required: true,
+                help: "Directory to serve files from, or http:// URL of a backend to pass \
+                       requests to.",
+            },
+            Field {
+                name: "realm",
+                label: "Realm",
+                kind: FieldKind::Phrase,
+                default: "Restricted",
+                required: true,
+                help: "Shown by some browsers when asking for the password.",
+            },
+            Field {
+                name: "user_file",
+                label: "User file",
+                kind: FieldKind::Text,
+                default: "/etc/nginx/.htpasswd",
+                required: true,
+                help: "htpasswd file with the users allowed in, edited in the Users pane.",
+            },
+            Field {
+                name: "hash",
+                label: "Password hash",
+                kind: FieldKind::Choice(&["apr1", "bcrypt"]),
+                default: "apr1",
+                required: true,
+                help: "How passwords set in the Users pane are hashed. apr1 works everywhere, \
+                       bcrypt only where the system crypt() supports it, as libxcrypt does \
+                       but glibc's own crypt() does not.",
+            },
+        ],
+        source: include_str!("templates/basic_auth.conf"),
+        prepare: prepare_basic_auth,
+    },
+    Template {
+        name: "TLS",
+        description: "Serves a site over HTTPS with hardened TLS settings, redirecting plain \
+                      HTTP to it.",
+        fields: &[
+            SERVER_NAME,
+            Field {
+                name: "port",
+                label: "Port",
+                kind: FieldKind::Number,
+                default: "443",
+                required: true,
+                help: "Port to listen on for HTTPS, for IPv4 and IPv6.",
+            },
+            Field {
+                name: "content",
+                label: "Content",
+                kind: FieldKind::Text,
+                default: "/var/www/html",
+                required: true,
+                help: "Directory to serve files from, or http:// URL of a backend to pass \
+                       requests to.",
+            },
+            Field {
+                name: "certificate",
+                label: "Certificate",
+                kind: FieldKind::Text,
+                default: "/etc/letsencrypt/live/example.com/fullchain.pem",
+                required: true,
+                help: "PEM file with the certificate followed by the intermediate ones.",
+            },
+            Field {
+                name: "key",
+                label: "Key",
+                kind: FieldKind::Text,
+                default: "/etc/letsencrypt/live/example.com/privkey.pem",
+                required: true,
+                help: "PEM file with the private key of the certificate.",
+            },
+            Field {
+                name: "profile",
+                label: "Profile",
+                kind: FieldKind::Choice(&["intermediate", "modern"]),
+                default: "intermediate",
+                required: true,
+                help: "Mozilla's TLS profiles. intermediate suits almost every site, modern \
+                       drops TLS 1.2 and with it clients from before 2018 or so.",
+            },
+            Field {
+                name: "hsts",
+                label: "HSTS",
+                kind: FieldKind::Toggle,
+                default: "on",
+                required: true,
+                help: "Tells browsers to only use HTTPS for this site from now on. Hard to \
+                       undo, as browsers remember it for two years.",
+            },
+            Field {
+                name: "stapling",
+                label: "OCSP stapling",
+                kind: FieldKind::Toggle,
+                default: "on",
+                required: true,
+                help: "Sends the revocation status along with the certificate. nginx warns and \
+                       carries on when the CA runs no OCSP responder.",
+            },
+            Field {
+                name: "chain",
+                label: "Chain",
+                kind: FieldKind::Text,
+                default: "/etc/letsencrypt/live/example.com/chain.pem",
+                required: false,
+                help: "PEM file with the intermediate and root certificates, to check OCSP \
+                       answers with.",
+            },
+            Field {
+                name: "resolver",
+                label: "Resolver",
+                kind: FieldKind::List,
+                default: "1.1.1.1 9.9.9.9",
+                required: false,
+                help: "DNS servers nginx looks up the OCSP responder with.",
+            },
+            Field {
+                name: "redirect",
+                label: "HTTP redirect",
+                kind: FieldKind::Toggle,
+                default: "on",
+                required: true,
+                help: "Adds a server on port 80 redirecting every request to HTTPS.",
+            },
+        ],
+        source: include_str!("templates/tls.conf"),
+        prepare: prepare_tls,
+    },
+    Template {
+        name: "ACME certificate",
+        description: "Answers ACME HTTP-01 challenges on port 80, so a CA such as Let's Encrypt \
+                      can issue a certificate. Write it, reload nginx, then press i to issue.",
+        fields: &[
+            SERVER_NAME,
+            PORT,
+            Field {
+                name: "directory",
+                label: "Directory",
+                kind: FieldKind::Text,
+                default: "https://acme-v02.api.letsencrypt.org/directory",
+                required: true,
+                help: "ACME directory URL of the CA. Issuing agrees to its terms of service. Try \
+                       https://acme-staging-v02.api.letsencrypt.org/directory first, or \
+                       https://localhost:14000/dir for a local Pebble.",
+            },
+            Field {
+                name: "ca_bundle",
+                label: "CA bundle",
+                kind: FieldKind::Text,
+                default: "",
+                required: false,
+                help: "PEM file with the root the directory's HTTPS certificate is signed by, \
+                       for test servers such as Pebble. Mozilla's roots are used when empty.",
+            },
+            Field {
+                name: "email",
+                label: "Email",
+                kind: FieldKind::Text,
+                default: "",
+                required: false,
+                help: "Contact address for the account, which the CA may send expiry \
+                       notices to.",
+            },
+            Field {
+                name: "webroot",
+                label: "Webroot",
+                kind: FieldKind::Text,
+                default: "/var/lib/lazynginx/acme",
+                required: true,
+                help: "Directory the challenge answers are written to and served from.",
+            },
+            Field {
+                name: "certificate",
+                label: "Certificate",
+                kind: FieldKind::Text,
+                default: "/etc/nginx/ssl/example.com/fullchain.pem",
+                required: true,
+                help: "Where the issued certificate and its chain are written.",
+            },
+            Field {
+                name: "key",
+                label: "Key",
+                kind: FieldKind::Text,
+                default: "/etc/nginx/ssl/example.com/privkey.pem",
+                required: true,
+                help: "Where the new private key is written, readable by its owner only.",
+            },
+            Field {
+                name: "redirect",
+                label: "HTTPS redirect",
+                kind: FieldKind::Toggle,
+                default: "off",
+                required: true,
+                help: "Redirects every other request to HTTPS. Turn on once a TLS server uses \
+                       the certificate, and off the redirect of the TLS template.",
+            },
+        ],
+        source: include_str!("templates/acme.conf"),
+        prepare: prepare_acme,
+    },
+];
+
+fn check_port(values: &Values, name: &str) -> Result<(), String> {
+    match values.get(name).map(str::parse::<u16>) {
+        Some(Ok(1..)) | None => Ok(()),
+        _ => Err(format!("{} is not a port number", name)),
+    }
+}
+
+/// Checks the backend addresses and names the upstream after the server.
+fn prepare_reverse_proxy(values: &mut Values, _: &Context) -> Result<(), String> {
+    check_port(values, "port")?;
+    for backend in values.items("backends") {
+        if backend.starts_with("unix:/") {
+            continue;
+        }
+        let (host, port) = match backend.rsplit_once(':') {
+            Some((host, port)) if !host.ends_with(':') => (host, Some(port)),
+            _ => (backend, None),
+        };
+        let valid_host = !host.is_empty()
+            && (host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+                || host.starts_with('[') && host.ends_with(']'));
+        if !valid_host || port.is_some_and(|port| !matches!(port.parse::<u16>(), Ok(1..))) {
+            return Err(format!(
+                "backend {} is not a host:port or unix:/path address",
+                backend
+            ));
+        }
+    }
+    let name = values
+        .items("server_name")
+        .first()
+        .copied()
+        .unwrap_or("proxy");
+    let upstream: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    values.set("upstream", format!("{}_backend", upstream));
+    Ok(())
+}
+
+/// Checks the location and tells a directory from a backend URL, or finds
+/// the location to add to.
+fn prepare_basic_auth(values: &mut Values, context: &Context) -> Result<(), String> {
+    check_port(values, "port")?;
+    if !values.get("location").unwrap_or_default().starts_with('/') {
+        return Err("Location must start with /".to_string());
+    }
+    if values.get("target") == Some(EXISTING_LOCATION) {
+        find_location(values, context)?;
+        return Ok(());
+    }
+    set_content(values)
+}
+
+/// Tells a directory from a backend URL in the `content` field.
+fn set_content(values: &mut Values) -> Result<(), String> {
+    let content = values.get("content").unwrap_or_default().to_string();
+    if content.starts_with("http://") || content.starts_with("https://") {
+        values.set("proxy", content);
+        values.set("root", String::new());
+    } else if content.starts_with('/') {
+        values.set("proxy", String::new());
+        values.set("root", content);
+    } else {
+        return Err("Content must be a directory or a http:// URL".to_string());
+    }
+    Ok(())
+}
+
+/// Checks the paths and picks how to turn HTTP/2 on.
+fn prepare_tls(values: &mut Values, context: &Context) -> Result<(), String> {
+    check_port(values, "port")?;
+    set_content(values)?;
+    check_absolute(values, &["certificate", "key", "chain"])?;
+    if values.flag("stapling") && values.items("resolver").is_empty() {
+        return Err("OCSP stapling needs a resolver".to_string());
+    }
+    let https_port = match values.get("port") {
+        Some("443") => String::new(),
+        Some(port) => format!(":{}", port),
+        None => String::new(),
+    };
+    values.set("https_port", https_port);
+    values.set(
+        "http2_directive",
+        if context.http2_directive { "on" } else { "" }.to_string(),
+    );
+    Ok(())
+}
+
+/// Checks the names can be validated over HTTP and the paths.
+fn prepare_acme(values: &mut Values, _: &Context) -> Result<(), String> {
+    check_port(values, "port")?;
+    for name in values.items("server_name") {
+        if name.contains('*') || name.starts_with('~') || name == "_" {
+            return Err(format!(
+                "{} cannot be validated over HTTP, only plain host names can",
+                name
+            ));
+        }
+    }
+    if !values
+        .get("directory")
+        .unwrap_or_default()
+        .starts_with("https://")
+    {
+        return Err("Directory must be an https:// URL".to_string());
+    }
+    let email = values.get("email").unwrap_or_default();
+    if !email.is_empty() && !email.contains('@') {
+        return Err(format!("{} is not an email address", email));
+    }
+    check_absolute(values, &["ca_bundle", "webroot", "certificate", "key"])
+}
+
+/// Fails unless the fields called `names` are empty or absolute paths.
+fn check_absolute(values: &Values, names: &[&str]) -> Result<(), String> {
+    for name in names {
+        let path = values.get(name).unwrap_or_default();
+        if !path.is_empty() && !path.starts_with('/') {
+            return Err(format!("{} {} is not an absolute path", name, path));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the root is readable, brotli available and turns the cache
+/// rules into locations.
+fn prepare_static_site(values: &mut Values, context: &Context) -> Result<(), String> {
+    check_port(values, "port")?;
+    let root = Path::new(values.get("root").unwrap_or_default());
+    if !root.is_absolute() {
+        return Err(format!("Root {} is not an absolute path", root.display()));
+    }
+    check_readable(root, &context.user, &context.group)?;
+    if values.flag("brotli") && !context.brotli {
+        return Err(
+            "Brotli needs the ngx_brotli module, which this nginx neither has built in nor loads"
+                .to_string(),
+        );
+    }
+
+    let extensions = Regex::new(r"^[[:alnum:]]+(\|[[:alnum:]]+)*$").unwrap();
+    let time = Regex::new(r"^\d+(ms|s|m|h|d|w|M|y)?$").unwrap();
+    let mut locations = Vec::new();
+    for rule in values.items("cache") {
+        let (exts, expires) = rule
+            .split_once('=')
+            .filter(|(exts, _)| extensions.is_match(exts))
+            .ok_or_else(|| format!("Cache rule {} is not extensions=time", rule))?;
+        let expires = match expires {
+            "no-cache" => "-1",
+            "max" => "max",
+            expires if time.is_match(expires) => expires,
+            _ => return Err(format!("Cache rule {} has an invalid time", rule)),
+        };
+        locations.push(format!(
+            "    location ~* \\.({})$ {{\n        expires {};\n    }}",
+            exts, expires
+        ));
+    }
+    values.set("cache_locations", locations.join("\n\n"));
+    Ok(())
+}
+
+/// Fails unless `user` in `group` can list and read `dir`, going by the
+/// permission bits of it and its parents. Users not in `/etc/passwd` are
+/// not checked.
+fn check_readable(dir: &Path, user: &str, group: &str) -> Result<(), String> {
+    let metadata = fs::metadata(dir).map_err(|err| format!("Root {}: {}", dir.display(), err))?;
+    if !metadata.is_dir() {
+        return Err(format!("Root {} is not a directory", dir.display()));
+    }
+    let passwd = fs::read_to_string("/etc/passwd").unwrap_or_default();
+    let Some((uid, gid)) = passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        (fields.len() > 3 && fields[0] == user).then(|| {
+            Some((
+                fields[2].parse::<u32>().ok()?,
+                fields[3].parse::<u32>().ok()?,
+            ))
+        })?
+    }) else {
+        return Ok(());
+    };
+    if uid == 0 {
+        return Ok(());
+    }
+    let mut gids = vec![gid];
+    for line in fs::read_to_string("/etc/group").unwrap_or_default().lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() > 3
+            && (fields[0] == group || fields[3].split(',').any(|member| member == user))
+        {
+            gids.extend(fields[2].parse::<u32>().ok());
+        }
+    }
+
+    // Every parent must be searchable, the root itself readable too.
+    for path in dir.ancestors() {
+        let (needed, what) = if path == dir {
+            (0o5, "read")
+        } else {
+            (0o1, "enter")
+        };
+        let metadata = fs::metadata(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let mode = metadata.mode();
+        let bits = if metadata.uid() == uid {
+            mode >> 6
+        } else if gids.contains(&metadata.gid()) {
+            mode >> 3
+        } else {
+            mode
+        };
+        if bits & needed != needed {
+            return Err(format!(
+                "nginx workers run as {} and cannot {} {}",
+                user,
+                what,
+                path.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The values of a form, by field name.
+#[derive(Debug, Clone, Default)]
+pub struct Values {
+    entries: Vec<(&'static str, FieldKind, String)>,
+}
+
+impl Values {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(n, _, _)| *n == name)
+            .map(|(_, _, value)| value.as_str())
+    }
+
+    /// Sets a value computed by [`Template::prepare`], adding it if needed.
+    pub fn set(&mut self, name: &'static str, value: String) {
+        match self.entries.iter_mut().find(|(n, _, _)| *n == name) {
+            Some(entry) => entry.2 = value,
+            None => self.entries.push((name, FieldKind::Text, value)),
+        }
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.get(name) == Some("on")
+    }
+
+    pub fn items(&self, name: &str) -> Vec<&str> {
+        split_items(self.get(name).unwrap_or_default())
+    }
+
+    fn kind(&self, name: &str) -> Option<FieldKind> {
+        self.entries
+            .iter()
+            .find(|(n, _, _)| *n == name)
+            .map(|(_, kind, _)| *kind)
+    }
+}
+
+fn split_items(value: &str) -> Vec<&str> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Renders `source` with `values`:
+///
+/// - `{{name}}` is replaced by the value, the items of a list separated by
+///   spaces,
+/// - `{{#name}}...{{/name}}` is kept when a toggle is on or a value is not
+///   empty, `{{^name}}...{{/name}}` when it is off or empty,
+/// - `{{#name=value}}...{{/name=value}}` is kept when the value matches,
+/// - `{{*name}}...{{/name}}` is repeated for each item of a list, with
+///   `{{.}}` standing for the item.
+///
+/// Lines holding nothing but a section tag are left out of the output.
+pub fn render(source: &str, values: &Values) -> Result<String, String> {
+    // Drop standalone tag lines, keeping the tags themselves.
+    let mut compact = String::with_capacity(source.len());
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let standalone = trimmed.starts_with("{{")
+            && trimmed.ends_with("}}")
+            && trimmed.matches("{{").count() == 1
+            && matches!(trimmed.chars().nth(2), Some('#' | '^' | '*' | '/'));
+        compact.push_str(if standalone { trimmed } else { line });
+    }
+    let mut out = String::new();
+    render_into(&compact, values, None, &mut out)?;
+    Ok(out)
+}
+
+fn render_into(
+    source: &str,
+    values: &Values,
+    item: Option<&str>,
+    out: &mut String,
+) -> Result<(), String> {
+    let mut rest = source;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or("unclosed {{ in template")?;
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+
+        if let Some(name) = tag.strip_prefix(['#', '^', '*']) {
+            let (body, remainder) = split_section(rest, name)?;
+            rest = remainder;
+            let (inverted, each) = (tag.starts_with('^'), tag.starts_with('*'));
+            let (field, expected) = match name.split_once('=') {
+                Some((field, expected)) => (field, Some(expected)),
+                None => (name, None),
+            };
+            let value = values
+                .get(field)
+                .ok_or_else(|| format!("unknown field {} in template", field))?;
+            // The items the body is rendered for, the enclosing one unless
+            // iterating over a list.
+            let items: Vec<Option<&str>> = match (expected, values.kind(field)) {
+                _ if each => split_items(value).into_iter().map(Some).collect(),
+                (Some(expected), _) if value != expected => Vec::new(),
+                (None, Some(FieldKind::Toggle)) if value != "on" => Vec::new(),
+                (None, _) if value.is_empty() => Vec::new(),
+                _ => vec![item],
+            };
+            if inverted {
+                if items.is_empty() {
+                    render_into(body, values, item, out)?;
+                }
+            } else {
+                for item in items {
+                    render_into(body, values, item, out)?;
+                }
+            }
+        } else if tag.starts_with('/') {
+            return Err(format!("{{{{{}}}}} closes no section", tag));
+        } else if tag == "." {
+            out.push_str(item.unwrap_or_default());
+        } else {
+            let value = values
+                .get(tag)
+                .ok_or_else(|| format!("unknown field {} in template", tag))?;
+            match values.kind(tag) {
+                Some(FieldKind::List) => out.push_str(&split_items(value).join(" ")),
+                _ => out.push_str(value),
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(())
+}
+
+/// The body of the section `name` opened just before `source`, and what
+/// follows its closing tag.
+fn split_section<'s>(source: &'s str, name: &str) -> Result<(&'s str, &'s str), String> {
+    let open = ['#', '^', '*'].map(|sigil| format!("{{{{{}{}}}}}", sigil, name));
+    let close = format!("{{{{/{}}}}}", name);
+    let mut depth = 0;
+    let mut at = 0;
+    while let Some(found) = source[at..].find("{{") {
+        let start = at + found;
+        let tail = &source[start..];
+        if tail.starts_with(&close) {
+            if depth == 0 {
+                return Ok((&source[..start], &source[start + close.len()..]));
+            }
+            depth -= 1;
+        } else if open.iter().any(|open| tail.starts_with(open.as_str())) {
+            depth += 1;
+        }
+        at = start + 2;
+    }
+    Err(format!("{{{{#{}}}}} is never closed", name))
+}
+
+/// A template with the values typed in for it.
+#[derive(Debug)]
+pub struct TemplateForm {
+    pub template: &'static Template,
+    pub values: Vec<String>,
+    pub state: ListState,
+}
+
+impl TemplateForm {
+    pub fn new(template: &'static Template) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            template,
+            values: template
+                .fields
+                .iter()
+                .map(|field| field.default.to_string())
+                .collect(),
+            state,
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.state.selected().unwrap_or(0)
+    }
+
+    /// The value typed in for the field called `name`.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        let i = self.template.fields.iter().position(|f| f.name == name)?;
+        Some(self.values[i].trim())
+    }
+
+    pub fn selected_field(&self) -> &'static Field {
+        &self.template.fields[self.selected()]
+    }
+
+    /// Flips a toggle or moves a choice to its next value. Returns `false`
+    /// for fields that are typed in instead.
+    pub fn cycle_selected(&mut self) -> bool {
+        let i = self.selected();
+        let value = &mut self.values[i];
+        match self.template.fields[i].kind {
+            FieldKind::Toggle => {
+                *value = if value == "on" { "off" } else { "on" }.to_string();
+                true
+            }
+            FieldKind::Choice(options) => {
+                let next = options
+                    .iter()
+                    .position(|option| option == value)
+                    .map_or(0, |i| (i + 1) % options.len());
+                *value = options[next].to_string();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks every value against its field.
+    fn values(&self) -> Result<Values, String> {
+        let mut entries = Vec::new();
+        for (field, value) in self.template.fields.iter().zip(&self.values) {
+            let value = value.trim();
+            if value.is_empty() {
+                if field.required {
+                    return Err(format!("{} is required", field.label));
+                }
+            } else if let Some(c) = value
+                .chars()
+                .find(|c| matches!(c, ';' | '{' | '}' | '"' | '\'' | '#' | '\\') || c.is_control())
+            {
+                return Err(format!("{} cannot contain {:?}", field.label, c));
+            }
+            match field.kind {
+                FieldKind::Text if value.contains(char::is_whitespace) => {
+                    return Err(format!("{} cannot contain spaces", field.label));
+                }
+                FieldKind::Number if !value.is_empty() && value.parse::<u32>().is_err() => {
+                    return Err(format!("{} must be a number", field.label));
+                }
+                FieldKind::Choice(options) if !options.contains(&value) => {
+                    return Err(format!(
+                        "{} must be one of {}",
+                        field.label,
+                        options.join(", ")
+                    ));
+                }
+                _ => {}
+            }
+            entries.push((field.name, field.kind, value.to_string()));
+        }
+        Ok(Values { entries })
+    }
+
+    /// The config file for the current values, checked to parse.
+    pub fn render(&self, context: &Context) -> Result<String, String> {
+        let mut values = self.values()?;
+        (self.template.prepare)(&mut values, context)?;
+        let body = render(self.template.source, &values)?;
+        parse_snippet(&body).map_err(|err| format!("generated config does not parse: {}", err))?;
+        Ok(format!(
+            "# Generated by lazynginx from the {} template.\n{}",
+            self.template.name, body
+        ))
+    }
+
+    /// The location of the config the form adds to, `None` when it writes
+    /// a file of its own.
+    pub fn existing_location<'c>(
+        &self,
+        context: &'c Context,
+    ) -> Option<Result<&'c ConfigLocation, String>> {
+        if self.value("target") != Some(EXISTING_LOCATION) {
+            return None;
+        }
+        Some(
+            self.values()
+                .and_then(|values| find_location(&values, context)),
+        )
+    }
+
+    /// Named after the first server name and the template, so the ACME
+    /// challenge server and the TLS server for one host get separate files.
+    pub fn file_name(&self) -> String {
+        let template = self.template.name.to_lowercase().replace(' ', "-");
+        let host = self
+            .template
+            .fields
+            .iter()
+            .position(|field| field.name == "server_name")
+            .and_then(|i| split_items(&self.values[i]).first().map(|s| s.to_string()))
+            .filter(|name| !name.contains(['/', '*', '~']));
+        match host {
+            Some(host) => format!("{}-{}.conf", host, template),
+            None => format!("{}.conf", template),
+        }
+    }
+}
+
+/// The `sites-available` and `sites-enabled` directories next to the main
+/// config file.
+pub fn sites_dirs(config_path: &Path) -> (PathBuf, PathBuf) {
+    let dir = config_path.parent().unwrap_or(Path::new("/etc/nginx"));
+    (dir.join("sites-available"), dir.join("sites-enabled"))
+}
+
+/// Whether the config includes files from `dir`, as Debian's nginx.conf
+/// does with `include /etc/nginx/sites-enabled/*;`.
+pub fn includes_dir(config: &Result<Config, ConfigError>, config_path: &Path, dir: &Path) -> bool {
+    let Ok(config) = config else {
+        return false;
+    };
+    let prefix = config_path.parent().unwrap_or(Path::new(""));
+    // `walk` replaces includes by what they pull in, so look at the files.
+    fn any_include(nodes: &[Node], matches: &dyn Fn(&str) -> bool) -> bool {
+        nodes.iter().any(|node| match node {
+            Node::Directive(directive) if directive.name == "include" => {
+                directive.arg(0).is_some_and(matches)
+            }
+            Node::Directive(directive) => any_include(directive.children(), matches),
+            Node::Comment(_) => false,
+        })
+    }
+    let matches = |pattern: &str| prefix.join(pattern).parent() == Some(dir);
+    config
+        .files
+        .iter()
+        .any(|file| any_include(&file.nodes, &matches))
+}
+
+pub fn templates_component(forms: &[TemplateForm]) -> List<'static> {
+    let items: Vec<_> = forms
+        .iter()
+        .map(|form| ListItem::new(form.template.name))
+        .collect();
+    List::new(items).highlight_style(
+        Style::default()
+            .add_modifier(Modifier::BOLD)
+            .fg(Color::LightBlue),
+    )
+}
+
+/// The fields of `form` with their values.
+pub fn form_component(form: &TemplateForm) -> List<'static> {
+    let width = form
+        .template
+        .fields
+        .iter()
+        .map(|field| field.label.len())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<_> = form
+        .template
+        .fields
+        .iter()
+        .zip(&form.values)
+        .map(|(field, value)| {
+            let shown = match field.kind {
+                FieldKind::Toggle if value == "on" => "[x]".to_string(),
+                FieldKind::Toggle => "[ ]".to_string(),
+                FieldKind::Choice(_) => format!("‹{}›", value),
+                _ => value.clone(),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:<width$}  ", field.label, width = width),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(shown),
+            ]))
+        })
+        .collect();
+    List::new(items).highlight_style(Style::default().fg(Color::LightBlue))
+}
+
+/// The rendered config with comments dimmed, or why it cannot be rendered.
+pub fn preview_component(preview: &Result<String, String>) -> Paragraph<'static> {
+    match preview {
+        Ok(config) => Paragraph::new(Text::from(
+            config
+                .lines()
+                .map(|line| {
+                    if line.trim_start().starts_with('#') {
+                        Line::styled(line.to_string(), Style::default().fg(Color::DarkGray))
+                    } else {
+                        Line::from(line.to_string())
+                    }
+                })
+                .collect::<Vec<_>>(),
+        )),
+        Err(err) => Paragraph::new(Text::styled(err.clone(), Style::default().fg(Color::Red)))
+            .wrap(Wrap { trim: false }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(entries: &[(&'static str, FieldKind, &str)]) -> Values {
+        Values {
+            entries: entries
+                .iter()
+                .map(|(name, kind, value)| (*name, *kind, value.to_string()))
+                .collect(),
+        }
+    }
+
+    /// A form for the template called `name`, with `values` typed in over
+    /// the defaults.
+    fn form(name: &str, values: &[(&str, &str)]) -> TemplateForm {
+        let template = TEMPLATES.iter().find(|t| t.name == name).unwrap();
+        let mut form = TemplateForm::new(template);
+        for (name, value) in values {
+            let i = template
+                .fields
+                .iter()
+                .position(|f| f.name == *name)
+                .unwrap();
+            form.values[i] = value.to_string();
+        }
+        form
+    }
+
+    fn context() -> Context {
+        Context {
+            user: "root".to_string(),
+            group: "root".to_string(),
+            gid: Some(0),
+            brotli: false,
+            http2_directive: true,
+            locations: Vec::new(),
+        }
+    }
+
+    /// Every directive of `nodes` and the blocks in them, depth first.
+    fn directives(nodes: &[Node]) -> Vec<&Directive> {
+        let mut found = Vec::new();
+        for node in nodes {
+            if let Node::Directive(directive) = node {
+                found.push(directive);
+                found.extend(directives(directive.children()));
+            }
+        }
+        found
+    }
+
+    /// The arguments of each simple directive called `name` in `config`,
+    /// which must parse.
+    fn args(config: &str, name: &str) -> Vec<String> {
+        let nodes = parse_snippet(config).unwrap();
+        directives(&nodes)
+            .into_iter()
+            .filter(|directive| directive.name == name && !directive.is_block())
+            .map(|directive| directive.args.join(" "))
+            .collect()
+    }
+
+    #[test]
+    fn renders_sections() {
+        let values = values(&[
+            ("on", FieldKind::Toggle, "on"),
+            ("off", FieldKind::Toggle, "off"),
+            ("text", FieldKind::Text, "/srv"),
+            ("empty", FieldKind::Text, ""),
+            ("choice", FieldKind::Choice(&["a", "b"]), "b"),
+        ]);
+        let source = "\
+{{#on}}
+on
+{{/on}}
+{{#off}}
+off
+{{/off}}
+{{^off}}
+not off
+{{/off}}
+{{^on}}
+not on
+{{/on}}
+{{#text}}
+root {{text}};
+{{#empty}}
+nested empty
+{{/empty}}
+{{/text}}
+{{^empty}}
+empty
+{{/empty}}
+{{#choice=a}}
+a
+{{/choice=a}}
+{{#choice=b}}
+b
+{{/choice=b}}
+inline {{#on}}kept{{/on}}{{^on}}dropped{{/on}} here
+";
+        assert_eq!(
+            render(source, &values).unwrap(),
+            "on\nnot off\nroot /srv;\nempty\nb\ninline kept here\n"
+        );
+    }
+
+    #[test]
+    fn repeats_sections_over_lists() {
+        let source = "\
+{{*backends}}
+    server {{.}};
+{{/backends}}
+{{^backends}}
+    server 127.0.0.1:3000;
+{{/backends}}
+    server_name {{backends}};
+";
+        let list = |value| values(&[("backends", FieldKind::List, value)]);
+        assert_eq!(
+            render(source, &list("a:80, b:80\tunix:/run/app.sock")).unwrap(),
+            "    server a:80;\n    server b:80;\n    server unix:/run/app.sock;\n    \
+             server_name a:80 b:80 unix:/run/app.sock;\n"
+        );
+        assert_eq!(
+            render(source, &list("")).unwrap(),
+            "    server 127.0.0.1:3000;\n    server_name ;\n"
+        );
+    }
+
+    #[test]
+    fn fails_on_broken_templates() {
+        let values = values(&[("on", FieldKind::Toggle, "on")]);
+        for (source, err) in [
+            ("{{missing}}", "unknown field missing in template"),
+            (
+                "{{#missing}}x{{/missing}}",
+                "unknown field missing in template",
+            ),
+            ("{{#on}}x", "{{#on}} is never closed"),
+            ("x{{/on}}", "{{/on}} closes no section"),
+            ("{{on", "unclosed {{ in template"),
+        ] {
+            assert_eq!(render(source, &values).unwrap_err(), err, "{}", source);
+        }
+    }
+
+    #[test]
+    fn starts_from_the_defaults() {
+        let proxy = form("Reverse proxy", &[("server_name", "  app.example.com  ")]);
+        assert_eq!(proxy.value("server_name"), Some("app.example.com"));
+        assert_eq!(proxy.value("balance"), Some("round-robin"));
+        assert_eq!(proxy.value("connect_timeout"), Some("5"));
+        let config = proxy.render(&context()).unwrap();
+        assert_eq!(args(&config, "server"), ["127.0.0.1:3000"]);
+        assert_eq!(args(&config, "proxy_connect_timeout"), ["5s"]);
+
+        assert_eq!(
+            form("Reverse proxy", &[("backends", " ")])
+                .render(&context())
+                .unwrap_err(),
+            "Backends is required"
+        );
+    }
+
+    #[test]
+    fn keeps_config_syntax_out_of_values() {
+        // Values are put in as they are, so anything ending a directive or
+        // opening a block is refused rather than escaped.
+        for (value, c) in [
+            ("example.com; include /etc/passwd", ';'),
+            ("example.com { }", '{'),
+            ("\"example.com\"", '"'),
+            ("example.com # comment", '#'),
+            ("example\\.com", '\\'),
+            ("example.com\nlisten 81", '\n'),
+        ] {
+            let form = form("Server block", &[("server_name", value)]);
+            assert_eq!(
+                form.render(&context()).unwrap_err(),
+                format!("Server names cannot contain {:?}", c)
+            );
+        }
+        assert_eq!(
+            form("Reverse proxy", &[("port", "80 81")])
+                .render(&context())
+                .unwrap_err(),
+            "Port must be a number"
+        );
+
+        // `$` is text to the template, only `{{` starts a tag.
+        let values = values(&[("upstream", FieldKind::Text, "app_backend")]);
+        assert_eq!(
+            render("Connection ${{upstream}}_connection; ${host}", &values).unwrap(),
+            "Connection $app_backend_connection; ${host}"
+        );
+    }
+
+    #[test]
+    fn inserts_at_the_end_of_a_block() {
+        let source = "server {\n    location /admin {\n        proxy_pass http://app;\n    }\n}\n";
+        let end = source.find("    }").unwrap() + 4;
+        assert_eq!(
+            insert_in_block(source, end, "# Added\nauth_basic \"Staff\";\n").unwrap(),
+            "server {\n    location /admin {\n        proxy_pass http://app;\n        \
+             # Added\n        auth_basic \"Staff\";\n    }\n}\n"
+        );
+
+        let source = "server {\n    location / { return 204; }\n}\n";
+        let end = source.find("; }").unwrap() + 2;
+        assert_eq!(
+            insert_in_block(source, end, "auth_basic off;").unwrap(),
+            "server {\n    location / { return 204;\n        auth_basic off;\n    }\n}\n"
+        );
+
+        assert!(insert_in_block(source, 3, "auth_basic off;").is_err());
+    }
+
+    #[test]
+    fn renders_the_reverse_proxy() {
+        let config = form(
+            "Reverse proxy",
+            &[
+                ("server_name", "app.example.com api.example.com"),
+                ("port", "8080"),
+                (
+                    "backends",
+                    "10.0.0.1:3000, 10.0.0.2:3000 [::1]:3001 backend.internal unix:/run/app.sock",
+                ),
+                ("balance", "least_conn"),
+                ("websocket", "on"),
+                ("read_timeout", "300"),
+            ],
+        )
+        .render(&context())
+        .unwrap();
+        let nodes = parse_snippet(&config).unwrap();
+        let upstream = directives(&nodes)
+            .into_iter()
+            .find(|directive| directive.name == "upstream")
+            .unwrap();
+        assert_eq!(upstream.args, ["app_example_com_backend"]);
+        let upstream: Vec<String> = directives(upstream.children())
+            .iter()
+            .map(|directive| {
+                std::iter::once(&directive.name)
+                    .chain(&directive.args)
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        assert_eq!(
+            upstream,
+            [
+                "least_conn",
+                "server 10.0.0.1:3000",
+                "server 10.0.0.2:3000",
+                "server [::1]:3001",
+                "server backend.internal",
+                "server unix:/run/app.sock",
+                "keepalive 32",
+            ]
+        );
+        assert_eq!(args(&config, "listen"), ["8080", "[::]:8080"]);
+        assert_eq!(
+            args(&config, "server_name"),
+            ["app.example.com api.example.com"]
+        );
+        assert_eq!(
+            args(&config, "proxy_pass"),
+            ["http://app_example_com_backend"]
+        );
+        assert_eq!(
+            args(&config, "proxy_set_header")[..2],
+            [
+                "Upgrade $http_upgrade",
+                "Connection $app_example_com_backend_connection"
+            ]
+        );
+        assert_eq!(args(&config, "proxy_read_timeout"), ["300s"]);
+        assert!(config.contains("map $http_upgrade $app_example_com_backend_connection {"));
+
+        // Round robin is nginx's default, so it is not named.
+        for (balance, directive) in [("ip_hash", "ip_hash"), ("random", "random")] {
+            let config = form("Reverse proxy", &[("balance", balance)])
+                .render(&context())
+                .unwrap();
+            assert_eq!(args(&config, directive).len(), 1);
+            assert!(args(&config, "least_conn").is_empty());
+        }
+        let config = form("Reverse proxy", &[]).render(&context()).unwrap();
+        for directive in ["least_conn", "ip_hash", "random"] {
+            assert!(args(&config, directive).is_empty());
+        }
+        // An empty Connection header lets keepalive connections be reused.
+        assert_eq!(args(&config, "proxy_set_header")[0], "Connection ");
+        assert!(!config.contains("map "));
+
+        assert_eq!(
+            form("Reverse proxy", &[("backends", "10.0.0.1:0")])
+                .render(&context())
+                .unwrap_err(),
+            "backend 10.0.0.1:0 is not a host:port or unix:/path address"
+        );
+    }
+
+    #[test]
+    fn renders_the_static_site() {
+        let root = std::env::temp_dir().join(format!("lazynginx-static-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let root_value = root.display().to_string();
+        let site = |values: &[(&str, &str)], context: &Context| {
+            let mut values = values.to_vec();
+            values.push(("root", &root_value));
+            form("Static site", &values).render(context)
+        };
+
+        let config = site(
+            &[
+                ("index", "index.html, index.htm"),
+                ("spa", "on"),
+                ("autoindex", "on"),
+            ],
+            &context(),
+        )
+        .unwrap();
+        assert_eq!(args(&config, "root"), [root_value.as_str()]);
+        assert_eq!(args(&config, "index"), ["index.html index.htm"]);
+        assert_eq!(args(&config, "autoindex"), ["on"]);
+        assert_eq!(args(&config, "try_files"), ["$uri $uri/ /index.html"]);
+        assert_eq!(args(&config, "gzip"), ["on"]);
+        assert!(args(&config, "brotli").is_empty());
+        let nodes = parse_snippet(&config).unwrap();
+        let caching: Vec<String> = directives(&nodes)
+            .into_iter()
+            .filter(|directive| directive.name == "location" && directive.arg(0) == Some("~*"))
+            .map(|location| {
+                format!(
+                    "{} {}",
+                    location.args[1],
+                    directives(location.children())[0].args.join(" ")
+                )
+            })
+            .collect();
+        assert_eq!(
+            caching,
+            [
+                "\\.(css|js)$ 7d",
+                "\\.(png|jpg|jpeg|gif|svg|webp|ico)$ 30d",
+                "\\.(woff|woff2)$ 1y",
+                "\\.(html)$ -1",
+            ]
+        );
+
+        let config = site(&[("cache", ""), ("gzip", "off")], &context()).unwrap();
+        assert_eq!(args(&config, "try_files"), ["$uri $uri/ =404"]);
+        assert!(args(&config, "gzip").is_empty());
+        assert!(args(&config, "expires").is_empty());
+        assert_eq!(args(&config, "index"), ["index.html"]);
+        assert!(args(&config, "autoindex").is_empty());
+
+        assert!(site(&[("brotli", "on")], &context())
+            .unwrap_err()
+            .starts_with("Brotli needs the ngx_brotli module"));
+        let brotli = Context {
+            brotli: true,
+            ..context()
+        };
+        let config = site(&[("brotli", "on")], &brotli).unwrap();
+        assert_eq!(args(&config, "brotli_static"), ["on"]);
+
+        assert_eq!(
+            site(&[("cache", "css=soon")], &context()).unwrap_err(),
+            "Cache rule css=soon has an invalid time"
+        );
+        assert_eq!(
+            site(&[("cache", "*.css=1d")], &context()).unwrap_err(),
+            "Cache rule *.css=1d is not extensions=time"
+        );
+        fs::remove_dir_all(&root).unwrap();
+        assert!(site(&[], &context()).unwrap_err().starts_with("Root "));
+    }
+
+    #[test]
+    fn renders_tls_for_each_profile() {
+        for (profile, protocols) in [("intermediate", "TLSv1.2 TLSv1.3"), ("modern", "TLSv1.3")] {
+            let config = form("TLS", &[("profile", profile)])
+                .render(&context())
+                .unwrap();
+            assert_eq!(args(&config, "ssl_protocols"), [protocols], "{}", profile);
+            assert_eq!(
+                args(&config, "ssl_ciphers").len(),
+                usize::from(profile == "intermediate")
+            );
+            assert_eq!(
+                args(&config, "ssl_certificate"),
+                ["/etc/letsencrypt/live/example.com/fullchain.pem"]
+            );
+            assert_eq!(args(&config, "ssl_stapling"), ["on"]);
+            assert_eq!(args(&config, "resolver"), ["1.1.1.1 9.9.9.9"]);
+            assert_eq!(
+                args(&config, "add_header"),
+                ["Strict-Transport-Security max-age=63072000 always"]
+            );
+        }
+    }
+
+    #[test]
+    fn renders_tls_http2_and_redirect_only_when_on() {
+        let servers = |config: &str| -> Vec<Vec<String>> {
+            let nodes = parse_snippet(config).unwrap();
+            directives(&nodes)
+                .into_iter()
+                .filter(|directive| directive.name == "server" && directive.is_block())
+                .map(|server| {
+                    directives(server.children())
+                        .iter()
+                        .filter(|directive| matches!(directive.name.as_str(), "listen" | "return"))
+                        .map(|directive| directive.args.join(" "))
+                        .collect()
+                })
+                .collect()
+        };
+
+        let config = form("TLS", &[("port", "8443")]).render(&context()).unwrap();
+        assert_eq!(
+            servers(&config),
+            [
+                vec!["80", "[::]:80", "301 https://$host:8443$request_uri"],
+                vec!["8443 ssl", "[::]:8443 ssl"],
+            ]
+        );
+        assert_eq!(args(&config, "http2"), ["on"]);
+
+        // nginx before 1.25.1 turns HTTP/2 on with a listen parameter.
+        let old = Context {
+            http2_directive: false,
+            ..context()
+        };
+        let config = form(
+            "TLS",
+            &[("redirect", "off"), ("content", "http://127.0.0.1:3000")],
+        )
+        .render(&old)
+        .unwrap();
+        assert_eq!(
+            servers(&config),
+            [vec!["443 ssl http2", "[::]:443 ssl http2"]]
+        );
+        assert!(args(&config, "http2").is_empty());
+        assert_eq!(args(&config, "proxy_pass"), ["http://127.0.0.1:3000"]);
+
+        let config = form(
+            "TLS",
+            &[("hsts", "off"), ("stapling", "off"), ("resolver", "")],
+        )
+        .render(&context())
+        .unwrap();
+        assert_eq!(
+            servers(&config)[0],
+            ["80", "[::]:80", "301 https://$host$request_uri"]
+        );
+        assert!(args(&config, "add_header").is_empty());
+        assert!(args(&config, "ssl_stapling").is_empty());
+        assert_eq!(
+            form("TLS", &[("resolver", "")])
+                .render(&context())
+                .unwrap_err(),
+            "OCSP stapling needs a resolver"
+        );
+    }
+
+    #[test]
+    fn names_files_after_the_host_and_the_template() {
+        let form = |name: &str, server_name: &str| {
+            let template = TEMPLATES.iter().find(|t| t.name == name).unwrap();
+            let mut form = TemplateForm::new(template);
+            let i = template
+                .fields
+                .iter()
+                .position(|f| f.name == "server_name")
+                .unwrap();
+            form.values[i] = server_name.to_string();
+            form.file_name()
+        };
+        assert_eq!(
+            form("ACME certificate", "example.com www.example.com"),
+            "example.com-acme-certificate.conf"
+        );
+        assert_eq!(form("TLS", "example.com"), "example.com-tls.conf");
+        assert_eq!(form("Reverse proxy", "*.example.com"), "reverse-proxy.conf");
+    }
+
+    #[test]
+    fn reads_ports_of_listen_addresses() {
+        assert_eq!(listen_port("8080"), Some("8080"));
+        assert_eq!(listen_port("*:443"), Some("443"));
+        assert_eq!(listen_port("[::]:8443"), Some("8443"));
+        assert_eq!(listen_port("[::1]"), Some("80"));
+        assert_eq!(listen_port("127.0.0.1"), Some("80"));
+        assert_eq!(listen_port("unix:/run/nginx.sock"), None);
+    }
+
+    #[test]
+    fn basic_auth_adds_to_a_location_of_the_config() {
+        let dir = std::env::temp_dir().join(format!("lazynginx-template-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("nginx.conf");
+        let source = "http {
+    server {
+        listen 8080;
+        server_name app.example.com;
+        location / {
+            proxy_pass http://127.0.0.1:3000;
+        }
+        location ~ \\.php$ {
+            return 403;
+        }
+        location /private {
+            auth_basic \"Old\";
+        }
+    }
+}
+";
+        fs::write(&path, source).unwrap();
+        let context = Context::new(&Config::load(&path), None);
+        let paths: Vec<&str> = context.locations.iter().map(|l| l.path.as_str()).collect();
+        assert_eq!(paths, ["/", "/private"]);
+        assert_eq!(context.locations[0].ports, ["8080"]);
+        assert_eq!(context.locations[0].line, 7);
+        assert_eq!(&source[context.locations[0].end..][..1], "}");
+
+        let template = TEMPLATES.iter().find(|t| t.name == "Basic auth").unwrap();
+        let mut form = TemplateForm::new(template);
+        let mut set = |name: &str, value: &str| {
+            let i = template.fields.iter().position(|f| f.name == name).unwrap();
+            form.values[i] = value.to_string();
+        };
+        set("server_name", "app.example.com");
+        set("port", "8080");
+        set("target", EXISTING_LOCATION);
+        set("user_file", "/etc/nginx/app.htpasswd");
+        let location = form.existing_location(&context).unwrap().unwrap();
+        assert_eq!(location.path, "/");
+        let lines = form.render(&context).unwrap();
+        assert_eq!(
+            lines,
+            "# Generated by lazynginx from the Basic auth template.\n\
+             auth_basic \"Restricted\";\n\
+             auth_basic_user_file /etc/nginx/app.htpasswd;\n"
+        );
+        let edited = insert_in_block(source, location.end, &lines).unwrap();
+        assert!(edited.contains(
+            "            proxy_pass http://127.0.0.1:3000;\n            \
+             # Generated by lazynginx from the Basic auth template.\n            \
+             auth_basic \"Restricted\";\n"
+        ));
+
+        let i = template
+            .fields
+            .iter()
+            .position(|f| f.name == "location")
+            .unwrap();
+        form.values[i] = "/private".to_string();
+        let err = form.existing_location(&context).unwrap().unwrap_err();
+        assert!(err.contains("asks for a password already"), "{}", err);
+        form.values[i] = "/missing".to_string();
+        assert!(form.render(&context).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
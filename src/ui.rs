@@ -1,16 +1,37 @@
+use chrono::Utc;
 use tui::{
     backend::Backend,
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style, Stylize},
-    text::Line,
-    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Tabs},
+    text::{Line, Span},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, List, ListItem, Paragraph, Tabs, Wrap,
+    },
     Frame,
 };
 
 use crate::{
-    app::App,
-    logs::{access_log, error_log, log_locations_component},
-    version::get_nginx_version,
+    access_log::access_log_component,
+    app::{Action, App, ConfigFocus, TemplateFocus},
+    certificates::{certificate_component, certificates_component, expiry_warning},
+    command::command_output_component,
+    config_tree::{config_errors_component, config_source_component, config_tree_component},
+    dashboard::{requests_component, summary_component, top_component, Window},
+    error_log::error_log_component,
+    history::history_component,
+    htpasswd::htpasswd_component,
+    logs::{log_locations_component, LogKind},
+    modal::{centered_rect, modal_component},
+    prompt::prompt_component,
+    service::Job,
+    spool::SPOOL_LIMIT,
+    status::status_component,
+    stub_status::series_component,
+    tabs::{get_current_screen, Screen},
+    tail::log_view_component,
+    template::{form_component, preview_component, templates_component},
+    validate::{diagnostics_component, Level},
 };
 
 /// Renders the user interface widgets.
@@ -69,52 +90,243 @@ pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
 
     frame.render_widget(tabs, chunks[0]);
 
-    match app.horizontal_position {
-        0 => {
+    match get_current_screen(app) {
+        Screen::Status => {
+            let warning = expiry_warning(&app.certificates, app.expiry_warning, Utc::now());
+            let rows = Layout::default()
+                .constraints([
+                    Constraint::Length(if warning.is_some() { 3 } else { 0 }),
+                    Constraint::Min(0),
+                    Constraint::Length(if app.stub_status.is_some() { 12 } else { 0 }),
+                ])
+                .split(chunks[1]);
+            if let Some(warning) = warning {
+                frame.render_widget(
+                    Paragraph::new(warning).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded)
+                            .border_style(Style::default().fg(Color::Yellow)),
+                    ),
+                    rows[0],
+                );
+            }
             let chunks = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([Constraint::Ratio(1, 5), Constraint::Ratio(4, 5)])
-                .split(chunks[1]);
+                .constraints([Constraint::Ratio(1, 3), Constraint::Ratio(2, 3)])
+                .split(rows[1]);
 
+            let status_title = match app.status_poller.last_refresh {
+                Some(time) => format!("Status (refreshed {})", time.format("%H:%M:%S")),
+                None => "Status (refreshing…)".to_string(),
+            };
             let status_block = Block::default()
-                .title("Status")
+                .title(status_title)
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded);
-            frame.render_widget(
-                Paragraph::new(app.status.clone()).block(status_block),
-                chunks[0],
-            );
+            frame.render_widget(status_component(&app.status).block(status_block), chunks[0]);
 
-            // Create a list of systemctl commands
-            let commands: Vec<_> = vec![
-                "Start", "Stop", "Restart", "Reload", "Status", "Enable", "Disable",
-            ]
-            .into_iter()
-            .map(ListItem::new)
-            .collect();
+            // Create a list of service commands
+            let commands: Vec<_> = Action::ALL
+                .iter()
+                .map(|action| ListItem::new(action.label()))
+                .collect();
 
             let commands_list = List::new(commands)
                 .highlight_style(Style::default().fg(Color::Yellow))
                 .highlight_symbol(">>")
                 .block(
                     Block::default()
-                        .title("Systemctl Commands")
+                        .title(format!("Service Commands ({})", app.service_manager.name()))
                         .title_alignment(Alignment::Center)
                         .borders(Borders::ALL)
                         .border_type(BorderType::Rounded),
                 );
 
-            frame.render_stateful_widget(commands_list, chunks[1], &mut app.list_state);
+            let right = Layout::default()
+                .constraints([
+                    Constraint::Length(Action::ALL.len() as u16 + 2),
+                    Constraint::Min(0),
+                ])
+                .split(chunks[1]);
+
+            frame.render_stateful_widget(commands_list, right[0], &mut app.list_state);
+
+            let output_title = match app.command_runner.running {
+                Some(command) => format!("Output (running {}…)", command.as_str()),
+                None => "Output (PgUp/PgDn to scroll)".to_string(),
+            };
+            let output_block = Block::default()
+                .title(output_title)
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded);
+            match &app.command_output {
+                Some(result) => frame.render_widget(
+                    command_output_component(result)
+                        .block(output_block)
+                        .scroll((app.output_scroll, 0)),
+                    right[1],
+                ),
+                None => frame.render_widget(output_block, right[1]),
+            }
+
+            if let Some(stub_status) = &app.stub_status {
+                let charts = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+                    .split(rows[2]);
+                let block = |title: &'static str| {
+                    let footer = match &stub_status.error {
+                        Some(err) => Span::styled(err.clone(), Style::default().fg(Color::Red)),
+                        None => Span::styled(
+                            format!(
+                                "{} Host: {}",
+                                stub_status.endpoint.url(),
+                                stub_status.endpoint.host
+                            ),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                    };
+                    Block::default()
+                        .title(title)
+                        .title(Title::from(footer).position(Position::Bottom))
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                };
+                let span = stub_status.span();
+                let connections = stub_status.connections();
+                frame.render_widget(
+                    series_component(&connections, span).block(block("Connections")),
+                    charts[0],
+                );
+                let rates = stub_status.rates();
+                frame.render_widget(
+                    series_component(&rates, span)
+                        .block(block("Requests and connections per second")),
+                    charts[1],
+                );
+            }
         }
-        1 => {}
-        2 => {
+        Screen::Config => {
+            let errors = match &app.config {
+                Ok(config) => config.errors.len(),
+                Err(_) => 1,
+            };
+            let testing = app.command_runner.running == Some(Job::TestConfig);
+            let diagnostics = app
+                .config_test
+                .as_ref()
+                .map(|test| test.diagnostics.len().max(1))
+                .or(testing.then_some(1));
+            let rows = Layout::default()
+                .constraints([
+                    Constraint::Min(0),
+                    Constraint::Length(if errors == 0 {
+                        0
+                    } else {
+                        errors.min(4) as u16 + 2
+                    }),
+                    Constraint::Length(diagnostics.map_or(0, |len| len.min(6) as u16 + 2)),
+                ])
+                .split(chunks[1]);
             let chunks = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([Constraint::Min(27), Constraint::Ratio(4, 5)])
+                .constraints([Constraint::Ratio(2, 5), Constraint::Ratio(3, 5)])
+                .split(rows[0]);
+
+            let focus_style = |focus| {
+                if app.config_focus == focus {
+                    Style::default().fg(Color::LightBlue)
+                } else {
+                    Style::default()
+                }
+            };
+
+            frame.render_stateful_widget(
+                config_tree_component(&app.config_tree).block(
+                    Block::default()
+                        .title(app.config_path.to_string_lossy().into_owned())
+                        .title_alignment(Alignment::Center)
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(focus_style(ConfigFocus::Tree)),
+                ),
+                chunks[0],
+                &mut app.config_tree.list_state,
+            );
+
+            if let Ok(config) = &app.config {
+                // A diagnostic is shown in its whole file, a block on its own.
+                let source = match (app.config_tree.focus, app.config_tree.selected()) {
+                    (Some((file, line)), _) => Some((file, 1, usize::MAX, Some(line))),
+                    (None, Some(row)) => {
+                        Some((row.span.file, row.span.start_line, row.span.end_line, None))
+                    }
+                    (None, None) => None,
+                };
+                if let Some((file, start_line, end_line, highlight)) = source {
+                    let source_block = Block::default()
+                        .title(format!(
+                            "{}:{}",
+                            config.files[file].path.display(),
+                            highlight.unwrap_or(start_line)
+                        ))
+                        .title_alignment(Alignment::Center)
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded);
+                    // Keep the highlighted line in the middle of the pane.
+                    let scroll = highlight.map_or(0, |line| {
+                        line.saturating_sub(chunks[1].height as usize / 2) as u16
+                    });
+                    frame.render_widget(
+                        config_source_component(config, file, start_line, end_line, highlight)
+                            .block(source_block)
+                            .scroll((scroll, 0)),
+                        chunks[1],
+                    );
+                }
+            }
+
+            if errors > 0 {
+                frame.render_widget(
+                    config_errors_component(&app.config).block(
+                        Block::default()
+                            .title("Errors")
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    ),
+                    rows[1],
+                );
+            }
+
+            let diagnostics_block = Block::default()
+                .title(if testing {
+                    "nginx -t (running…)"
+                } else {
+                    "nginx -t (t to run again, Tab to switch pane)"
+                })
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(focus_style(ConfigFocus::Diagnostics));
+            match &app.config_test {
+                Some(test) => frame.render_stateful_widget(
+                    diagnostics_component(test).block(diagnostics_block),
+                    rows[2],
+                    &mut app.diagnostics_state,
+                ),
+                None if testing => frame.render_widget(diagnostics_block, rows[2]),
+                None => {}
+            }
+        }
+        Screen::Logs => {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
                 .split(chunks[1]);
 
             frame.render_stateful_widget(
-                log_locations_component().block(
+                log_locations_component(&app.log_locations).block(
                     Block::default()
                         .title("Pick Log File")
                         .title_alignment(Alignment::Center)
@@ -125,14 +337,420 @@ pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                 &mut app.log_list_state,
             );
 
-            if let Some(log_selection) = app.log_list_state.selected() {
-                match log_selection {
-                    0 => frame.render_widget(access_log(), chunks[1]), // Render access_log when selection is 0
-                    1 => frame.render_widget(error_log(), chunks[1]), // Render error_log when selection is 1
-                    _ => {} // Optional: Handle other cases if needed
+            let is_error_log = app
+                .selected_log()
+                .is_some_and(|log| log.kind == LogKind::Error);
+            if let Some(log_view) = app.log_view.as_mut() {
+                let mut state = if log_view.indexing() {
+                    format!("indexing {}%", log_view.indexed_percent())
+                } else if log_view.filtering() {
+                    format!("filtering {}%", log_view.filtered_percent())
+                } else if log_view.follow {
+                    "following".to_string()
+                } else if log_view.filtered() {
+                    format!("match {} of {}", log_view.top() + 1, log_view.rows())
+                } else {
+                    format!("line {} of {}", log_view.top() + 1, log_view.line_count)
+                };
+                let mut keys =
+                    "PgUp/PgDn j/k Home/End  : line  / search  n/N  f filter  t range".to_string();
+                if is_error_log {
+                    if app.error_level != Level::Debug {
+                        state.push_str(&format!(", {} and above", app.error_level));
+                    }
+                    keys.push_str("  l level");
+                }
+                if let Some(filter) = &app.log_filter {
+                    state.push_str(&format!(", filter {}", filter.input));
+                }
+                if log_view.spool_full() {
+                    state.push_str(&format!(
+                        ", cut at {} MB, narrow the time range",
+                        SPOOL_LIMIT / (1024 * 1024)
+                    ));
+                }
+                let mut log_block = Block::default()
+                    .title(format!("{} ({})", log_view.name, state))
+                    .title(
+                        Title::from(keys)
+                            .alignment(Alignment::Right)
+                            .position(Position::Bottom),
+                    )
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded);
+                if let Some(notice) = &app.log_notice {
+                    log_block = log_block.title(
+                        Title::from(Span::styled(
+                            notice.as_str(),
+                            Style::default().fg(Color::Red),
+                        ))
+                        .position(Position::Bottom),
+                    );
+                }
+                let mut area = log_block.inner(chunks[1]);
+                frame.render_widget(log_block, chunks[1]);
+
+                if let Some(prompt) = &app.prompt {
+                    area.height = area.height.saturating_sub(1);
+                    let prompt_area = Rect::new(area.x, area.y + area.height, area.width, 1);
+                    frame.render_widget(prompt_component(prompt), prompt_area);
+                }
+                match &app.log_format {
+                    _ if is_error_log && log_view.error.is_none() => {
+                        log_view.height = area.height.saturating_sub(1) as usize;
+                        log_view.load_window();
+                        frame.render_widget(
+                            error_log_component(log_view, app.search.as_ref()),
+                            area,
+                        );
+                    }
+                    Some(format) if log_view.error.is_none() => {
+                        // One line goes to the table header.
+                        log_view.height = area.height.saturating_sub(1) as usize;
+                        log_view.load_window();
+                        frame.render_widget(
+                            access_log_component(log_view, format, app.search.as_ref()),
+                            area,
+                        );
+                    }
+                    _ => {
+                        log_view.height = area.height as usize;
+                        log_view.load_window();
+                        frame
+                            .render_widget(log_view_component(log_view, app.search.as_ref()), area);
+                    }
+                }
+            } else if let Some(log) = app.selected_log() {
+                let reason = if log.target == "off" {
+                    "Logging is turned off here."
+                } else {
+                    "Not a file that can be read: syslog, stderr, a memory buffer or a path built from variables."
+                };
+                frame.render_widget(
+                    Paragraph::new(reason).wrap(Wrap { trim: false }).block(
+                        Block::default()
+                            .title(format!("{}_log {}", log.kind.as_str(), log.target))
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    ),
+                    chunks[1],
+                );
+            }
+        }
+        Screen::Certificates => {
+            let now = Utc::now();
+            let rows = Layout::default()
+                .constraints([Constraint::Min(0), Constraint::Length(14)])
+                .split(chunks[1]);
+            let block = |title: String| {
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+            };
+            if app.certificates.is_empty() {
+                frame.render_widget(
+                    Paragraph::new("The config names no ssl_certificate.")
+                        .block(block("Certificates".to_string())),
+                    chunks[1],
+                );
+            } else {
+                frame.render_stateful_widget(
+                    certificates_component(&app.certificates, app.expiry_warning, now).block(
+                        block(format!(
+                            "Certificates (highlighted within {} days of expiry)",
+                            app.expiry_warning
+                        ))
+                        .title(
+                            Title::from("r reread")
+                                .alignment(Alignment::Right)
+                                .position(Position::Bottom),
+                        ),
+                    ),
+                    rows[0],
+                    &mut app.certificate_state,
+                );
+                let selected = app.certificate_state.selected().unwrap_or(0);
+                if let Some(certificate) = app.certificates.get(selected) {
+                    frame.render_widget(
+                        certificate_component(certificate, app.expiry_warning, now)
+                            .block(block(certificate.name())),
+                        rows[1],
+                    );
+                }
+            }
+        }
+        Screen::History => {
+            let title = match &app.audit_error {
+                Some(err) => format!("History (audit log not written: {})", err),
+                None => "History".to_string(),
+            };
+            frame.render_stateful_widget(
+                history_component(&app.history).block(
+                    Block::default()
+                        .title(title)
+                        .title_alignment(Alignment::Center)
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                ),
+                chunks[1],
+                &mut app.history_state,
+            );
+        }
+        Screen::Dashboard => {
+            let block = |title: String| {
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+            };
+            match &app.dashboard {
+                None => frame.render_widget(
+                    Paragraph::new("No access log with a known log_format to read.")
+                        .block(block("Dashboard".to_string())),
+                    chunks[1],
+                ),
+                Some(dashboard) => match dashboard.error() {
+                    Some(err) => frame.render_widget(
+                        Paragraph::new(err).block(block(dashboard.name().to_string())),
+                        chunks[1],
+                    ),
+                    None => {
+                        let rows = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([Constraint::Length(8), Constraint::Min(0)])
+                            .split(chunks[1]);
+                        let columns = Layout::default()
+                            .direction(Direction::Horizontal)
+                            .constraints([
+                                Constraint::Length(30),
+                                Constraint::Ratio(2, 5),
+                                Constraint::Length(26),
+                                Constraint::Ratio(3, 5),
+                            ])
+                            .split(rows[1]);
+
+                        let windows: Vec<Span> = Window::ALL
+                            .iter()
+                            .map(|&window| {
+                                if window == dashboard.window {
+                                    Span::styled(
+                                        format!("[{}]", window.label()),
+                                        Style::default().fg(Color::LightBlue),
+                                    )
+                                } else {
+                                    Span::raw(format!(" {} ", window.label()))
+                                }
+                            })
+                            .collect();
+                        let requests_block = block(format!(
+                            "{}{}",
+                            dashboard.name(),
+                            if dashboard.loading() {
+                                " (reading…)"
+                            } else {
+                                ""
+                            }
+                        ))
+                        .title(Title::from(Line::from(windows)).alignment(Alignment::Right))
+                        .title(
+                            Title::from("w: change window")
+                                .alignment(Alignment::Right)
+                                .position(Position::Bottom),
+                        );
+                        let inner = requests_block.inner(rows[0]);
+                        let (series, interval) = dashboard.series(inner.width as usize);
+                        let peak = series.iter().max().copied().unwrap_or(0);
+                        let requests_block = requests_block.title(
+                            Title::from(format!("requests per {}s, peak {}", interval, peak))
+                                .position(Position::Bottom),
+                        );
+                        frame.render_widget(
+                            requests_component(&series).block(requests_block),
+                            rows[0],
+                        );
+
+                        let stats = dashboard.stats();
+                        frame.render_widget(
+                            summary_component(&stats, dashboard.window)
+                                .block(block("Summary".to_string())),
+                            columns[0],
+                        );
+                        for (values, title, area) in [
+                            (&stats.top_paths, "Top paths", columns[1]),
+                            (&stats.top_clients, "Top clients", columns[2]),
+                            (&stats.top_agents, "Top user agents", columns[3]),
+                        ] {
+                            frame.render_widget(
+                                top_component(values).block(block(title.to_string())),
+                                area,
+                            );
+                        }
+                    }
+                },
+            }
+        }
+        Screen::Template => {
+            app.refresh_template_preview();
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Ratio(2, 5), Constraint::Ratio(3, 5)])
+                .split(chunks[1]);
+            let left = Layout::default()
+                .constraints([
+                    Constraint::Length(app.templates.len() as u16 + 2),
+                    Constraint::Min(0),
+                    Constraint::Length(6),
+                ])
+                .split(columns[0]);
+            let focus_style = |focus| {
+                if app.template_focus == focus {
+                    Style::default().fg(Color::LightBlue)
+                } else {
+                    Style::default()
                 }
+            };
+
+            frame.render_stateful_widget(
+                templates_component(&app.templates).block(
+                    Block::default()
+                        .title("Templates")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(focus_style(TemplateFocus::List)),
+                ),
+                left[0],
+                &mut app.template_state,
+            );
+
+            let right = Layout::default()
+                .constraints(if app.htpasswd.is_some() {
+                    [Constraint::Min(0), Constraint::Length(10)]
+                } else {
+                    [Constraint::Min(0), Constraint::Length(0)]
+                })
+                .split(columns[1]);
+
+            let target = app.template_target();
+            let i = app.selected_template();
+            let form = &mut app.templates[i];
+            let form_block = Block::default()
+                .title(form.template.name)
+                .title(
+                    Title::from(if form.value("directory").is_some() {
+                        "Tab focus  Enter edit  w write  i issue"
+                    } else {
+                        "Tab focus  Enter edit  w write"
+                    })
+                    .alignment(Alignment::Right)
+                    .position(Position::Bottom),
+                )
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(focus_style(TemplateFocus::Form));
+            let form_area = form_block.inner(left[1]);
+            frame.render_widget(form_block, left[1]);
+            if app.template_focus == TemplateFocus::Form {
+                frame.render_stateful_widget(form_component(form), form_area, &mut form.state);
+            } else {
+                frame.render_widget(form_component(form), form_area);
+            }
+            if let Some(prompt) = &app.prompt {
+                let line = Rect {
+                    y: form_area.bottom().saturating_sub(1),
+                    height: 1.min(form_area.height),
+                    ..form_area
+                };
+                frame.render_widget(Clear, line);
+                frame.render_widget(prompt_component(prompt), line);
+            }
+
+            let mut help = vec![Line::from(match app.template_focus {
+                TemplateFocus::List => form.template.description,
+                TemplateFocus::Form => form.selected_field().help,
+                TemplateFocus::Users => "Users of the user file. Passwords are hashed with the scheme picked in the form.",
+            })];
+            match &app.template_notice {
+                Some(Ok(notice)) => help.push(Line::styled(
+                    notice.clone(),
+                    Style::default().fg(Color::Green),
+                )),
+                Some(Err(notice)) => help.push(Line::styled(
+                    notice.clone(),
+                    Style::default().fg(Color::Red),
+                )),
+                None => {}
+            }
+            frame.render_widget(
+                Paragraph::new(help).wrap(Wrap { trim: false }).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                ),
+                left[2],
+            );
+
+            let preview = match &app.template_preview {
+                Some((_, _, preview)) => preview,
+                None => &Ok(String::new()),
+            };
+            frame.render_widget(
+                preview_component(preview)
+                    .scroll((app.preview_scroll, 0))
+                    .block(
+                        Block::default()
+                            .title(format!("Preview of {}", target))
+                            .title(
+                                Title::from("PgUp/PgDn scroll")
+                                    .alignment(Alignment::Right)
+                                    .position(Position::Bottom),
+                            )
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    ),
+                right[0],
+            );
+
+            if let Some(htpasswd) = &app.htpasswd {
+                let title = if htpasswd.path.exists() {
+                    format!("Users in {}", htpasswd.path.display())
+                } else {
+                    format!("Users in {} (new)", htpasswd.path.display())
+                };
+                frame.render_stateful_widget(
+                    htpasswd_component(htpasswd).block(
+                        Block::default()
+                            .title(title)
+                            .title(
+                                Title::from("a add  p password  d remove")
+                                    .alignment(Alignment::Right)
+                                    .position(Position::Bottom),
+                            )
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded)
+                            .border_style(focus_style(TemplateFocus::Users)),
+                    ),
+                    right[1],
+                    &mut app.htpasswd_state,
+                );
             }
         }
-        _ => {}
+        Screen::Unknown => {}
+    }
+
+    if let Some(modal) = &app.modal {
+        let area = centered_rect(70, 50, frame.size());
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            modal_component(modal).block(
+                Block::default()
+                    .title(modal.title())
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(Color::Red)),
+            ),
+            area,
+        );
     }
 }
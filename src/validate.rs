@@ -0,0 +1,218 @@
+use std::{
+    ffi::OsStr,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+use tui::{
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{List, ListItem},
+};
+
+use crate::command::{run_command, CommandResult};
+
+/// nginx log levels, most severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Emerg,
+    Alert,
+    Crit,
+    Error,
+    Warn,
+    Notice,
+    Info,
+    Debug,
+}
+
+impl Level {
+    pub const ALL: [Level; 8] = [
+        Level::Emerg,
+        Level::Alert,
+        Level::Crit,
+        Level::Error,
+        Level::Warn,
+        Level::Notice,
+        Level::Info,
+        Level::Debug,
+    ];
+
+    pub fn parse(level: &str) -> Option<Level> {
+        match level {
+            "emerg" => Some(Level::Emerg),
+            "alert" => Some(Level::Alert),
+            "crit" => Some(Level::Crit),
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "notice" => Some(Level::Notice),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Level::Emerg => "emerg",
+            Level::Alert => "alert",
+            Level::Crit => "crit",
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Notice => "notice",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match *self {
+            Level::Emerg | Level::Alert | Level::Crit => Color::LightRed,
+            Level::Error => Color::Red,
+            Level::Warn => Color::Yellow,
+            Level::Notice | Level::Info => Color::Gray,
+            Level::Debug => Color::DarkGray,
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A problem reported by `nginx -t`.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+}
+
+/// Result of running `nginx -t`.
+#[derive(Debug, Clone)]
+pub struct ConfigTest {
+    pub success: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    pub result: CommandResult,
+}
+
+/// Runs `nginx -t -c <config_path>`.
+pub fn test_config(config_path: &Path) -> ConfigTest {
+    let result = run_command(
+        "nginx",
+        &[OsStr::new("-t"), OsStr::new("-c"), config_path.as_os_str()],
+    );
+
+    ConfigTest {
+        success: result.success(),
+        // nginx reports on stderr, stdout is only used by `-T`.
+        diagnostics: parse_diagnostics(&result.stderr),
+        result,
+    }
+}
+
+/// Extracts the `nginx: [level] message in file:line` lines of `nginx -t`
+/// output.
+pub fn parse_diagnostics(output: &str) -> Vec<Diagnostic> {
+    let re = Regex::new(r"^nginx: \[(\w+)\] (.*?)(?: in (\S+):(\d+))?$").unwrap();
+
+    output
+        .lines()
+        .filter_map(|line| {
+            let cap = re.captures(line.trim_end())?;
+            Some(Diagnostic {
+                level: Level::parse(&cap[1])?,
+                message: cap[2].to_string(),
+                file: cap.get(3).map(|m| PathBuf::from(m.as_str())),
+                line: cap.get(4).and_then(|m| m.as_str().parse().ok()),
+            })
+        })
+        .collect()
+}
+
+pub fn diagnostics_component(test: &ConfigTest) -> List<'static> {
+    let mut items: Vec<_> = test
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let mut spans = vec![
+                Span::styled(
+                    format!("[{}] ", diagnostic.level),
+                    Style::default().fg(diagnostic.level.color()),
+                ),
+                Span::raw(diagnostic.message.clone()),
+            ];
+            if let (Some(file), Some(line)) = (&diagnostic.file, diagnostic.line) {
+                spans.push(Span::styled(
+                    format!("  {}:{}", file.display(), line),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    if items.is_empty() {
+        items.push(ListItem::new(if test.success {
+            "Configuration test successful"
+        } else {
+            "Configuration test failed"
+        }));
+    }
+
+    List::new(items).highlight_symbol(">>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nginx_t_output() {
+        let output = "\
+nginx: [warn] the \"listen ... http2\" directive is deprecated, use the \"http2\" directive instead in /etc/nginx/sites-enabled/app.conf:3
+nginx: [emerg] unknown directive \"proxy_pas\" in /etc/nginx/sites-enabled/app.conf:12
+nginx: [alert] could not open error log file: open() \"/var/log/nginx/error.log\" failed (13: Permission denied)
+nginx: configuration file /etc/nginx/nginx.conf test failed
+";
+        let diagnostics = parse_diagnostics(output);
+        assert_eq!(diagnostics.len(), 3);
+
+        assert_eq!(diagnostics[0].level, Level::Warn);
+        assert_eq!(
+            diagnostics[0].message,
+            "the \"listen ... http2\" directive is deprecated, use the \"http2\" directive instead"
+        );
+        assert_eq!(
+            diagnostics[0].file.as_deref(),
+            Some(Path::new("/etc/nginx/sites-enabled/app.conf"))
+        );
+        assert_eq!(diagnostics[0].line, Some(3));
+
+        assert_eq!(diagnostics[1].level, Level::Emerg);
+        assert_eq!(diagnostics[1].message, "unknown directive \"proxy_pas\"");
+        assert_eq!(diagnostics[1].line, Some(12));
+
+        // No location, the path in the message is not one.
+        assert_eq!(diagnostics[2].level, Level::Alert);
+        assert_eq!(
+            diagnostics[2].message,
+            "could not open error log file: open() \"/var/log/nginx/error.log\" failed (13: \
+             Permission denied)"
+        );
+        assert_eq!(diagnostics[2].file, None);
+        assert_eq!(diagnostics[2].line, None);
+    }
+
+    #[test]
+    fn ignores_other_lines() {
+        let output = "nginx: the configuration file /etc/nginx/nginx.conf syntax is ok\r\n\
+                      nginx: configuration file /etc/nginx/nginx.conf test is successful\n\
+                      nginx: [bogus] not a level\n";
+        assert!(parse_diagnostics(output).is_empty());
+        assert!(parse_diagnostics("").is_empty());
+    }
+}
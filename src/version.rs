@@ -14,6 +14,12 @@ impl Display for NginxVersion {
     }
 }
 
+impl NginxVersion {
+    pub fn at_least(&self, major: u8, minor: u8, patch: u8) -> bool {
+        (self.major, self.minor, self.patch) >= (major, minor, patch)
+    }
+}
+
 pub fn get_nginx_version() -> Option<NginxVersion> {
     let output_result = Command::new("nginx").arg("-v").output();
 
@@ -39,3 +45,22 @@ pub fn get_nginx_version() -> Option<NginxVersion> {
         })
     })
 }
+
+/// Value of a `--name=value` argument nginx was configured with, as reported
+/// by `nginx -V`.
+pub fn get_configure_argument(name: &str) -> Option<String> {
+    let output = Command::new("nginx").arg("-V").output().ok()?;
+    let build_info = String::from_utf8_lossy(&output.stderr);
+    let re = Regex::new(&format!(r"--{}=(\S+)", regex::escape(name))).unwrap();
+
+    re.captures(&build_info).map(|cap| cap[1].to_string())
+}
+
+/// Whether `nginx -V` mentions `name`, as it does for modules added with
+/// `--add-module` or `--add-dynamic-module`.
+pub fn built_with_module(name: &str) -> bool {
+    Command::new("nginx")
+        .arg("-V")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stderr).contains(name))
+}